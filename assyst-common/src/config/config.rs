@@ -5,6 +5,19 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 pub struct AssystConfig {
     pub bot_id: u64,
+    #[serde(default)]
+    pub image_source_precedence: ImageSourcePrecedence,
+    /// Read through [`AssystConfig::image_history_scan_limit`], which clamps it.
+    #[serde(default = "default_image_history_scan_limit")]
+    image_history_scan_limit: u16,
+    #[serde(default = "default_max_reply_pages")]
+    pub max_reply_pages: usize,
+    #[serde(default = "default_max_concurrent_downloads")]
+    pub max_concurrent_downloads: usize,
+    #[serde(default = "default_argument_parse_timeout_secs")]
+    pub argument_parse_timeout_secs: u64,
+    #[serde(default)]
+    pub rate_tracker_logging: RateTrackerLogging,
     pub urls: Urls,
     pub authentication: Authentication,
     pub database: Database,
@@ -14,6 +27,52 @@ pub struct AssystConfig {
     pub entitlements: Entitlements,
 }
 
+impl AssystConfig {
+    /// How many recent messages to scan for an image, between 1 and 100 (the most Discord returns
+    /// in one request) whatever the configured value.
+    pub fn image_history_scan_limit(&self) -> u16 {
+        self.image_history_scan_limit.clamp(1, 100)
+    }
+}
+
+fn default_image_history_scan_limit() -> u16 {
+    20
+}
+
+fn default_max_reply_pages() -> usize {
+    3
+}
+
+fn default_max_concurrent_downloads() -> usize {
+    8
+}
+
+fn default_argument_parse_timeout_secs() -> u64 {
+    60
+}
+
+/// Which image source wins when a command invocation both has an attachment and replies to a
+/// message containing an image.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSourcePrecedence {
+    #[default]
+    Attachment,
+    Reply,
+}
+
+/// What the periodic rate tracker summary does besides logging.
+#[derive(Deserialize, Default)]
+pub struct RateTrackerLogging {
+    /// Store each summary in the database, for historical graphs.
+    #[serde(default)]
+    pub persist: bool,
+    /// Clear the logged trackers after each summary, so that each one only covers the time since
+    /// the last.
+    #[serde(default)]
+    pub reset_after_logging: bool,
+}
+
 #[derive(Deserialize)]
 pub struct Entitlements {
     pub premium_server_sku_id: u64,
@@ -46,6 +105,8 @@ pub struct Authentication {
     pub filer_key: String,
     pub notsoapi: String,
     pub rapidapi_token: String,
+    #[serde(default)]
+    pub tenor_api_key: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -87,6 +148,9 @@ impl Database {
 #[derive(Deserialize)]
 pub struct Prefixes {
     pub default: String,
+    /// Prefixes accepted everywhere, alongside the guild's own prefix.
+    #[serde(default)]
+    pub additional: Vec<String>,
 }
 
 #[derive(Deserialize)]
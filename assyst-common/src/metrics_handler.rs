@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
 
 use assyst_database::DatabaseHandler;
@@ -7,19 +6,19 @@ use prometheus::{register_int_counter, register_int_gauge_vec, IntCounter, IntGa
 use tracing::debug;
 
 use crate::util::process::get_processes_mem_usage;
-use crate::util::rate_tracker::RateTracker;
+use crate::util::rate_tracker::{KeyedRateTracker, SharedRateTracker};
 
 /// Handler for general metrics, including rate trackers, Prometheus metrics, etc.
 pub struct MetricsHandler {
     pub cache_sizes: IntGaugeVec,
     pub memory_usage: IntGaugeVec,
     pub guilds: IntGaugeVec,
-    pub guilds_rate_tracker: Mutex<RateTracker>,
+    pub guilds_rate_tracker: SharedRateTracker,
     pub events: IntCounter,
-    pub events_rate_tracker: Mutex<RateTracker>,
+    pub events_rate_tracker: SharedRateTracker,
     pub commands: IntCounter,
-    pub total_commands_rate_tracker: Mutex<RateTracker>,
-    pub individual_commands_rate_trackers: tokio::sync::Mutex<HashMap<&'static str /* command name */, RateTracker>>,
+    pub total_commands_rate_tracker: SharedRateTracker,
+    pub individual_commands_rate_trackers: tokio::sync::Mutex<KeyedRateTracker<&'static str /* command name */>>,
     pub database_handler: Arc<DatabaseHandler>,
 }
 impl MetricsHandler {
@@ -28,12 +27,14 @@ impl MetricsHandler {
             cache_sizes: register_int_gauge_vec!("cache_sizes", "Cache sizes", &["cache"])?,
             memory_usage: register_int_gauge_vec!("memory_usage", "Memory usage in MB", &["process"])?,
             guilds: register_int_gauge_vec!("guilds", "Total guilds and user installs", &["context"])?,
-            guilds_rate_tracker: Mutex::new(RateTracker::new(Duration::from_secs(60 * 60))),
+            guilds_rate_tracker: SharedRateTracker::new(Duration::from_secs(60 * 60)),
             events: register_int_counter!("events", "Total number of events")?,
-            events_rate_tracker: Mutex::new(RateTracker::new(Duration::from_secs(1))),
+            events_rate_tracker: SharedRateTracker::new(Duration::from_secs(1)),
             commands: register_int_counter!("commands", "Total number of commands executed")?,
-            total_commands_rate_tracker: Mutex::new(RateTracker::new(Duration::from_secs(60))),
-            individual_commands_rate_trackers: tokio::sync::Mutex::new(HashMap::new()),
+            total_commands_rate_tracker: SharedRateTracker::new(Duration::from_secs(60)),
+            individual_commands_rate_trackers: tokio::sync::Mutex::new(KeyedRateTracker::new(Duration::from_secs(
+                60 * 60,
+            ))),
             database_handler,
         })
     }
@@ -74,42 +75,37 @@ impl MetricsHandler {
     }
 
     pub fn inc_guilds(&self) {
-        self.guilds_rate_tracker.lock().unwrap().add_sample();
+        self.guilds_rate_tracker.add_sample();
         self.guilds.with_label_values(&["guilds"]).inc();
     }
 
     pub fn dec_guilds(&self) {
-        self.guilds_rate_tracker.lock().unwrap().remove_sample();
+        self.guilds_rate_tracker.remove_sample();
         self.guilds.with_label_values(&["guilds"]).dec();
     }
 
     pub fn add_event(&self) {
         self.events.inc();
-        self.events_rate_tracker.lock().unwrap().add_sample();
+        self.events_rate_tracker.add_sample();
     }
 
     pub fn get_events_rate(&self) -> usize {
-        self.events_rate_tracker.lock().unwrap().get_rate()
+        self.events_rate_tracker.get_rate()
     }
 
     pub fn add_command(&self) {
         self.commands.inc();
-        self.total_commands_rate_tracker.lock().unwrap().add_sample();
+        self.total_commands_rate_tracker.add_sample();
     }
 
     pub fn get_commands_rate(&self) -> usize {
-        self.total_commands_rate_tracker.lock().unwrap().get_rate()
+        self.total_commands_rate_tracker.get_rate()
     }
 
     pub async fn add_individual_command_usage(&self, command_name: &'static str) {
-        let mut lock = self.individual_commands_rate_trackers.lock().await;
-        let entry = lock.get_mut(&command_name);
-        if let Some(entry) = entry {
-            entry.add_sample();
-        } else {
-            let mut tracker = RateTracker::new(Duration::from_secs(60 * 60));
-            tracker.add_sample();
-            lock.insert(command_name, tracker);
-        }
+        self.individual_commands_rate_trackers
+            .lock()
+            .await
+            .add_sample(command_name);
     }
 }
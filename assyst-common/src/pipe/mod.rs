@@ -14,6 +14,8 @@ use crate::util::string_from_likely_utf8;
 pub mod pipe_server;
 
 pub static GATEWAY_PIPE_PATH: &str = "/tmp/assyst2-gateway-com";
+/// Gateway commands (such as guild member requests) sent from assyst-core to assyst-gateway.
+pub static GATEWAY_COMMAND_PIPE_PATH: &str = "/tmp/assyst2-gateway-cmd";
 pub static CACHE_PIPE_PATH: &str = "/tmp/assyst2-cache-com";
 
 static POLL_FREQUENCY: Duration = Duration::from_secs(10);
@@ -0,0 +1,316 @@
+/// CSS named colours, sorted by name, as packed `0xRRGGBB`.
+const CSS_NAMED_COLOURS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[derive(Debug)]
+pub enum ParseColourError {
+    InvalidHex(String),
+    InvalidRgb(String),
+    UnknownName(String),
+    InvalidAlpha(String),
+}
+
+impl std::fmt::Display for ParseColourError {
+    #[rustfmt::skip]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseColourError::InvalidHex(input) => write!(f, "{input} is not a valid hex colour (expected 6 hex digits, e.g. #ff0000)"),
+            ParseColourError::InvalidRgb(input) => write!(f, "{input} is not a valid rgb colour (expected rgb(r,g,b) with each value from 0 to 255)"),
+            ParseColourError::UnknownName(input) => write!(f, "{input} is not a known colour name or code (try a hex code like #ff0000)"),
+            ParseColourError::InvalidAlpha(input) => write!(f, "{input} is not a valid alpha value (expected a whole number from 0 to 255, or a decimal from 0.0 to 1.0)"),
+        }
+    }
+}
+
+impl std::error::Error for ParseColourError {}
+
+/// Looks up a CSS named colour (case insensitive).
+#[must_use]
+pub fn named_colour(name: &str) -> Option<u32> {
+    let name = name.to_ascii_lowercase();
+    CSS_NAMED_COLOURS
+        .binary_search_by(|(n, _)| (*n).cmp(&name[..]))
+        .ok()
+        .map(|i| CSS_NAMED_COLOURS[i].1)
+}
+
+/// Parses a colour as `#RRGGBB`, `RRGGBB`, `0xRRGGBB`, `rgb(r,g,b)` or a CSS colour name to packed
+/// `0xRRGGBB`.
+pub fn parse_colour(input: &str) -> Result<u32, ParseColourError> {
+    let input = input.trim();
+    let lower = input.to_ascii_lowercase();
+
+    if let Some(inner) = lower.strip_prefix("rgb(").and_then(|x| x.strip_suffix(')')) {
+        let channels = inner
+            .split(',')
+            .map(|c| c.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ParseColourError::InvalidRgb(input.to_owned()))?;
+
+        let [r, g, b] = channels[..] else {
+            return Err(ParseColourError::InvalidRgb(input.to_owned()));
+        };
+
+        return Ok(u32::from_be_bytes([0, r, g, b]));
+    }
+
+    if let Some(colour) = named_colour(&lower) {
+        return Ok(colour);
+    }
+
+    let (hex, prefixed) = match lower.strip_prefix('#').or_else(|| lower.strip_prefix("0x")) {
+        Some(hex) => (hex, true),
+        None => (&lower[..], false),
+    };
+
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        // checked above that this is valid
+        Ok(u32::from_str_radix(hex, 16).unwrap())
+    } else if prefixed {
+        Err(ParseColourError::InvalidHex(input.to_owned()))
+    } else {
+        Err(ParseColourError::UnknownName(input.to_owned()))
+    }
+}
+
+/// Parses an alpha value, either as a whole number from 0 to 255 or as a decimal opacity from 0.0
+/// to 1.0 (which must contain a `.`, so that `1` is not ambiguous).
+fn parse_alpha(input: &str) -> Option<u8> {
+    let input = input.trim();
+
+    if input.contains('.') {
+        let opacity = input.parse::<f64>().ok()?;
+        (0.0..=1.0).contains(&opacity).then(|| (opacity * 255.0).round() as u8)
+    } else {
+        input.parse::<u8>().ok()
+    }
+}
+
+/// Parses a colour with an alpha channel to packed `0xRRGGBBAA`. Accepts `#RRGGBBAA`, `RRGGBBAA`,
+/// `0xRRGGBBAA` and `rgba(r,g,b,a)`, as well as anything accepted by [`parse_colour`], optionally
+/// followed by `@alpha` (e.g. `red@0.5`). Colours without an alpha channel are fully opaque.
+pub fn parse_colour_alpha(input: &str) -> Result<u32, ParseColourError> {
+    let input = input.trim();
+    let lower = input.to_ascii_lowercase();
+
+    if let Some(inner) = lower.strip_prefix("rgba(").and_then(|x| x.strip_suffix(')')) {
+        let channels = inner.split(',').collect::<Vec<_>>();
+
+        let [r, g, b, a] = channels[..] else {
+            return Err(ParseColourError::InvalidRgb(input.to_owned()));
+        };
+
+        let rgb =
+            parse_colour(&format!("rgb({r},{g},{b})")).map_err(|_| ParseColourError::InvalidRgb(input.to_owned()))?;
+        let alpha = parse_alpha(a).ok_or_else(|| ParseColourError::InvalidAlpha(a.trim().to_owned()))?;
+
+        return Ok((rgb << 8) | u32::from(alpha));
+    }
+
+    if let Some((colour, alpha)) = input.rsplit_once('@') {
+        let rgb = parse_colour(colour)?;
+        let alpha = parse_alpha(alpha).ok_or_else(|| ParseColourError::InvalidAlpha(alpha.trim().to_owned()))?;
+
+        return Ok((rgb << 8) | u32::from(alpha));
+    }
+
+    let hex = lower
+        .strip_prefix('#')
+        .or_else(|| lower.strip_prefix("0x"))
+        .unwrap_or(&lower);
+
+    if hex.len() == 8 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        // checked above that this is valid
+        return Ok(u32::from_str_radix(hex, 16).unwrap());
+    }
+
+    Ok((parse_colour(input)? << 8) | 0xff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_colours_are_sorted_for_lookup() {
+        assert!(CSS_NAMED_COLOURS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn colours_parse_in_every_form() {
+        for input in [
+            "#ff6347",
+            "FF6347",
+            "0xff6347",
+            "rgb(255, 99, 71)",
+            "RGB(255,99,71)",
+            "Tomato",
+            " tomato ",
+        ] {
+            assert_eq!(parse_colour(input).unwrap(), 0xff6347, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_colours_are_rejected() {
+        assert!(matches!(parse_colour("#ff63"), Err(ParseColourError::InvalidHex(_))));
+        assert!(matches!(parse_colour("0xgggggg"), Err(ParseColourError::InvalidHex(_))));
+        assert!(matches!(
+            parse_colour("rgb(256,0,0)"),
+            Err(ParseColourError::InvalidRgb(_))
+        ));
+        assert!(matches!(parse_colour("rgb(1,2)"), Err(ParseColourError::InvalidRgb(_))));
+        assert!(matches!(
+            parse_colour("notacolour"),
+            Err(ParseColourError::UnknownName(_))
+        ));
+    }
+}
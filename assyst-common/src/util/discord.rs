@@ -21,8 +21,9 @@ pub async fn get_guild_owner(http: &Client, guild_id: u64) -> anyhow::Result<u64
         .get())
 }
 
+/// The URL of the default avatar Discord shows for a user without a custom one. Users on the new
+/// username system have a discriminator of 0, and their avatar index comes from their ID instead.
 #[must_use] pub fn get_default_avatar_url(user: &User) -> String {
-    // Unwrapping discrim parsing is ok, it should never be out of range or non-numeric
     let suffix = if user.discriminator == 0 {
         // Pomelo users
         (user.id.get().wrapping_shr(22) % 6) as u16
@@ -7,6 +7,7 @@ pub enum Type {
     JPEG,
     PNG,
     WEBP,
+    AVIF,
     MP4,
     WEBM,
     MP3,
@@ -19,6 +20,7 @@ impl Type {
             Type::JPEG => "jpeg",
             Type::PNG => "png",
             Type::WEBP => "webp",
+            Type::AVIF => "avif",
             Type::MP4 => "mp4",
             Type::WEBM => "webm",
             Type::MP3 => "mp3",
@@ -31,6 +33,7 @@ impl Type {
             Type::JPEG => "image/jpeg",
             Type::PNG => "image/png",
             Type::WEBP => "image/webp",
+            Type::AVIF => "image/avif",
             Type::MP4 => "video/mp4",
             Type::WEBM => "video/webm",
             Type::MP3 => "audio/mpeg",
@@ -44,6 +47,18 @@ impl Type {
 
 const WEBP: [u8; 4] = [87, 69, 66, 80];
 const MP4: [u8; 4] = [0x66, 0x74, 0x79, 0x70];
+/// `ftyp` major brand of a still AVIF image.
+const AVIF: [u8; 4] = *b"avif";
+/// `ftyp` major brand of an AVIF image sequence.
+const AVIS: [u8; 4] = *b"avis";
+/// Extended WebP header chunk, which holds the animation flag.
+const WEBP_VP8X: [u8; 4] = *b"VP8X";
+/// Bit in the VP8X flags byte that is set for animated WebPs (which then have an `ANIM` chunk).
+const WEBP_ANIMATION_FLAG: u8 = 0x02;
+/// Introducer of a GIF extension block, such as the graphic control or looping extension.
+const GIF_EXTENSION: u8 = 0x21;
+/// Introducer of a GIF image descriptor, which starts each frame.
+const GIF_IMAGE_DESCRIPTOR: u8 = 0x2C;
 
 fn bounded_range(start: usize, end: usize, len: usize) -> Range<usize> {
     min(len, start)..min(len, end)
@@ -58,6 +73,63 @@ fn check_webp(that: &[u8]) -> bool {
     sig(bytes_offset_removed, &WEBP)
 }
 
+fn ftyp_brand(that: &[u8]) -> Option<&[u8]> {
+    check_mp4(that).then(|| &that[bounded_range(8, 12, that.len())])
+}
+
+fn check_avif(that: &[u8]) -> bool {
+    ftyp_brand(that).is_some_and(|brand| brand == AVIF || brand == AVIS)
+}
+
+/// Length of the colour table following a GIF screen or image descriptor with the given flags byte.
+fn gif_colour_table_len(flags: u8) -> usize {
+    if flags & 0x80 != 0 {
+        3 << ((flags & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// Skips the GIF data sub-blocks starting at `pos`, returning the position after the terminator.
+fn skip_gif_sub_blocks(buf: &[u8], mut pos: usize) -> usize {
+    while let Some(&len) = buf.get(pos) {
+        pos += 1 + len as usize;
+        if len == 0 {
+            break;
+        }
+    }
+    pos
+}
+
+/// Counts the frames in a GIF, stopping once `limit` is reached. Truncated or malformed data ends
+/// the count at the frames read so far.
+fn gif_frame_count(buf: &[u8], limit: usize) -> usize {
+    // the header and logical screen descriptor are 13 bytes, followed by the global colour table
+    let Some(&flags) = buf.get(10) else {
+        return 0;
+    };
+    let mut pos = 13 + gif_colour_table_len(flags);
+    let mut frames = 0;
+
+    while frames < limit {
+        match buf.get(pos) {
+            // introducer and label, then data sub-blocks
+            Some(&GIF_EXTENSION) => pos = skip_gif_sub_blocks(buf, pos + 2),
+            // 10 byte descriptor, local colour table, LZW minimum code size, then data sub-blocks
+            Some(&GIF_IMAGE_DESCRIPTOR) => {
+                let Some(&flags) = buf.get(pos + 9) else {
+                    break;
+                };
+                frames += 1;
+                pos = skip_gif_sub_blocks(buf, pos + 10 + gif_colour_table_len(flags) + 1);
+            },
+            _ => break,
+        }
+    }
+
+    frames
+}
+
 fn check_mp4(that: &[u8]) -> bool {
     let bytes_offset_removed = &that[bounded_range(4, 8, that.len())];
     sig(bytes_offset_removed, &MP4)
@@ -72,7 +144,127 @@ fn check_mp4(that: &[u8]) -> bool {
         [0x49, 0x44, 0x33, ..] /* ID3 tagged */ | [0xff, 0xfb, ..] /* untagged */ => Some(Type::MP3),
         [0x50, 0x4b, ..] => Some(Type::ZIP),
         _ if check_webp(buf) => Some(Type::WEBP),
+        // AVIF shares its container with MP4, so must be checked first
+        _ if check_avif(buf) => Some(Type::AVIF),
         _ if check_mp4(buf) => Some(Type::MP4),
         _ => None,
     }
 }
+
+/// Whether the image in `buf` is animated. Only GIF, WebP and AVIF are checked; any other format
+/// is assumed to be static.
+///
+/// WebPs are animated if the VP8X header has the animation flag set, and AVIFs if they are image
+/// sequences (`avis` brand). GIFs are animated if they have more than one frame.
+#[must_use]
+pub fn is_animated(buf: &[u8]) -> bool {
+    match get_sig(buf) {
+        Some(Type::GIF) => gif_frame_count(buf, 2) == 2,
+        Some(Type::WEBP) => {
+            sig(&buf[bounded_range(12, 16, buf.len())], &WEBP_VP8X)
+                && buf.get(20).is_some_and(|flags| flags & WEBP_ANIMATION_FLAG != 0)
+        },
+        Some(Type::AVIF) => ftyp_brand(buf).is_some_and(|brand| brand == AVIS),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 GIF with a two colour global colour table and `frames` frames, optionally with the
+    /// looping extension.
+    fn gif(frames: usize, looping: bool) -> Vec<u8> {
+        let mut buf = b"GIF89a".to_vec();
+        buf.extend_from_slice(&[1, 0, 1, 0, 0x80, 0, 0]);
+        buf.extend_from_slice(&[0, 0, 0, 255, 255, 255]);
+
+        if looping {
+            buf.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+            buf.extend_from_slice(b"NETSCAPE2.0");
+            buf.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+        }
+
+        for frame in 0..frames {
+            // graphic control extension
+            buf.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00, 0x0A, 0x00, 0x00, 0x00]);
+            // image descriptor, with a local colour table on every other frame
+            let local_table = frame % 2 == 1;
+            buf.extend_from_slice(&[0x2C, 0, 0, 0, 0, 1, 0, 1, 0, if local_table { 0x80 } else { 0 }]);
+            if local_table {
+                // includes bytes that would be mistaken for introducers if not skipped
+                buf.extend_from_slice(&[0x2C, 0x2C, 0x2C, 0x21, 0x21, 0x21]);
+            }
+            // LZW minimum code size, then the image data
+            buf.extend_from_slice(&[0x02, 0x02, 0x4C, 0x01, 0x00]);
+        }
+
+        buf.push(0x3B);
+        buf
+    }
+
+    fn ftyp(brand: &[u8; 4]) -> Vec<u8> {
+        let mut buf = vec![0, 0, 0, 0x14];
+        buf.extend_from_slice(b"ftyp");
+        buf.extend_from_slice(brand);
+        buf.extend_from_slice(&[0, 0, 0, 0]);
+        buf.extend_from_slice(b"mif1");
+        buf
+    }
+
+    /// An extended WebP with the given VP8X flags, followed by an `ANIM` chunk if animated.
+    fn webp_vp8x(flags: u8) -> Vec<u8> {
+        let mut buf = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        buf.extend_from_slice(&[10, 0, 0, 0, flags, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        if flags & WEBP_ANIMATION_FLAG != 0 {
+            buf.extend_from_slice(b"ANIM");
+            buf.extend_from_slice(&[6, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        buf
+    }
+
+    #[test]
+    fn avif_sequences_are_animated() {
+        assert_eq!(get_sig(&ftyp(b"avif")), Some(Type::AVIF));
+        assert_eq!(get_sig(&ftyp(b"avis")), Some(Type::AVIF));
+        assert_eq!(get_sig(&ftyp(b"isom")), Some(Type::MP4));
+
+        assert!(is_animated(&ftyp(b"avis")));
+        assert!(!is_animated(&ftyp(b"avif")));
+        assert!(!is_animated(&ftyp(b"isom")));
+    }
+
+    #[test]
+    fn gifs_with_several_frames_are_animated() {
+        assert_eq!(get_sig(&gif(1, false)), Some(Type::GIF));
+
+        assert!(is_animated(&gif(2, true)));
+        assert!(is_animated(&gif(3, true)));
+        // frame count decides, not the looping extension
+        assert!(is_animated(&gif(2, false)));
+        assert!(!is_animated(&gif(1, true)));
+        assert!(!is_animated(&gif(1, false)));
+    }
+
+    #[test]
+    fn truncated_gifs_only_count_complete_frame_headers() {
+        let full = gif(2, false);
+        assert!(!is_animated(&full[..30]));
+        assert!(!is_animated(&full[..6]));
+        assert_eq!(gif_frame_count(&full[..full.len() - 10], 2), 2);
+    }
+
+    #[test]
+    fn webps_with_the_animation_flag_are_animated() {
+        assert_eq!(get_sig(&webp_vp8x(0)), Some(Type::WEBP));
+
+        assert!(is_animated(&webp_vp8x(WEBP_ANIMATION_FLAG)));
+        assert!(!is_animated(&webp_vp8x(0)));
+
+        // simple lossy WebPs have no VP8X header at all
+        let mut lossy = b"RIFF\0\0\0\0WEBPVP8 ".to_vec();
+        lossy.extend_from_slice(&[0; 16]);
+        assert!(!is_animated(&lossy));
+    }
+}
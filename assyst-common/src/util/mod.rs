@@ -11,6 +11,7 @@ use tracing_subscriber::fmt::time::UtcTime;
 use tracing_subscriber::EnvFilter;
 use twilight_model::channel::message::Mention;
 
+pub mod colour;
 pub mod discord;
 pub mod filetype;
 pub mod process;
@@ -18,9 +19,10 @@ pub mod rate_tracker;
 pub mod regex;
 pub mod table;
 
-/// Converts a unit string (s, m, h, d) to milliseconds
+/// Converts a unit string (ms, s, m, h, d) to milliseconds
 fn unit_to_ms(u: &str) -> u64 {
     match u {
+        "ms" => 1,
         "s" => 1000,
         "m" => 1000 * 60,
         "h" => 1000 * 60 * 60,
@@ -33,6 +35,7 @@ fn unit_to_ms(u: &str) -> u64 {
 pub enum ParseToMillisError {
     ParseIntError,
     Overflow,
+    InvalidFormat,
 }
 
 impl std::fmt::Display for ParseToMillisError {
@@ -40,14 +43,15 @@ impl std::fmt::Display for ParseToMillisError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ParseToMillisError::ParseIntError => write!(f, "Input string is too large to fit in numeric type"),
-            ParseToMillisError::Overflow => write!(f, "Final time is too large to fit in numeric type")
+            ParseToMillisError::Overflow => write!(f, "Final time is too large to fit in numeric type"),
+            ParseToMillisError::InvalidFormat => write!(f, "Input string is not a valid time (expected e.g. 30, 30s or 1h20m)")
         }
     }
 }
 
 impl std::error::Error for ParseToMillisError {}
 
-/// Parses a string such as 2h1m20s to milliseconds
+/// Parses a string such as 2h1m20s or 1s500ms to milliseconds
 pub fn parse_to_millis(input: &str) -> Result<u64, ParseToMillisError> {
     let matches = regex::TIME_STRING.captures_iter(input);
 
@@ -68,6 +72,65 @@ pub fn parse_to_millis(input: &str) -> Result<u64, ParseToMillisError> {
     Ok(total)
 }
 
+/// Converts a file size unit string (case insensitive) to its size in bytes
+fn file_size_unit_to_bytes(u: &str) -> Option<u64> {
+    match &u.to_ascii_lowercase()[..] {
+        "" | "b" => Some(1),
+        "kb" => Some(1000),
+        "mb" => Some(1000 * 1000),
+        "gb" => Some(1000 * 1000 * 1000),
+        "kib" => Some(1024),
+        "mib" => Some(1024 * 1024),
+        "gib" => Some(1024 * 1024 * 1024),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseFileSizeError {
+    InvalidNumber,
+    InvalidUnit(String),
+    Overflow,
+}
+
+impl std::fmt::Display for ParseFileSizeError {
+    #[rustfmt::skip]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseFileSizeError::InvalidNumber => write!(f, "Input string does not start with a valid positive number"),
+            ParseFileSizeError::InvalidUnit(unit) => write!(f, "Unknown size unit {unit} (expected B, KB, MB, GB, KiB, MiB or GiB)"),
+            ParseFileSizeError::Overflow => write!(f, "Final size is too large to fit in numeric type")
+        }
+    }
+}
+
+impl std::error::Error for ParseFileSizeError {}
+
+/// Parses a string such as 8MB, 500KB, 1.5GB or 4MiB to bytes. A bare number is treated as bytes.
+pub fn parse_file_size(input: &str) -> Result<u64, ParseFileSizeError> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+
+    let unit = file_size_unit_to_bytes(unit.trim()).ok_or_else(|| ParseFileSizeError::InvalidUnit(unit.to_owned()))?;
+
+    // whole numbers are handled separately so that large byte counts don't lose precision
+    if let Ok(amount) = amount.parse::<u64>() {
+        return amount.checked_mul(unit).ok_or(ParseFileSizeError::Overflow);
+    }
+
+    let amount = amount.parse::<f64>().map_err(|_| ParseFileSizeError::InvalidNumber)?;
+    let bytes = (amount * unit as f64).round();
+
+    if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+        return Err(ParseFileSizeError::Overflow);
+    }
+
+    Ok(bytes as u64)
+}
+
 /// Initialises tracing logging.
 pub fn tracing_init() {
     let filter = EnvFilter::from_default_env()
@@ -130,6 +193,34 @@ mod units {
     }
 }
 
+/// Formats milliseconds in the units [`parse_to_millis`] accepts, omitting any that are zero (e.g.
+/// `1h 20m 30s`, `2d 3h` or `500ms`), so that parsing the result gives back the same time.
+#[must_use]
+pub fn format_time_units(input: u64) -> String {
+    const UNITS: &[(u64, &str)] = &[
+        (units::DAY, "d"),
+        (units::HOUR, "h"),
+        (units::MINUTE, "m"),
+        (units::SECOND, "s"),
+        (1, "ms"),
+    ];
+
+    if input == 0 {
+        return "0s".to_owned();
+    }
+
+    let mut remaining = input;
+    let mut parts = Vec::new();
+    for &(size, unit) in UNITS {
+        if remaining >= size {
+            parts.push(format!("{}{unit}", remaining / size));
+            remaining %= size;
+        }
+    }
+
+    parts.join(" ")
+}
+
 /// Like [`String::from_utf8_lossy`], but takes an owned `Vec<u8>` and is
 /// able to reuse the vec's allocation if the bytes are valid UTF-8.
 ///
@@ -162,7 +253,7 @@ mod units {
 
 /// Normalizes custom emojis by replacing them with their names
 #[must_use] pub fn normalize_emojis(input: &str) -> Cow<'_, str> {
-    CUSTOM_EMOJI.replace_all(input, |c: &Captures| c.get(1).unwrap().as_str().to_string())
+    CUSTOM_EMOJI.replace_all(input, |c: &Captures| c.get(2).unwrap().as_str().to_string())
 }
 
 /// Normalizes mentions by replacing them with their names
@@ -175,3 +266,48 @@ mod units {
         name
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_sizes_parse_in_every_unit() {
+        for (input, bytes) in [
+            ("512", 512),
+            ("512B", 512),
+            ("8MB", 8_000_000),
+            ("500 kb", 500_000),
+            ("1.5GB", 1_500_000_000),
+            ("4MiB", 4 * 1024 * 1024),
+            ("2KiB", 2048),
+            ("1gib", 1024 * 1024 * 1024),
+            ("0.5kb", 500),
+        ] {
+            assert_eq!(parse_file_size(input).unwrap(), bytes, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_file_sizes_are_rejected() {
+        assert!(matches!(parse_file_size("8TB"), Err(ParseFileSizeError::InvalidUnit(unit)) if unit == "TB"));
+        assert!(matches!(parse_file_size("MB"), Err(ParseFileSizeError::InvalidNumber)));
+        assert!(matches!(
+            parse_file_size("1.2.3MB"),
+            Err(ParseFileSizeError::InvalidNumber)
+        ));
+    }
+
+    #[test]
+    fn oversized_file_sizes_overflow() {
+        assert!(matches!(parse_file_size("18446744073709551615B"), Ok(u64::MAX)));
+        assert!(matches!(
+            parse_file_size("18446744073709551615KB"),
+            Err(ParseFileSizeError::Overflow)
+        ));
+        assert!(matches!(
+            parse_file_size("99999999999999999999.5GiB"),
+            Err(ParseFileSizeError::Overflow)
+        ));
+    }
+}
@@ -1,46 +1,596 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use tokio::time::Instant;
 
+/// The shortest tracking length a [`RateTracker`] accepts. Shorter lengths are clamped to this, so
+/// that rates per second are always finite.
+pub const MIN_TRACKING_LENGTH: Duration = Duration::from_millis(1);
+
+/// Whether a [`RateTracker`]'s rate is increasing or decreasing over its tracking length. See
+/// [`RateTracker::trend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateTrend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+/// Receives the samples and rates observed by a [`RateTracker`], so that they can be mirrored to an
+/// external metrics exporter (e.g., Prometheus or StatsD).
+pub trait RateSink: Send + Sync {
+    /// Called whenever a sample is added to the tracker.
+    fn on_sample(&self);
+    /// Called whenever the rate is fetched from the tracker, with the fetched rate.
+    fn on_rate(&self, rate: usize);
+}
+
 /// Struct to allow the tracking of how fast a value increases, or how fast a state changes.
 ///
 /// For example, can be used to determine how frequently a command is ran over a time period,
 /// or the rate of events being received.
 pub struct RateTracker {
     tracking_length: Duration,
-    samples: Vec<Instant>,
+    samples: VecDeque<Instant>,
+    /// Maximum number of samples to hold at once, if any.
+    max_samples: Option<usize>,
+    /// Where to mirror samples and rates to, if anywhere.
+    sink: Option<Arc<dyn RateSink>>,
 }
 impl RateTracker {
+    /// Creates a tracker with no sample limit. A `tracking_length` shorter than
+    /// [`MIN_TRACKING_LENGTH`] is clamped to it.
     #[must_use] pub fn new(tracking_length: Duration) -> RateTracker {
         RateTracker {
-            tracking_length,
-            samples: vec![],
+            tracking_length: tracking_length.max(MIN_TRACKING_LENGTH),
+            samples: VecDeque::new(),
+            max_samples: None,
+            sink: None,
         }
     }
 
+    /// Creates a tracker that holds at most `max_samples` samples, dropping the oldest sample
+    /// when a new one would exceed the limit, even if it hasn't expired yet. This bounds the memory
+    /// used by trackers that receive samples faster than they expire.
+    ///
+    /// A `max_samples` of 0 is clamped to 1, since the newest sample is always kept, and
+    /// `tracking_length` is clamped as in [`RateTracker::new`].
+    #[must_use]
+    pub fn with_capacity_limit(tracking_length: Duration, max_samples: usize) -> RateTracker {
+        RateTracker {
+            tracking_length: tracking_length.max(MIN_TRACKING_LENGTH),
+            samples: VecDeque::new(),
+            max_samples: Some(max_samples.max(1)),
+            sink: None,
+        }
+    }
+
+    /// Mirrors every sample added to, and every rate fetched from, this tracker to `sink`.
+    #[must_use]
+    pub fn with_sink(mut self, sink: Arc<dyn RateSink>) -> RateTracker {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Sets or removes the sink that samples and rates are mirrored to. See
+    /// [`RateTracker::with_sink`].
+    pub fn set_sink(&mut self, sink: Option<Arc<dyn RateSink>>) {
+        self.sink = sink;
+    }
+
+    /// Removes all samples from this tracker.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+
     /// Removes all samples from this tracker which are older than the tracking length.
     pub fn remove_expired_samples(&mut self) {
-        self.samples
-            .retain(|x| Instant::now().duration_since(*x) <= self.tracking_length);
+        // samples are added in time order, so the expired ones are always at the front
+        let now = Instant::now();
+        while self
+            .samples
+            .front()
+            .is_some_and(|oldest| now.duration_since(*oldest) > self.tracking_length)
+        {
+            self.samples.pop_front();
+        }
     }
 
     /// Add a sample to the tracker.
     pub fn add_sample(&mut self) {
-        self.samples.push(Instant::now());
+        if let Some(max_samples) = self.max_samples {
+            while self.samples.len() >= max_samples {
+                self.samples.pop_front();
+            }
+        }
+
+        self.samples.push_back(Instant::now());
         self.remove_expired_samples();
+
+        if let Some(sink) = &self.sink {
+            sink.on_sample();
+        }
     }
 
     /// Remove the oldest sample from the tracker.
     pub fn remove_sample(&mut self) {
-        if !self.samples.is_empty() {
-            self.samples.remove(0);
-        }
+        self.samples.pop_front();
         self.remove_expired_samples();
     }
 
-    /// Fetches the amount of current non-expired samples.
+    /// Fetches the amount of current non-expired samples, i.e., how many times the tracked event
+    /// happened within the tracking length. This is a count of events, not a difference between
+    /// sample values.
     pub fn get_rate(&mut self) -> usize {
+        let rate = self.count();
+
+        if let Some(sink) = &self.sink {
+            sink.on_rate(rate);
+        }
+
+        rate
+    }
+
+    /// Fetches the amount of samples currently within the tracking length.
+    pub fn count(&mut self) -> usize {
         self.remove_expired_samples();
         self.samples.len()
     }
+
+    /// Fetches the average number of samples per second over the tracking length.
+    pub fn per_second(&mut self) -> f64 {
+        self.count() as f64 / self.tracking_length.as_secs_f64()
+    }
+
+    /// Splits the tracking length in half, returning the number of samples in the older half and
+    /// in the more recent half, in that order.
+    pub fn split_rate(&mut self) -> (usize, usize) {
+        self.remove_expired_samples();
+
+        // samples are in time order, so everything before the first recent sample is older
+        let older = match Instant::now().checked_sub(self.tracking_length / 2) {
+            Some(midpoint) => self.samples.partition_point(|sample| *sample < midpoint),
+            None => 0,
+        };
+
+        (older, self.samples.len() - older)
+    }
+
+    /// Compares the recent half of the tracking length with the older half (see
+    /// [`RateTracker::split_rate`]). The rate is rising or falling if one half has more than
+    /// `threshold` times as many samples as the other, and steady otherwise. For example, with a
+    /// threshold of 1.5, the rate is rising if the recent half has over 50% more samples.
+    pub fn trend(&mut self, threshold: f64) -> RateTrend {
+        let (older, recent) = self.split_rate();
+        let (older, recent) = (older as f64, recent as f64);
+
+        if recent > older * threshold {
+            RateTrend::Rising
+        } else if older > recent * threshold {
+            RateTrend::Falling
+        } else {
+            RateTrend::Steady
+        }
+    }
+}
+
+/// A [`RateTracker`] that can be cloned and shared between tasks.
+///
+/// Each method locks the tracker only for the duration of that call. The lock is synchronous, so
+/// it can never be held across an await point.
+#[derive(Clone)]
+pub struct SharedRateTracker(Arc<Mutex<RateTracker>>);
+impl SharedRateTracker {
+    #[must_use]
+    pub fn new(tracking_length: Duration) -> SharedRateTracker {
+        SharedRateTracker(Arc::new(Mutex::new(RateTracker::new(tracking_length))))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RateTracker> {
+        // a panic while holding the lock can't leave the samples in an invalid state
+        self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// See [`RateTracker::set_sink`].
+    pub fn set_sink(&self, sink: Option<Arc<dyn RateSink>>) {
+        self.lock().set_sink(sink);
+    }
+
+    /// See [`RateTracker::add_sample`].
+    pub fn add_sample(&self) {
+        self.lock().add_sample();
+    }
+
+    /// See [`RateTracker::reset`].
+    pub fn reset(&self) {
+        self.lock().reset();
+    }
+
+    /// See [`RateTracker::remove_sample`].
+    pub fn remove_sample(&self) {
+        self.lock().remove_sample();
+    }
+
+    /// See [`RateTracker::get_rate`].
+    #[must_use]
+    pub fn get_rate(&self) -> usize {
+        self.lock().get_rate()
+    }
+
+    /// See [`RateTracker::count`].
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.lock().count()
+    }
+
+    /// See [`RateTracker::per_second`].
+    #[must_use]
+    pub fn per_second(&self) -> f64 {
+        self.lock().per_second()
+    }
+}
+
+/// A set of [`RateTracker`]s, one per key, such as per command or per guild.
+///
+/// Trackers are created on their first sample, and are only removed by [`KeyedRateTracker::prune`].
+/// This type does no locking of its own; when shared, hold the lock only for the duration of each
+/// call, and never across an await point.
+pub struct KeyedRateTracker<K> {
+    tracking_length: Duration,
+    trackers: HashMap<K, RateTracker>,
+}
+impl<K: Eq + Hash> KeyedRateTracker<K> {
+    #[must_use]
+    pub fn new(tracking_length: Duration) -> KeyedRateTracker<K> {
+        KeyedRateTracker {
+            tracking_length,
+            trackers: HashMap::new(),
+        }
+    }
+
+    /// Add a sample to the tracker for `key`, creating it if it doesn't exist.
+    pub fn add_sample(&mut self, key: K) {
+        self.trackers
+            .entry(key)
+            .or_insert_with(|| RateTracker::new(self.tracking_length))
+            .add_sample();
+    }
+
+    /// Fetches the amount of current non-expired samples for `key`. See [`RateTracker::get_rate`].
+    pub fn get_rate<Q>(&mut self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.trackers.get_mut(key).map_or(0, RateTracker::get_rate)
+    }
+
+    /// Fetches the current rate of every key that has any non-expired samples.
+    pub fn rates(&mut self) -> impl Iterator<Item = (&K, usize)> {
+        self.trackers
+            .iter_mut()
+            .map(|(key, tracker)| (key, tracker.get_rate()))
+            .filter(|(_, rate)| *rate > 0)
+    }
+
+    /// Fetches the `count` keys with the highest current rates, highest first.
+    pub fn top_rates(&mut self, count: usize) -> Vec<(K, usize)>
+    where
+        K: Clone,
+    {
+        let mut rates = self.rates().map(|(key, rate)| (key.clone(), rate)).collect::<Vec<_>>();
+
+        rates.sort_by(|a, b| b.1.cmp(&a.1));
+        rates.truncate(count);
+        rates
+    }
+
+    /// Removes the trackers for every key.
+    pub fn reset(&mut self) {
+        self.trackers.clear();
+    }
+
+    /// Removes the trackers for any keys whose samples have all expired. This only visits each
+    /// tracker once, so it is cheap enough to call periodically.
+    pub fn prune(&mut self) {
+        self.trackers.retain(|_, tracker| tracker.count() > 0);
+    }
+
+    /// Fetches the number of keys currently being tracked, including any not yet pruned.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.trackers.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.trackers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::time::advance;
+
+    use super::*;
+
+    const LENGTH: Duration = Duration::from_secs(10);
+
+    #[tokio::test(start_paused = true)]
+    async fn samples_expire_after_the_tracking_length() {
+        let mut tracker = RateTracker::new(LENGTH);
+        tracker.add_sample();
+        advance(Duration::from_secs(4)).await;
+        tracker.add_sample();
+
+        // a sample exactly as old as the tracking length is still counted
+        advance(Duration::from_secs(6)).await;
+        assert_eq!(tracker.count(), 2);
+
+        advance(Duration::from_millis(1)).await;
+        assert_eq!(tracker.count(), 1);
+
+        advance(Duration::from_secs(4)).await;
+        assert_eq!(tracker.count(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_is_the_number_of_samples_in_the_window() {
+        let mut tracker = RateTracker::new(LENGTH);
+        assert_eq!(tracker.get_rate(), 0);
+
+        // a burst counts every sample, however close together they are
+        for _ in 0..5 {
+            tracker.add_sample();
+        }
+        assert_eq!(tracker.get_rate(), 5);
+
+        advance(Duration::from_secs(8)).await;
+        tracker.add_sample();
+        assert_eq!(tracker.get_rate(), 6);
+
+        // once the burst expires, only the later sample is left
+        advance(Duration::from_secs(3)).await;
+        assert_eq!(tracker.get_rate(), 1);
+        assert_eq!(tracker.get_rate(), tracker.count());
+
+        let shared = SharedRateTracker::new(LENGTH);
+        shared.add_sample();
+        shared.add_sample();
+        assert_eq!(shared.get_rate(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expiry_matches_filtering_every_sample() {
+        // the same operations against a plain list of samples, filtered in full each time
+        let mut tracker = RateTracker::new(LENGTH);
+        let mut expected: Vec<Instant> = Vec::new();
+
+        for step in 0..200u64 {
+            advance(Duration::from_millis(step * 37 % 1500)).await;
+
+            tracker.add_sample();
+            expected.push(Instant::now());
+
+            let now = Instant::now();
+            expected.retain(|sample| now.duration_since(*sample) <= LENGTH);
+            assert_eq!(tracker.count(), expected.len(), "step {step}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn removing_a_sample_drops_the_oldest() {
+        let mut tracker = RateTracker::new(LENGTH);
+        tracker.remove_sample();
+        assert_eq!(tracker.count(), 0);
+
+        tracker.add_sample();
+        advance(Duration::from_secs(5)).await;
+        tracker.add_sample();
+        tracker.remove_sample();
+
+        // had the newer sample been removed, the older one would now have expired
+        advance(Duration::from_secs(6)).await;
+        assert_eq!(tracker.count(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn adding_and_removing_matches_a_list() {
+        let mut tracker = RateTracker::new(LENGTH);
+        let mut expected: Vec<Instant> = Vec::new();
+
+        for step in 0..200u64 {
+            advance(Duration::from_millis(step * 37 % 1500)).await;
+
+            if step % 3 == 0 {
+                tracker.remove_sample();
+                if !expected.is_empty() {
+                    expected.remove(0);
+                }
+            } else {
+                tracker.add_sample();
+                expected.push(Instant::now());
+            }
+
+            let now = Instant::now();
+            expected.retain(|sample| now.duration_since(*sample) <= LENGTH);
+            assert_eq!(tracker.count(), expected.len(), "step {step}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn capacity_limit_drops_the_oldest_unexpired_samples() {
+        let mut tracker = RateTracker::with_capacity_limit(LENGTH, 3);
+
+        for _ in 0..5 {
+            tracker.add_sample();
+            advance(Duration::from_secs(1)).await;
+        }
+        assert_eq!(tracker.count(), 3);
+
+        // the samples from 2, 3 and 4 seconds were kept, and by now the one from 2 seconds has expired
+        advance(Duration::from_secs(8)).await;
+        assert_eq!(tracker.count(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn capacity_limit_evicts_only_once_full() {
+        let mut tracker = RateTracker::with_capacity_limit(LENGTH, 3);
+
+        for _ in 0..3 {
+            tracker.add_sample();
+            advance(Duration::from_secs(1)).await;
+        }
+        assert_eq!(tracker.count(), 3);
+
+        // the fourth sample evicts the one from 0 seconds, so nothing expires at 10 seconds
+        tracker.add_sample();
+        assert_eq!(tracker.count(), 3);
+        advance(Duration::from_millis(7001)).await;
+        assert_eq!(tracker.count(), 3);
+        advance(Duration::from_secs(1)).await;
+        assert_eq!(tracker.count(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn capacity_limit_of_zero_keeps_the_newest_sample() {
+        let mut tracker = RateTracker::with_capacity_limit(LENGTH, 0);
+        assert_eq!(tracker.count(), 0);
+
+        tracker.add_sample();
+        advance(Duration::from_secs(1)).await;
+        tracker.add_sample();
+        assert_eq!(tracker.count(), 1);
+
+        // the kept sample is the newer one, which is still within the tracking length
+        advance(Duration::from_millis(9500)).await;
+        assert_eq!(tracker.count(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn zero_tracking_length_is_clamped() {
+        let mut tracker = RateTracker::new(Duration::ZERO);
+        tracker.add_sample();
+        assert!(tracker.per_second().is_finite());
+        assert_eq!(tracker.per_second(), 1000.0);
+
+        let mut tracker = RateTracker::with_capacity_limit(Duration::ZERO, 1);
+        assert_eq!(tracker.per_second(), 0.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_empties_a_full_capped_tracker() {
+        let mut tracker = RateTracker::with_capacity_limit(LENGTH, 2);
+        tracker.add_sample();
+        tracker.add_sample();
+        tracker.add_sample();
+
+        tracker.reset();
+        assert_eq!(tracker.count(), 0);
+
+        // the limit still applies after a reset
+        tracker.add_sample();
+        tracker.add_sample();
+        tracker.add_sample();
+        assert_eq!(tracker.count(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_removes_every_sample() {
+        let mut tracker = RateTracker::new(LENGTH);
+        tracker.add_sample();
+        tracker.add_sample();
+
+        tracker.reset();
+        assert_eq!(tracker.count(), 0);
+
+        tracker.add_sample();
+        assert_eq!(tracker.count(), 1);
+    }
+
+    #[derive(Default)]
+    struct CountingSink {
+        samples: AtomicUsize,
+        last_rate: AtomicUsize,
+    }
+
+    impl RateSink for CountingSink {
+        fn on_sample(&self) {
+            self.samples.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_rate(&self, rate: usize) {
+            self.last_rate.store(rate, Ordering::Relaxed);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sink_mirrors_samples_and_rates() {
+        let sink = Arc::new(CountingSink::default());
+        let mut tracker = RateTracker::new(LENGTH).with_sink(sink.clone());
+
+        tracker.add_sample();
+        tracker.add_sample();
+        assert_eq!(tracker.get_rate(), 2);
+        assert_eq!(sink.samples.load(Ordering::Relaxed), 2);
+        assert_eq!(sink.last_rate.load(Ordering::Relaxed), 2);
+
+        // only fetching the rate is reported, not counting
+        advance(LENGTH * 2).await;
+        assert_eq!(tracker.count(), 0);
+        assert_eq!(sink.last_rate.load(Ordering::Relaxed), 2);
+
+        tracker.set_sink(None);
+        tracker.add_sample();
+        assert_eq!(tracker.get_rate(), 1);
+        assert_eq!(sink.samples.load(Ordering::Relaxed), 2);
+        assert_eq!(sink.last_rate.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn split_rate_divides_the_tracking_length_in_half() {
+        let mut tracker = RateTracker::new(LENGTH);
+        assert_eq!(tracker.split_rate(), (0, 0));
+
+        tracker.add_sample();
+        advance(Duration::from_secs(3)).await;
+        tracker.add_sample();
+        tracker.add_sample();
+        advance(Duration::from_secs(3)).await;
+        tracker.add_sample();
+
+        // the midpoint is 5 seconds ago, so only the first sample is in the older half
+        assert_eq!(tracker.split_rate(), (1, 3));
+
+        // the first sample has expired, and the midpoint has moved past the two in the middle
+        advance(Duration::from_secs(5)).await;
+        assert_eq!(tracker.split_rate(), (2, 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn trend_compares_the_halves_against_the_threshold() {
+        let mut tracker = RateTracker::new(LENGTH);
+        assert_eq!(tracker.trend(1.5), RateTrend::Steady);
+
+        for _ in 0..2 {
+            tracker.add_sample();
+        }
+        advance(Duration::from_secs(6)).await;
+        for _ in 0..3 {
+            tracker.add_sample();
+        }
+
+        // 3 recent samples to 2 older ones is exactly 1.5 times as many, which isn't over the threshold
+        assert_eq!(tracker.trend(1.5), RateTrend::Steady);
+        assert_eq!(tracker.trend(1.2), RateTrend::Rising);
+
+        // the older samples have expired, and the recent ones are now in the older half
+        advance(Duration::from_secs(10)).await;
+        assert_eq!(tracker.trend(1.5), RateTrend::Falling);
+    }
 }
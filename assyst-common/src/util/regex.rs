@@ -2,13 +2,34 @@ use lazy_static::lazy_static;
 use regex::Regex;
 
 lazy_static! {
-    pub static ref CUSTOM_EMOJI: Regex = Regex::new(r"<a?:(\w+):(\d{16,20})>").unwrap();
+    pub static ref CUSTOM_EMOJI: Regex = Regex::new(r"<(a)?:(\w+):(\d{16,20})>").unwrap();
     pub static ref TENOR_GIF: Regex = Regex::new(r"https://\w+\.tenor\.com/[\w\-]+/[^\.]+\.gif").unwrap();
     pub static ref URL: Regex = Regex::new(
-        r"https?://(www\.)?[-a-zA-Z0-9@:%._\+~#=]{1,256}\.[a-zA-Z0-9()]{1,6}\b([-a-zA-Z0-9()@:%_\+.~#?&//=]*)"
+        r"(?i:https?)://(www\.)?[-a-zA-Z0-9@:%._\+~#=]{1,256}\.[a-zA-Z0-9()]{1,6}\b([-a-zA-Z0-9()@:%_\+.~#?&//=]*)"
+    )
+    .unwrap();
+    pub static ref MESSAGE_LINK: Regex = Regex::new(
+        r"(?i)^https?://(?:(?:ptb|canary)\.)?discord(?:app)?\.com/channels/(\d+|@me)/(\d{16,20})/(\d{16,20})/?$"
     )
     .unwrap();
     pub static ref USER_MENTION: Regex = Regex::new(r"(?:<@!?)?(\d{16,20})>?").unwrap();
-    pub static ref TIME_STRING: Regex = Regex::new("(\\d+)([smhd])").unwrap();
+    pub static ref TIME_STRING: Regex = Regex::new("(\\d+)(ms|[smhd])").unwrap();
     pub static ref COMMAND_FLAG: Regex = Regex::new(r#"\s+-(\w+)(?: *"([^"]+)"| *([^\-\s]+))?"#).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_schemes_are_case_insensitive() {
+        for url in [
+            "https://example.com/a.png",
+            "HTTPS://example.com/a.png",
+            "Http://example.com/a.png",
+        ] {
+            assert!(URL.is_match(url), "{url:?}");
+        }
+        assert!(MESSAGE_LINK.is_match("HTTPS://Discord.com/channels/@me/1099115731301449758/1099115731301449758"));
+    }
+}
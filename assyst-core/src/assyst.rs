@@ -1,27 +1,33 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
 use assyst_common::config::CONFIG;
 use assyst_common::err;
 use assyst_common::metrics_handler::MetricsHandler;
-use assyst_common::pipe::CACHE_PIPE_PATH;
+use assyst_common::pipe::{CACHE_PIPE_PATH, GATEWAY_COMMAND_PIPE_PATH};
 use assyst_database::model::active_guild_premium_entitlement::ActiveGuildPremiumEntitlement;
 use assyst_database::model::badtranslator_channel::BadTranslatorChannel;
+use assyst_database::model::global_blacklist::GlobalBlacklist;
 use assyst_database::DatabaseHandler;
 use assyst_flux_iface::FluxHandler;
+use tokio::sync::Semaphore;
 use twilight_http::client::InteractionClient;
 use twilight_http::Client as HttpClient;
-use twilight_model::id::marker::ApplicationMarker;
+use twilight_model::id::marker::{ApplicationMarker, GuildMarker, UserMarker};
 use twilight_model::id::Id;
+use twilight_model::user::User;
 
 use crate::bad_translator::{BadTranslator, BadTranslatorEntry};
 use crate::command::componentctxt::ComponentCtxts;
+use crate::command::middleware::{DynOutputMiddleware, OutputMiddlewares, TOutputMiddleware};
 use crate::command_ratelimits::CommandRatelimits;
 use crate::persistent_cache_handler::PersistentCacheHandler;
 use crate::replies::Replies;
 use crate::rest::patreon::Patron;
 use crate::rest::rest_cache_handler::RestCacheHandler;
+use crate::task::tasks::refresh_entitlements::EntitlementRefreshStats;
 use crate::task::Task;
+use crate::user_resolver::{self, GuildMemberRequests};
 
 pub type ThreadSafeAssyst = Arc<Assyst>;
 
@@ -50,6 +56,9 @@ pub struct Assyst {
     pub metrics_handler: Arc<MetricsHandler>,
     /// The reqwest client, used to issue general HTTP requests
     pub reqwest_client: reqwest::Client,
+    /// Bounds how many media downloads run at once (see `max_concurrent_downloads` in the config),
+    /// so that a burst of commands queues up instead of saturating bandwidth and memory.
+    pub download_permits: Arc<Semaphore>,
     /// Tasks are functions which are called on an interval.
     pub tasks: Mutex<Vec<Task>>,
     /// The recommended number of shards for this instance.
@@ -62,8 +71,22 @@ pub struct Assyst {
     /// All entitlements. At present, these entitlements are a single tier of guild subscription.
     /// `Arc`ed since it's also included as part of the Flux handler
     pub entitlements: Arc<Mutex<HashMap<i64, ActiveGuildPremiumEntitlement>>>,
+    /// Statistics about how entitlements have changed in recent refreshes.
+    pub entitlement_refresh_stats: Mutex<EntitlementRefreshStats>,
     /// Component contexts, mapping a custom ID (e.g., a button) to a context.
     pub component_contexts: ComponentCtxts,
+    /// Middleware applied, in registration order, to every command reply before it is sent.
+    pub output_middleware: OutputMiddlewares<DynOutputMiddleware>,
+    /// Resolved Twemoji image URLs, keyed by emoji codepoint. The mapping is effectively static,
+    /// so entries live for the lifetime of the process.
+    pub emoji_urls: Mutex<HashMap<String, String>>,
+    /// Every globally blacklisted user ID, so that checking the blacklist (which happens for every
+    /// message) never needs the database. Kept up to date by
+    /// [`Assyst::set_user_globally_blacklisted`], and periodically refreshed in case the database
+    /// was changed elsewhere.
+    pub global_blacklist: Mutex<HashSet<u64>>,
+    /// Guild member requests sent through assyst-gateway, used to resolve users without REST.
+    pub guild_member_requests: GuildMemberRequests,
 }
 impl Assyst {
     pub async fn new() -> anyhow::Result<Assyst> {
@@ -76,6 +99,7 @@ impl Assyst {
         let entitlements = Arc::new(Mutex::new(
             ActiveGuildPremiumEntitlement::get_all(&database_handler).await?,
         ));
+        let global_blacklist = Mutex::new(GlobalBlacklist::get_all(&database_handler).await?);
 
         Ok(Assyst {
             bad_translator: BadTranslator::new(),
@@ -86,6 +110,9 @@ impl Assyst {
             premium_users: premium_users.clone(),
             metrics_handler: Arc::new(MetricsHandler::new(database_handler.clone())?),
             reqwest_client: reqwest::Client::new(),
+            // always allow at least one download, so a misconfigured limit of zero can't stall every
+            // command
+            download_permits: Arc::new(Semaphore::new(CONFIG.max_concurrent_downloads.max(1))),
             tasks: Mutex::new(vec![]),
             shard_count,
             replies: Replies::new(),
@@ -97,7 +124,12 @@ impl Assyst {
             rest_cache_handler: RestCacheHandler::new(http_client.clone()),
             command_ratelimits: CommandRatelimits::new(),
             entitlements,
+            entitlement_refresh_stats: Mutex::new(EntitlementRefreshStats::default()),
             component_contexts: ComponentCtxts::new(),
+            output_middleware: OutputMiddlewares::default(),
+            emoji_urls: Mutex::new(HashMap::new()),
+            global_blacklist,
+            guild_member_requests: GuildMemberRequests::new(GATEWAY_COMMAND_PIPE_PATH),
         })
     }
 
@@ -106,6 +138,69 @@ impl Assyst {
         self.tasks.lock().unwrap().push(task);
     }
 
+    /// Register a new `OutputMiddleware` to Assyst.
+    pub fn register_output_middleware(&self, middleware: TOutputMiddleware) {
+        self.output_middleware.register(middleware);
+    }
+
+    /// Resolves several users by ID, each at most once: from the REST cache, then from the members
+    /// of `guild_id` (if given) through the gateway, and only the rest over REST. Users that
+    /// don't exist are left out. Everything resolved is added to the REST cache.
+    pub async fn resolve_users(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        ids: &[Id<UserMarker>],
+    ) -> HashMap<Id<UserMarker>, User> {
+        let users = user_resolver::resolve_users(
+            ids,
+            |id| self.rest_cache_handler.get_user(id),
+            |ids| async move {
+                match guild_id {
+                    Some(guild_id) => self.guild_member_requests.request(guild_id, &ids).await,
+                    None => Vec::new(),
+                }
+            },
+            |id| async move { self.http_client.user(id).await.ok()?.model().await.ok() },
+        )
+        .await;
+
+        for user in users.values() {
+            self.rest_cache_handler.set_user(user.clone());
+        }
+
+        users
+    }
+
+    /// Whether a user is globally blacklisted from the bot.
+    pub fn user_globally_blacklisted(&self, user_id: u64) -> bool {
+        self.global_blacklist.lock().unwrap().contains(&user_id)
+    }
+
+    /// Adds a user to, or removes a user from, the global blacklist. Takes effect immediately.
+    pub async fn set_user_globally_blacklisted(&self, user_id: u64, blacklisted: bool) -> anyhow::Result<()> {
+        if blacklisted {
+            GlobalBlacklist {}
+                .set_user_blacklisted(&self.database_handler, user_id)
+                .await?;
+            self.global_blacklist.lock().unwrap().insert(user_id);
+        } else {
+            GlobalBlacklist {}
+                .remove_user_from_blacklist(&self.database_handler, user_id)
+                .await?;
+            self.global_blacklist.lock().unwrap().remove(&user_id);
+        }
+
+        Ok(())
+    }
+
+    /// Reloads the global blacklist from the database.
+    pub async fn refresh_global_blacklist(&self) -> anyhow::Result<()> {
+        let blacklist = GlobalBlacklist::get_all(&self.database_handler).await?;
+        *self.global_blacklist.lock().unwrap() = blacklist;
+
+        Ok(())
+    }
+
     pub fn update_premium_user_list(&self, patrons: Vec<Patron>) {
         let mut flux_prems = HashMap::new();
         for patron in &patrons {
@@ -1,27 +1,43 @@
+use std::collections::HashMap;
 use std::fmt::Display;
-
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use assyst_common::config::config::ImageSourcePrecedence;
+use assyst_common::config::CONFIG;
+use assyst_common::util::colour::{parse_colour, parse_colour_alpha};
 use assyst_common::util::discord::{channel_mention_to_id, get_avatar_url, id_from_mention, user_mention_to_id};
-use assyst_common::util::{parse_to_millis, regex};
-use assyst_string_fmt::markdown::parse_codeblock;
+use assyst_common::util::filetype::{get_sig, is_animated, Type};
+use assyst_common::util::{
+    format_time, format_time_units, parse_file_size, parse_to_millis, regex, unix_timestamp, ParseFileSizeError,
+    ParseToMillisError,
+};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use reqwest::{Client, StatusCode};
 use serde::Deserialize;
+use tracing::debug;
 use twilight_model::application::command::CommandOption;
 use twilight_model::application::interaction::application_command::CommandOptionValue;
 use twilight_model::channel::message::sticker::{MessageSticker, StickerFormatType};
 use twilight_model::channel::message::Embed;
-use twilight_model::channel::{Attachment, Channel as TwlChannel};
-use twilight_model::id::marker::{ChannelMarker, UserMarker};
+use twilight_model::channel::{Attachment, Channel as TwlChannel, Message};
+use twilight_model::id::marker::{ChannelMarker, MessageMarker, RoleMarker, UserMarker};
 use twilight_model::id::Id;
 use twilight_model::user::User as TwlUser;
 use twilight_util::builder::command::{
-    AttachmentBuilder, ChannelBuilder, IntegerBuilder, NumberBuilder, StringBuilder, UserBuilder,
+    AttachmentBuilder, BooleanBuilder, ChannelBuilder, IntegerBuilder, MentionableBuilder, NumberBuilder,
+    StringBuilder, UserBuilder,
 };
 
 use super::errors::{ArgsExhausted, TagParseError};
 use super::{CommandCtxt, InteractionCommandParseCtxt, Label, RawMessageParseCtxt};
 use crate::assyst::Assyst;
-use crate::commit_if_ok;
-use crate::downloader::{self, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES};
+use crate::downloader::{self, DownloadOptions, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES};
 use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
+use crate::{collect_while_advancing, commit_if_ok};
 
 pub trait ParseArgument: Sized {
     /// Parses `Self`, given a command, where the source is a raw message.
@@ -37,10 +53,26 @@ pub trait ParseArgument: Sized {
     }
 }
 
+/// Replaces the description of the option called `name` in `options`, which are usually generic
+/// (e.g., "integer option"). Used by `#[description = "..."]` on command parameters.
+pub fn with_description(mut options: Vec<CommandOption>, name: &str, description: &str) -> Vec<CommandOption> {
+    for option in options.iter_mut().filter(|o| o.name == name) {
+        option.description = description.to_owned();
+    }
+
+    options
+}
+
+/// Parses a word as a number, reporting the word and what was `expected` if it isn't one.
+fn parse_number<T: FromStr>(word: &str, expected: &'static str) -> Result<T, TagParseError> {
+    word.parse()
+        .map_err(|_| TagParseError::InvalidNumber((word.to_owned(), expected)))
+}
+
 impl ParseArgument for i64 {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        Ok(word.parse()?)
+        parse_number(&word, "a whole number")
     }
 
     async fn parse_command_option(
@@ -64,10 +96,44 @@ impl ParseArgument for i64 {
     }
 }
 
+impl ParseArgument for i32 {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        parse_number(&word, "a whole number")
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let next = &ctxt.option_by_name(&label.unwrap().0)?.value;
+        if let CommandOptionValue::Integer(option) = next {
+            // discord integers are 64 bit, so make sure this fits instead of truncating
+            Ok(i32::try_from(*option)?)
+        } else {
+            // cloning is fine since this should (ideally) never happen
+            Err(TagParseError::MismatchedCommandOptionType((
+                "i32".to_owned(),
+                next.clone(),
+            )))
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            IntegerBuilder::new(name, "integer option")
+                .min_value(i32::MIN.into())
+                .max_value(i32::MAX.into())
+                .required(true)
+                .build(),
+        ]
+    }
+}
+
 impl ParseArgument for u64 {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        Ok(word.parse()?)
+        parse_number(&word, "a positive whole number")
     }
 
     async fn parse_command_option(
@@ -94,7 +160,7 @@ impl ParseArgument for u64 {
 impl ParseArgument for f64 {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        Ok(word.parse()?)
+        parse_number(&word, "a number")
     }
 
     async fn parse_command_option(
@@ -120,7 +186,7 @@ impl ParseArgument for f64 {
 impl ParseArgument for f32 {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        Ok(word.parse()?)
+        parse_number(&word, "a number")
     }
 
     async fn parse_command_option(
@@ -143,13 +209,13 @@ impl ParseArgument for f32 {
     }
 }
 
-impl<T: ParseArgument> ParseArgument for Option<T> {
+impl ParseArgument for bool {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        // TODO: should we be using commit_if_ok to undo failed parsers?
-        match T::parse_raw_message(ctxt, label).await {
-            Ok(v) => Ok(Some(v)),
-            Err(err) if err.get_severity() == ErrorSeverity::High => Err(err),
-            _ => Ok(None),
+        let word = ctxt.next_word(label)?;
+        match &word.to_ascii_lowercase()[..] {
+            "true" | "yes" | "on" => Ok(true),
+            "false" | "no" | "off" => Ok(false),
+            _ => Err(TagParseError::ParseBoolError(word.into_owned())),
         }
     }
 
@@ -157,14 +223,46 @@ impl<T: ParseArgument> ParseArgument for Option<T> {
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        // TODO: should we be using commit_if_ok to undo failed parsers?
-        match T::parse_command_option(ctxt, label).await {
-            Ok(v) => Ok(Some(v)),
-            Err(err) if err.get_severity() == ErrorSeverity::High => Err(err),
-            _ => Ok(None),
+        let next = &ctxt.option_by_name(&label.unwrap().0)?.value;
+        if let CommandOptionValue::Boolean(option) = next {
+            Ok(*option)
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "bool".to_owned(),
+                next.clone(),
+            )))
         }
     }
 
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![BooleanBuilder::new(name, "boolean option").required(true).build()]
+    }
+}
+
+/// Turns the result of parsing an optional argument into `None` if it failed with a low severity
+/// error. High severity errors are returned as they are.
+fn optional<T>(result: Result<T, TagParseError>) -> Result<Option<T>, TagParseError> {
+    match result {
+        Ok(v) => Ok(Some(v)),
+        Err(err) if err.get_severity() == ErrorSeverity::High => Err(err),
+        _ => Ok(None),
+    }
+}
+
+impl<T: ParseArgument> ParseArgument for Option<T> {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        // parse in a fork, so that anything consumed by a failed parse is given back to the next
+        // argument
+        optional(commit_if_ok!(ctxt, T::parse_raw_message, label))
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        optional(commit_if_ok!(ctxt, T::parse_command_option, label))
+    }
+
     fn as_command_options(name: &str) -> Vec<CommandOption> {
         let mut options = T::as_command_options(name);
         options.iter_mut().for_each(|o| o.required = Some(false));
@@ -178,6 +276,89 @@ impl<T: ParseArgument> ParseArgument for Option<T> {
     }
 }
 
+/// An argument that can be one of two types, such as a colour or an image in the same position.
+/// `A` is tried first, and if it fails with a low severity error, anything it consumed is given
+/// back and `B` is tried instead. High severity errors from `A` are returned straight away.
+///
+/// For interaction commands, only `A`'s options are registered, since one option can't have two
+/// types. `B` is still tried if `A` fails to parse, so it is only useful there if it accepts the
+/// same option type as `A` (e.g., `Either<u64, Word>` can't take a word as a slash command option,
+/// but `Either<Word, Rest>` works).
+#[derive(Debug)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B> Either<A, B> {
+    /// Takes `left` if it parsed, or else runs `right` if `left` failed with a low severity error.
+    async fn or_else<F>(left: Result<A, TagParseError>, right: impl FnOnce() -> F) -> Result<Self, TagParseError>
+    where
+        F: Future<Output = Result<B, TagParseError>>,
+    {
+        match left {
+            Ok(v) => Ok(Self::Left(v)),
+            Err(err) if err.get_severity() == ErrorSeverity::High => Err(err),
+            Err(_) => right().await.map(Self::Right),
+        }
+    }
+}
+
+impl<A: ParseArgument, B: ParseArgument> ParseArgument for Either<A, B> {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let left = commit_if_ok!(ctxt, A::parse_raw_message, label);
+        Self::or_else(left, move || B::parse_raw_message(ctxt, label)).await
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let left = commit_if_ok!(ctxt, A::parse_command_option, label);
+        Self::or_else(left, move || B::parse_command_option(ctxt, label)).await
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        A::as_command_options(name)
+    }
+}
+
+/// Any number of a value that can also be parsed from a single word, such as `Vec<u64>`. For
+/// interactions, this is a single string option, split on whitespace.
+///
+/// The list ends at the first word `T` can't parse, or once `T` parses without consuming anything
+/// (e.g. an `Anchor` falling back to its default), which would otherwise repeat forever.
+impl<T> ParseArgument for Vec<T>
+where
+    T: ParseArgument + FromStr,
+    TagParseError: From<T::Err>,
+{
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        // `Option<T>`'s parser takes care of recovering from low severity errors
+        // and any `Err`s returned are fatal
+        collect_while_advancing!(ctxt, |ctxt| {
+            <Option<T>>::parse_raw_message(ctxt, label.clone()).await
+        })
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let Word(text) = Word::parse_command_option(ctxt, label).await?;
+
+        Ok(text.split_ascii_whitespace().map(str::parse).try_collect()?)
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "space-separated list").required(true).build()]
+    }
+
+    fn usage(name: &str) -> String {
+        format!("<{name}[]>")
+    }
+}
+
 impl ParseArgument for Vec<Word> {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let mut items = Vec::new();
@@ -255,17 +436,43 @@ impl ParseArgument for Vec<WordAutocomplete> {
     }
 }
 
-/// A time argument such as `1h20m30s`.
+/// A time argument such as `1h20m30s`, or a bare number of seconds such as `30`.
 #[derive(Debug)]
 pub struct Time {
     pub millis: u64,
 }
+impl Time {
+    /// Parses a time such as `1h20m30s`. A bare integer, such as `30`, is treated as seconds.
+    fn parse(input: &str) -> Result<Self, ParseToMillisError> {
+        if !input.is_empty() && input.bytes().all(|b| b.is_ascii_digit()) {
+            let seconds = input.parse::<u64>().map_err(|_| ParseToMillisError::ParseIntError)?;
+            let millis = seconds.checked_mul(1000).ok_or(ParseToMillisError::Overflow)?;
+
+            return Ok(Time { millis });
+        }
+
+        // `parse_to_millis` skips anything it doesn't recognise, so catch input with no units at all
+        if !regex::TIME_STRING.is_match(input) {
+            return Err(ParseToMillisError::InvalidFormat);
+        }
+
+        Ok(Time {
+            millis: parse_to_millis(input)?,
+        })
+    }
+}
+/// Formats the time in the same units it is parsed from, omitting any that are zero (e.g.,
+/// `1h 20m 30s`, `2d 3h` or `500ms`). See [`format_time_units`].
+impl Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format_time_units(self.millis))
+    }
+}
 impl ParseArgument for Time {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        let millis = parse_to_millis(word)?;
 
-        Ok(Time { millis })
+        Ok(Time::parse(&word)?)
     }
 
     async fn parse_command_option(
@@ -275,9 +482,7 @@ impl ParseArgument for Time {
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
         if let CommandOptionValue::String(ref option) = word {
-            let millis = parse_to_millis(option)?;
-
-            Ok(Time { millis })
+            Ok(Time::parse(option)?)
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
                 "String (time)".to_owned(),
@@ -291,43 +496,80 @@ impl ParseArgument for Time {
     }
 }
 
-/// A single word argument.
+/// A time argument such as `1h20m30s`, which must be between `MIN_MS` and `MAX_MS` milliseconds
+/// (inclusive).
 #[derive(Debug)]
-pub struct Word(pub String);
-
-impl ParseArgument for Word {
+pub struct BoundedTime<const MIN_MS: u64, const MAX_MS: u64> {
+    pub millis: u64,
+}
+impl<const MIN_MS: u64, const MAX_MS: u64> BoundedTime<MIN_MS, MAX_MS> {
+    fn new(millis: u64) -> Result<Self, TagParseError> {
+        if (MIN_MS..=MAX_MS).contains(&millis) {
+            Ok(Self { millis })
+        } else {
+            Err(TagParseError::TimeOutOfRange((millis, MIN_MS, MAX_MS)))
+        }
+    }
+}
+impl<const MIN_MS: u64, const MAX_MS: u64> ParseArgument for BoundedTime<MIN_MS, MAX_MS> {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        Ok(Self(ctxt.next_word(label)?.to_owned()))
+        let Time { millis } = Time::parse_raw_message(ctxt, label).await?;
+        Self::new(millis)
     }
 
     async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
-
-        if let CommandOptionValue::String(ref option) = word {
-            Ok(Word(option.clone()))
-        } else {
-            Err(TagParseError::MismatchedCommandOptionType((
-                "String".to_owned(),
-                word.clone(),
-            )))
-        }
+        let Time { millis } = Time::parse_command_option(ctxt, label).await?;
+        Self::new(millis)
     }
 
     fn as_command_options(name: &str) -> Vec<CommandOption> {
-        vec![StringBuilder::new(name, "word input").required(true).build()]
+        vec![
+            StringBuilder::new(
+                name,
+                format!("time input ({} to {})", format_time(MIN_MS), format_time(MAX_MS)),
+            )
+            .required(true)
+            .build(),
+        ]
     }
 }
 
-/// A single word argument, with autocompletion.
-#[derive(Debug)]
-pub struct WordAutocomplete(pub String);
+/// A point in time relative to now, written as a [`Time`] (e.g. `2h`). By default this is in the
+/// past (so `2h` means 2 hours ago); a leading `+` makes it in the future instead (`+2h` means in 2
+/// hours), and a leading `-` is accepted to be explicit about the past.
+#[derive(Debug, Clone, Copy)]
+pub struct RelativeTime {
+    pub at: SystemTime,
+}
+impl RelativeTime {
+    pub fn parse(input: &str) -> Result<Self, ParseToMillisError> {
+        Self::parse_from(input, SystemTime::now())
+    }
 
-impl ParseArgument for WordAutocomplete {
+    fn parse_from(input: &str, now: SystemTime) -> Result<Self, ParseToMillisError> {
+        let (future, input) = match input.strip_prefix('+') {
+            Some(input) => (true, input),
+            None => (false, input.strip_prefix('-').unwrap_or(input)),
+        };
+
+        let offset = Duration::from_millis(Time::parse(input)?.millis);
+        let at = if future {
+            now.checked_add(offset)
+        } else {
+            now.checked_sub(offset)
+        };
+
+        at.map(|at| Self { at }).ok_or(ParseToMillisError::Overflow)
+    }
+}
+impl ParseArgument for RelativeTime {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        Ok(Self(ctxt.next_word(label)?.to_owned()))
+        let word = ctxt.next_word(label)?;
+
+        Ok(RelativeTime::parse(&word)?)
     }
 
     async fn parse_command_option(
@@ -337,10 +579,10 @@ impl ParseArgument for WordAutocomplete {
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
         if let CommandOptionValue::String(ref option) = word {
-            Ok(WordAutocomplete(option.clone()))
+            Ok(RelativeTime::parse(option.trim())?)
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "String (Word Autocomplete)".to_owned(),
+                "String (relative time)".to_owned(),
                 word.clone(),
             )))
         }
@@ -348,26 +590,29 @@ impl ParseArgument for WordAutocomplete {
 
     fn as_command_options(name: &str) -> Vec<CommandOption> {
         vec![
-            StringBuilder::new(name, "word input")
-                .autocomplete(true)
+            StringBuilder::new(name, "time ago (e.g. 2h), or from now with a + (e.g. +2h)")
                 .required(true)
                 .build(),
         ]
     }
 }
 
-/// A codeblock argument (may also be plaintext).
-#[derive(Debug)]
-pub struct Codeblock(pub String);
-impl ParseArgument for Codeblock {
+/// A file size argument, such as `8MB`, `500KB`, `1.5GB` or `4MiB`, in bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct FileSize(pub u64);
+
+impl FromStr for FileSize {
+    type Err = ParseFileSizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_file_size(s).map(Self)
+    }
+}
+
+impl ParseArgument for FileSize {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        let fork = ctxt.fork();
-        let all = fork.rest_all(label.clone());
-        if all.ends_with("```") {
-            Ok(Codeblock(parse_codeblock(ctxt.rest_all(label))))
-        } else {
-            Ok(Codeblock(parse_codeblock(ctxt.rest(label)?)))
-        }
+        let word = ctxt.next_word(label)?;
+        Ok(word.parse()?)
     }
 
     async fn parse_command_option(
@@ -377,705 +622,3579 @@ impl ParseArgument for Codeblock {
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
         if let CommandOptionValue::String(ref option) = word {
-            Ok(Codeblock(option.clone()))
+            Ok(option.parse()?)
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "String".to_owned(),
+                "String (file size)".to_owned(),
                 word.clone(),
             )))
         }
     }
 
     fn as_command_options(name: &str) -> Vec<CommandOption> {
-        vec![StringBuilder::new(name, "code argument").required(true).build()]
+        vec![StringBuilder::new(name, "file size input").required(true).build()]
     }
 }
 
-/// A user argument (mention or ID)
-#[derive(Debug)]
-pub struct User(pub TwlUser);
-impl ParseArgument for User {
-    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        let next = ctxt.next_word(label)?;
-        let id = user_mention_to_id(next);
+/// An output frame rate argument, such as `30` or `24fps`.
+#[derive(Debug, Clone, Copy)]
+pub struct Fps(pub f64);
 
-        let user = ctxt
-            .cx
-            .assyst()
-            .http_client
-            .user(Id::<UserMarker>::new(id.unwrap_or(next.parse::<u64>().unwrap_or(1))))
-            .await
-            .map_err(|e| TagParseError::TwilightHttp(Box::new(e)))?
-            .model()
-            .await
-            .map_err(|e| TagParseError::TwilightDeserialize(Box::new(e)))?;
+impl Fps {
+    pub const MIN: f64 = 1.0;
+    pub const MAX: f64 = 60.0;
+
+    fn validate(fps: f64) -> Result<Self, TagParseError> {
+        if (Self::MIN..=Self::MAX).contains(&fps) {
+            Ok(Self(fps))
+        } else {
+            Err(TagParseError::FpsOutOfRange(fps))
+        }
+    }
 
-        Ok(User(user))
+    fn parse_str(input: &str) -> Result<Self, TagParseError> {
+        let input = input.trim();
+        let number = input
+            .strip_suffix("fps")
+            .or_else(|| input.strip_suffix("FPS"))
+            .unwrap_or(input);
+
+        Self::validate(number.trim_end().parse()?)
+    }
+}
+
+impl ParseArgument for Fps {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        Self::parse_str(&word)
     }
 
     async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        if let Some(ref us) = &ctxt.cx.data.resolved_users
-            && let Some(u) = us.first()
-        {
-            return Ok(User(u.clone()));
+        let next = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        match next {
+            CommandOptionValue::Number(option) => Self::validate(*option),
+            CommandOptionValue::Integer(option) => Self::validate(*option as f64),
+            CommandOptionValue::String(option) => Self::parse_str(option),
+            _ => Err(TagParseError::MismatchedCommandOptionType((
+                "Number (fps)".to_owned(),
+                next.clone(),
+            ))),
         }
+    }
 
-        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            NumberBuilder::new(name, "frame rate")
+                .min_value(Self::MIN)
+                .max_value(Self::MAX)
+                .required(true)
+                .build(),
+        ]
+    }
+}
 
-        if let CommandOptionValue::User(id) = word {
-            let user = ctxt
-                .cx
-                .assyst()
-                .http_client
-                .user(*id)
-                .await
-                .map_err(|e| TagParseError::TwilightHttp(Box::new(e)))?
-                .model()
-                .await
-                .map_err(|e| TagParseError::TwilightDeserialize(Box::new(e)))?;
+/// A percentage argument between 0 and 100, such as `50`, `50%` or `0.5`.
+///
+/// The value is always stored on the 0 to 100 scale. A bare decimal below 1 (such as `0.5`) is
+/// read as a fraction and scaled up; anything else is read as a percentage, so `1` and `1%` are
+/// both one percent.
+#[derive(Debug, Clone, Copy)]
+pub struct Percentage(pub f64);
+
+impl Percentage {
+    fn validate(percentage: f64) -> Result<Self, TagParseError> {
+        if (0.0..=100.0).contains(&percentage) {
+            Ok(Self(percentage))
+        } else {
+            Err(TagParseError::PercentageOutOfRange(percentage))
+        }
+    }
+
+    fn parse_str(input: &str) -> Result<Self, TagParseError> {
+        let input = input.trim();
+
+        if let Some(percentage) = input.strip_suffix('%') {
+            return Self::validate(percentage.trim_end().parse()?);
+        }
 
-            Ok(User(user))
+        let number = input.parse::<f64>()?;
+        if input.contains('.') && (0.0..1.0).contains(&number) {
+            Self::validate(number * 100.0)
         } else {
-            Err(TagParseError::MismatchedCommandOptionType((
-                "User".to_owned(),
-                word.clone(),
-            )))
+            Self::validate(number)
         }
     }
 
-    fn as_command_options(name: &str) -> Vec<CommandOption> {
-        vec![UserBuilder::new(name, "user argument").required(true).build()]
+    /// The percentage as a fraction between 0.0 and 1.0.
+    pub fn as_fraction(&self) -> f64 {
+        self.0 / 100.0
     }
 }
 
-/// A user argument (mention or ID)
-#[derive(Debug)]
-pub struct Channel(pub TwlChannel);
-impl ParseArgument for Channel {
+impl ParseArgument for Percentage {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        let next = ctxt.next_word(label)?;
-        let id = channel_mention_to_id(next);
-
-        let channel = ctxt
-            .cx
-            .assyst()
-            .http_client
-            .channel(Id::<ChannelMarker>::new(id.unwrap_or(next.parse::<u64>().unwrap_or(1))))
-            .await
-            .map_err(|e| TagParseError::TwilightHttp(Box::new(e)))?
-            .model()
-            .await
-            .map_err(|e| TagParseError::TwilightDeserialize(Box::new(e)))?;
-
-        Ok(Channel(channel))
+        let word = ctxt.next_word(label)?;
+        Self::parse_str(&word)
     }
 
     async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+        let next = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
-        if let CommandOptionValue::Channel(id) = word {
-            let channel = ctxt
-                .cx
-                .assyst()
-                .http_client
-                .channel(*id)
-                .await
-                .map_err(|e| TagParseError::TwilightHttp(Box::new(e)))?
-                .model()
-                .await
-                .map_err(|e| TagParseError::TwilightDeserialize(Box::new(e)))?;
+        match next {
+            // a fraction such as 0.5 is read the same way as in raw messages
+            CommandOptionValue::Number(option) if *option > 0.0 && *option < 1.0 => Self::validate(*option * 100.0),
+            CommandOptionValue::Number(option) => Self::validate(*option),
+            CommandOptionValue::Integer(option) => Self::validate(*option as f64),
+            _ => Err(TagParseError::MismatchedCommandOptionType((
+                "Number (percentage)".to_owned(),
+                next.clone(),
+            ))),
+        }
+    }
 
-            Ok(Channel(channel))
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            NumberBuilder::new(name, "percentage (0 to 100, or a fraction such as 0.5)")
+                .min_value(0.0)
+                .max_value(100.0)
+                .required(true)
+                .build(),
+        ]
+    }
+}
+
+/// An effect strength between 0 and 100, such as `75` or `75%`. This is the convention for every
+/// effect with an adjustable intensity, so that they all behave the same way.
+///
+/// Unlike most arguments, this is optional by itself: if no strength is given, `DEFAULT` is used.
+/// Out of range values are an error, unless `CLAMP` is set, in which case they are clamped.
+#[derive(Debug, Clone, Copy)]
+pub struct Strength<const DEFAULT: u8 = 50, const CLAMP: bool = false>(pub u8);
+
+impl<const DEFAULT: u8, const CLAMP: bool> Strength<DEFAULT, CLAMP> {
+    pub const MAX: u8 = 100;
+
+    fn validate(strength: i64) -> Result<Self, TagParseError> {
+        if (0..=i64::from(Self::MAX)).contains(&strength) {
+            Ok(Self(strength as u8))
+        } else if CLAMP {
+            Ok(Self(strength.clamp(0, i64::from(Self::MAX)) as u8))
         } else {
-            Err(TagParseError::MismatchedCommandOptionType((
-                "Channel".to_owned(),
-                word.clone(),
-            )))
+            Err(TagParseError::StrengthOutOfRange(strength))
         }
     }
 
-    fn as_command_options(name: &str) -> Vec<CommandOption> {
-        vec![ChannelBuilder::new(name, "channel argument").required(true).build()]
+    /// Parses a strength from a word, or gives `None` if it isn't a number at all.
+    fn parse_word(word: &str) -> Option<Result<Self, TagParseError>> {
+        let strength = word.strip_suffix('%').unwrap_or(word).parse::<i64>().ok()?;
+        Some(Self::validate(strength))
+    }
+
+    /// The strength as a fraction between 0.0 and 1.0.
+    pub fn as_fraction(&self) -> f64 {
+        f64::from(self.0) / f64::from(Self::MAX)
     }
 }
 
-/// The rest of a message as an argument. This should be the last argument if used.
-#[derive(Debug)]
-pub struct Rest(pub String);
+impl<const DEFAULT: u8, const CLAMP: bool> Default for Strength<DEFAULT, CLAMP> {
+    fn default() -> Self {
+        Self(DEFAULT.min(Self::MAX))
+    }
+}
 
-impl ParseArgument for Rest {
+impl<const DEFAULT: u8, const CLAMP: bool> ParseArgument for Strength<DEFAULT, CLAMP> {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        if let Ok(r) = ctxt.rest(label.clone()) {
-            Ok(Self(r))
-        } else if let Some(m) = ctxt.cx.data.message {
-            if let Some(ref r) = m.referenced_message {
-                Ok(Self(r.content.clone()))
-            } else {
-                Ok(Self(ctxt.rest(label)?))
-            }
-        } else {
-            Ok(Self(ctxt.rest(label)?))
-        }
+        // anything that isn't a number is left for the next argument, and the default is used
+        let Some(strength) = ctxt.peek_word().and_then(|w| Self::parse_word(&w)) else {
+            return Ok(Self::default());
+        };
+
+        ctxt.next_word(label)?;
+        strength
     }
 
     async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        // look for resolved messages: if so, it's a context menu command, and use that message
-        if let Some(ref ms) = ctxt.cx.data.resolved_messages
-            && let Some(m) = ms.first()
-        {
-            if m.content.is_empty() {
-                return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
-            }
-            return Ok(Rest(m.content.clone()));
-        }
-
-        // treat Rest as same as Word because there is no option type which is just one
-        // whitespace-delimited word
-        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+        let Ok(option) = ctxt.option_by_name(&label.unwrap().0) else {
+            return Ok(Self::default());
+        };
 
-        if let CommandOptionValue::String(ref option) = word {
-            Ok(Rest(option.clone()))
+        if let CommandOptionValue::Integer(strength) = option.value {
+            Self::validate(strength)
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "String (Rest)".to_owned(),
-                word.clone(),
+                "Integer (strength)".to_owned(),
+                option.value.clone(),
             )))
         }
     }
 
     fn as_command_options(name: &str) -> Vec<CommandOption> {
-        vec![StringBuilder::new(name, "text input").required(true).build()]
+        let mut builder = IntegerBuilder::new(name, format!("strength, 0 to 100 (default {DEFAULT})")).required(false);
+        if !CLAMP {
+            builder = builder.min_value(0).max_value(i64::from(Self::MAX));
+        }
+
+        vec![builder.build()]
     }
 
     fn usage(name: &str) -> String {
-        format!("<...{name}>")
+        format!("[{name}]")
     }
 }
 
-/// The rest of a message as an argument, and asserts that there is no following flag argument. This
-/// should be the last argument if used.
-#[derive(Debug)]
-pub struct RestNoFlags(pub String);
+/// A video codec, for commands that let the output codec be chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    H264,
+    Vp9,
+}
 
-impl ParseArgument for RestNoFlags {
-    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        let all = ctxt.rest_all(label.clone());
-        if !all.is_empty() {
-            Ok(Self(all))
-        } else if let Some(m) = ctxt.cx.data.message
-            && let Some(ref r) = m.referenced_message
-        {
-            Ok(Self(r.content.clone()))
-        } else {
-            Err(TagParseError::ArgsExhausted(ArgsExhausted(label)))
+impl Codec {
+    /// The highest (worst quality) CRF value this codec accepts. The lowest is always 0.
+    pub fn max_crf(&self) -> u8 {
+        match self {
+            Codec::H264 => 51,
+            Codec::Vp9 => 63,
+        }
+    }
+}
+
+impl Choice for Codec {
+    const CHOICES: &'static [(&'static str, Self)] = &[("h264", Codec::H264), ("vp9", Codec::Vp9)];
+}
+
+impl Display for Codec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, _) = Self::CHOICES.iter().find(|(_, c)| c == self).unwrap();
+        f.write_str(name)
+    }
+}
+
+/// An output video quality: either a percentage such as `75` or `75%` (higher is better), which is
+/// mapped onto the range of whichever codec is used, or a codec-specific CRF value such as `crf:23`
+/// (lower is better), which is checked against the codec's range once it is known.
+#[derive(Debug, Clone, Copy)]
+pub enum Quality {
+    Percentage(u8),
+    Crf(u8),
+}
+
+impl Quality {
+    /// The CRF value to encode with, for the given codec.
+    pub fn crf(&self, codec: Codec) -> Result<u8, TagParseError> {
+        let max = codec.max_crf();
+
+        match *self {
+            Quality::Percentage(percentage) => {
+                Ok(((f64::from(100 - percentage) / 100.0) * f64::from(max)).round() as u8)
+            },
+            Quality::Crf(crf) if crf <= max => Ok(crf),
+            Quality::Crf(crf) => Err(TagParseError::CrfOutOfRange((crf, codec))),
+        }
+    }
+}
+
+impl FromStr for Quality {
+    type Err = TagParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(crf) = s.strip_prefix("crf:") {
+            return Ok(Quality::Crf(crf.parse()?));
         }
+
+        let Percentage(percentage) = Percentage::parse_str(s)?;
+        Ok(Quality::Percentage(percentage.round() as u8))
+    }
+}
+
+impl ParseArgument for Quality {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        word.parse()
     }
 
     async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        // look for resolved messages: if so, it's a context menu command, and use that message
-        if let Some(ref ms) = ctxt.cx.data.resolved_messages
-            && let Some(m) = ms.first()
-        {
-            if m.content.is_empty() {
-                return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
-            }
-            return Ok(RestNoFlags(m.content.clone()));
-        }
-
-        // treat Rest as same as Word because there is no option type which is just one
-        // whitespace-delimited word
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
         if let CommandOptionValue::String(ref option) = word {
-            Ok(RestNoFlags(option.clone()))
+            option.trim().parse()
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "String (Rest)".to_owned(),
+                "String (quality)".to_owned(),
                 word.clone(),
             )))
         }
     }
 
     fn as_command_options(name: &str) -> Vec<CommandOption> {
-        vec![StringBuilder::new(name, "text input").required(true).build()]
+        vec![
+            StringBuilder::new(name, "quality, 0 to 100 (or crf:N for a specific CRF)")
+                .required(true)
+                .build(),
+        ]
     }
+}
 
-    fn usage(name: &str) -> String {
-        format!("<...{name}>")
+/// A position to anchor something at, such as `top-left`, `top left` or `center`, for commands that
+/// place or crop media. If no position is given, `center` is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// The position as `(x, y)` fractions of the available space, from `(0.0, 0.0)` (top left) to
+    /// `(1.0, 1.0)` (bottom right).
+    pub fn fractions(&self) -> (f64, f64) {
+        match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        }
     }
 }
 
-pub struct ImageUrl(pub String);
+impl Choice for Anchor {
+    const CHOICES: &'static [(&'static str, Self)] = &[
+        ("top-left", Anchor::TopLeft),
+        ("top", Anchor::Top),
+        ("top-right", Anchor::TopRight),
+        ("left", Anchor::Left),
+        ("center", Anchor::Center),
+        ("right", Anchor::Right),
+        ("bottom-left", Anchor::BottomLeft),
+        ("bottom", Anchor::Bottom),
+        ("bottom-right", Anchor::BottomRight),
+    ];
+}
 
-impl ImageUrl {
-    async fn from_mention_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        let word = ctxt.next_word(label)?;
+impl FromStr for Anchor {
+    type Err = TagParseError;
 
-        let user_id = id_from_mention(word).ok_or(TagParseError::NoMention)?;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s
+            .split([' ', '-', '_'])
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        Self::from_name(&name)
+    }
+}
 
-        if user_id == 0 {
-            return Err(TagParseError::NoMention);
-        }
+impl ParseArgument for Anchor {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let mut fork = ctxt.fork();
+        let Ok(first) = fork.next_word(label.clone()) else {
+            return Ok(Self::default());
+        };
 
-        let user = ctxt
-            .cx
-            .assyst()
-            .http_client
-            .user(Id::new(user_id))
-            .await?
-            .model()
-            .await?;
+        // space-separated positions, such as `top left`, span two words
+        let mut second = fork.fork();
+        if let Ok(next) = second.next_word(label)
+            && let Ok(anchor) = format!("{first}-{next}").parse()
+        {
+            *ctxt = second;
+            return Ok(anchor);
+        }
 
-        Ok(Self(get_avatar_url(&user)))
+        let anchor = first.parse()?;
+        *ctxt = fork;
+        Ok(anchor)
     }
 
-    async fn from_mention_command_option(
+    async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
-
-        if let CommandOptionValue::String(ref option) = word {
-            let user_id = id_from_mention(option).ok_or(TagParseError::NoMention)?;
-
-            if user_id == 0 {
-                return Err(TagParseError::NoMention);
-            }
-
-            let user = ctxt
-                .cx
-                .assyst()
-                .http_client
-                .user(Id::new(user_id))
-                .await?
-                .model()
-                .await?;
+        let Ok(option) = ctxt.option_by_name(&label.unwrap().0) else {
+            return Ok(Self::default());
+        };
 
-            Ok(Self(get_avatar_url(&user)))
+        if let CommandOptionValue::String(ref anchor) = option.value {
+            anchor.parse()
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "String (mention aregument)".to_owned(),
-                word.clone(),
+                "String (position)".to_owned(),
+                option.value.clone(),
             )))
         }
     }
 
-    async fn from_url_argument_raw_message(
-        ctxt: &mut RawMessageParseCtxt<'_>,
-        label: Label,
-    ) -> Result<Self, TagParseError> {
-        let word = ctxt.next_word(label)?;
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            Self::command_option(name, "position (default center)")
+                .required(false)
+                .build(),
+        ]
+    }
 
-        if regex::URL.is_match(word) {
-            Ok(Self(word.to_owned()))
-        } else {
-            Err(TagParseError::NoUrl)
-        }
+    fn usage(name: &str) -> String {
+        format!("[{name}]")
     }
+}
 
-    async fn from_url_argument_command_option(
+/// A colour argument, such as `#ff0000`, `0xff0000`, `rgb(255,0,0)` or `red`, as packed `0xRRGGBB`.
+#[derive(Debug, Clone, Copy)]
+pub struct Colour(pub u32);
+
+impl ParseArgument for Colour {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        Ok(Self(parse_colour(&word)?))
+    }
+
+    async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
         if let CommandOptionValue::String(ref option) = word {
-            if regex::URL.is_match(option) {
-                Ok(Self(option.to_owned()))
-            } else {
-                Err(TagParseError::NoUrl)
-            }
+            Ok(Self(parse_colour(option)?))
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "String (url argument)".to_owned(),
+                "String (colour)".to_owned(),
                 word.clone(),
             )))
         }
     }
 
-    fn attachment(attachment: Option<&Attachment>) -> Result<Self, TagParseError> {
-        let attachment = attachment.ok_or(TagParseError::NoAttachment)?;
-        Ok(Self(attachment.url.clone()))
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            StringBuilder::new(name, "colour (hex code, rgb(r,g,b) or name)")
+                .required(true)
+                .build(),
+        ]
     }
+}
 
-    async fn from_attachment_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
-        Self::attachment(ctxt.cx.data.message.as_ref().unwrap().attachments.first())
+/// A colour argument with an alpha channel, such as `#ff000080`, `rgba(255,0,0,0.5)` or `red@128`,
+/// as packed `0xRRGGBBAA`. Anything accepted by [`Colour`] is also accepted, and is fully opaque.
+#[derive(Debug, Clone, Copy)]
+pub struct ColourAlpha(pub u32);
+
+impl ColourAlpha {
+    pub fn r(&self) -> u8 {
+        self.0.to_be_bytes()[0]
     }
 
-    async fn from_attachment_interaction_command(
+    pub fn g(&self) -> u8 {
+        self.0.to_be_bytes()[1]
+    }
+
+    pub fn b(&self) -> u8 {
+        self.0.to_be_bytes()[2]
+    }
+
+    pub fn a(&self) -> u8 {
+        self.0.to_be_bytes()[3]
+    }
+}
+
+impl ParseArgument for ColourAlpha {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        Ok(Self(parse_colour_alpha(&word)?))
+    }
+
+    async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
-        if let CommandOptionValue::Attachment(ref option) = word {
-            let attachment = ctxt.cx.data.interaction_attachments.get(option);
-            let attachment = attachment.ok_or(TagParseError::NoAttachment)?;
-            Self::attachment(Some(attachment))
+        if let CommandOptionValue::String(ref option) = word {
+            Ok(Self(parse_colour_alpha(option)?))
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "Attachment".to_owned(),
+                "String (colour)".to_owned(),
                 word.clone(),
             )))
         }
     }
 
-    async fn from_link_interaction_command(
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            StringBuilder::new(name, "colour (hex code, rgba(r,g,b,a) or name, e.g. red@0.5)")
+                .required(true)
+                .build(),
+        ]
+    }
+}
+
+/// A user-supplied regular expression argument.
+///
+/// The regex engine never backtracks, so matching always runs in time linear to the input and
+/// patterns can't cause catastrophic backtracking (ReDoS). What a pattern can still do is take a
+/// lot of time and memory to compile (e.g. `\w{1000}{1000}`), so the compiled size is limited,
+/// and patterns over the limit are rejected.
+#[derive(Debug)]
+pub struct UserRegex(pub ::regex::Regex);
+
+impl UserRegex {
+    /// The maximum size of a compiled pattern, in bytes.
+    pub const SIZE_LIMIT: usize = 1024 * 1024;
+    /// The maximum size of the cache used to match a pattern lazily, in bytes.
+    pub const DFA_SIZE_LIMIT: usize = 1024 * 1024;
+
+    fn compile(pattern: &str) -> Result<Self, TagParseError> {
+        let regex = ::regex::RegexBuilder::new(pattern)
+            .size_limit(Self::SIZE_LIMIT)
+            .dfa_size_limit(Self::DFA_SIZE_LIMIT)
+            .build()?;
+
+        Ok(Self(regex))
+    }
+}
+
+impl ParseArgument for UserRegex {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        Self::compile(&word)
+    }
+
+    async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
-        if let CommandOptionValue::String(option) = word {
-            Ok(Self(option.clone()))
+        if let CommandOptionValue::String(ref option) = word {
+            Self::compile(option)
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "Link".to_owned(),
+                "String (regex)".to_owned(),
                 word.clone(),
             )))
         }
     }
 
-    /// This only exists for raw message
-    async fn from_reply(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
-        let reply = ctxt
-            .cx
-            .data
-            .message
-            .as_ref()
-            .unwrap()
-            .referenced_message
-            .as_deref()
-            .ok_or(TagParseError::NoReply)?;
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "regular expression").required(true).build()]
+    }
+}
 
-        if let Some(attachment) = reply.attachments.first() {
-            return Ok(Self(attachment.url.clone()));
-        }
+/// A type with a fixed set of named values, such as a list of effects.
+pub trait Choice: Sized + Copy + PartialEq + Send + Sync + 'static {
+    /// Every valid value, along with the name used to select it.
+    const CHOICES: &'static [(&'static str, Self)];
+
+    /// Looks up a value by its name, case insensitively.
+    fn from_name(name: &str) -> Result<Self, TagParseError> {
+        Self::CHOICES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+            .ok_or_else(|| {
+                TagParseError::InvalidChoice((name.to_owned(), Self::CHOICES.iter().map(|(n, _)| *n).collect()))
+            })
+    }
 
-        macro_rules! handle {
-            ($v:expr) => {
-                match $v {
-                    Ok(v) => return Ok(v),
-                    Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
-                    _ => {},
-                }
-            };
-        }
+    /// A string command option that offers every value as a choice, instead of free text.
+    fn command_option(name: &str, description: &str) -> StringBuilder {
+        StringBuilder::new(name, description).choices(Self::CHOICES.iter().map(|(n, _)| (*n, *n)))
+    }
+}
 
-        handle!(Self::sticker(reply.sticker_items.first()));
-        handle!(Self::embed(reply.embeds.first()));
-        handle!(Self::emoji(&mut ctxt.cx, &reply.content).await);
+/// Implements [`ParseArgument`] for a [`Choice`], taking a single word (or a string option offering
+/// each choice) as its name.
+macro_rules! choice_argument {
+    ($ty:ty, $description:literal) => {
+        impl ParseArgument for $ty {
+            async fn parse_raw_message(
+                ctxt: &mut RawMessageParseCtxt<'_>,
+                label: Label,
+            ) -> Result<Self, TagParseError> {
+                let word = ctxt.next_word(label)?;
+                Self::from_name(&word)
+            }
 
-        Err(TagParseError::NoReply)
-    }
+            async fn parse_command_option(
+                ctxt: &mut InteractionCommandParseCtxt<'_>,
+                label: Label,
+            ) -> Result<Self, TagParseError> {
+                let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
-    fn embed(embed: Option<&Embed>) -> Result<Self, TagParseError> {
-        let embed = embed.ok_or(TagParseError::NoEmbed)?;
+                if let CommandOptionValue::String(ref option) = word {
+                    Self::from_name(option)
+                } else {
+                    Err(TagParseError::MismatchedCommandOptionType((
+                        concat!("String (", $description, ")").to_owned(),
+                        word.clone(),
+                    )))
+                }
+            }
 
-        if let Some(url) = &embed.url
-            && url.starts_with("https://tenor.com/view/")
-        {
-            return Ok(Self(url.clone()));
+            fn as_command_options(name: &str) -> Vec<CommandOption> {
+                vec![Self::command_option(name, $description).required(true).build()]
+            }
         }
+    };
+}
 
-        if let Some(image) = &embed.image {
-            return Ok(Self(image.url.clone()));
-        }
+choice_argument!(Codec, "video codec");
 
-        if let Some(thumbnail) = &embed.thumbnail {
-            return Ok(Self(thumbnail.url.clone()));
-        }
+/// A comma-separated set of [`Choice`] values, such as `blur,invert,sharpen`. Duplicates are
+/// removed, and the order they were first given in is preserved.
+#[derive(Debug, Clone)]
+pub struct Set<T>(pub Vec<T>);
 
-        if let Some(video) = &embed.video
-            && let Some(url) = &video.url
-        {
-            Ok(Self(url.clone()))
-        } else {
-            Err(TagParseError::NoEmbed)
-        }
-    }
+impl<T: Choice> FromStr for Set<T> {
+    type Err = TagParseError;
 
-    async fn emoji(ctxt: &mut CommandCtxt<'_>, word: &str) -> Result<Self, TagParseError> {
-        #[derive(Deserialize)]
-        struct TwemojiVendorImage {
-            pub twitter: String,
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut values = Vec::new();
 
-        #[derive(Deserialize)]
-        struct TwemojiLookup {
-            pub vendor_images: TwemojiVendorImage,
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let value = T::from_name(name)?;
+            if !values.contains(&value) {
+                values.push(value);
+            }
         }
 
-        if let Some(e) = emoji::lookup_by_glyph::lookup(word) {
-            let codepoint = e.codepoint.to_lowercase().replace(' ', "-").replace("-fe0f", "");
-
-            let emoji_url = format!("https://bignutty.gitlab.io/emojipedia-data/data/{codepoint}.json");
-            let dl = ctxt
-                .assyst()
-                .reqwest_client
-                .get(emoji_url)
-                .send()
-                .await?
-                .json::<TwemojiLookup>()
-                .await?;
-
-            Ok(Self(dl.vendor_images.twitter))
-        } else {
-            Err(TagParseError::NoEmoji)
+        if values.is_empty() {
+            return Err(TagParseError::InvalidChoice((
+                s.to_owned(),
+                T::CHOICES.iter().map(|(n, _)| *n).collect(),
+            )));
         }
+
+        Ok(Self(values))
     }
+}
 
-    async fn from_emoji_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+impl<T: Choice> ParseArgument for Set<T> {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        Self::emoji(&mut ctxt.cx, word).await
+        word.parse()
     }
 
-    async fn from_emoji_command_option(
+    async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
         let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
         if let CommandOptionValue::String(ref option) = word {
-            if regex::URL.is_match(option) {
-                Ok(Self::emoji(&mut ctxt.cx, option).await?)
-            } else {
-                Err(TagParseError::NoUrl)
-            }
+            option.parse()
         } else {
             Err(TagParseError::MismatchedCommandOptionType((
-                "String (emoji argument)".to_owned(),
+                "String (comma-separated set)".to_owned(),
                 word.clone(),
             )))
         }
     }
 
-    fn sticker(sticker: Option<&MessageSticker>) -> Result<Self, TagParseError> {
-        let sticker = sticker.ok_or(TagParseError::NoSticker)?;
-        match sticker.format_type {
-            StickerFormatType::Png => Ok(Self(format!("https://cdn.discordapp.com/stickers/{}.png", sticker.id))),
-            _ => Err(TagParseError::UnsupportedSticker(sticker.format_type)),
-        }
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            StringBuilder::new(name, "comma-separated list of values")
+                .required(true)
+                .build(),
+        ]
     }
 
-    /// This only exists for raw message
-    async fn from_sticker(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
-        Self::sticker(ctxt.cx.data.message.as_ref().unwrap().sticker_items.first())
+    fn usage(name: &str) -> String {
+        format!("<{name},...>")
     }
+}
 
-    // Defined separately without a CommandCtxt because it is also used elsewhere where we don't
-    // have one (and this also doesn't need it)
-    pub async fn from_channel_history(
-        assyst: &Assyst,
-        channel_id: Id<ChannelMarker>,
-    ) -> Result<ImageUrl, TagParseError> {
-        let messages = match assyst.http_client.channel_messages(channel_id).await {
-            Ok(m) => m.models().await?,
-            Err(_) => return Err(TagParseError::FailedToGetMessageHistory),
-        };
-
-        macro_rules! handle {
-            ($v:expr) => {
-                // Ignore any error, even high severity ones, since not doing that would mean
-                // we bail when we see a "random" malformed message in a channel
-                if let Ok(v) = $v {
-                    return Ok(v);
-                }
-            };
-        }
+/// A seed for commands with randomness, so that their output can be reproduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seed(pub u64);
 
-        for message in messages {
-            handle!(Self::embed(message.embeds.first()));
-            handle!(Self::sticker(message.sticker_items.first()));
-            handle!(Self::sticker(message.sticker_items.first()));
-            handle!(Self::attachment(message.attachments.first()));
-        }
+impl Seed {
+    /// Generates a new random seed. Kept within Discord's integer option range so that it can
+    /// always be passed back in through a slash command.
+    pub fn random() -> Self {
+        Self(thread_rng().gen_range(0..1 << 53))
+    }
 
-        Err(TagParseError::NoImageInHistory)
+    /// Creates a RNG from this seed.
+    pub fn rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.0)
     }
-}
 
-impl Display for ImageUrl {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+    fn parse_str(input: &str) -> Result<Self, TagParseError> {
+        Ok(Self(input.parse()?))
     }
 }
 
-impl ParseArgument for ImageUrl {
+impl ParseArgument for Seed {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        async fn combined_parsers(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<ImageUrl, TagParseError> {
-            macro_rules! handle {
-                ($v:expr) => {
-                    match $v {
-                        Ok(r) => return Ok(r),
-                        Err(err) if let TagParseError::TwilightHttp(_) = err => {},
-                        Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
-                        _ => {},
-                    }
-                };
-            }
+        let word = ctxt.next_word(label)?;
+        Self::parse_str(&word)
+    }
 
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_mention_raw_message, label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_url_argument_raw_message, label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_attachment_raw_message, label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_reply, label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_emoji_raw_message, label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_sticker, label));
-            if !ctxt.cx.data.command_from_install_context {
-                handle!(ImageUrl::from_channel_history(ctxt.cx.assyst(), ctxt.cx.data.channel_id).await);
-            } else {
-                return Err(TagParseError::MessageHistoryUnavailableInContext);
-            };
-            Err(TagParseError::NoImageFound)
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let next = &ctxt.option_by_name(&label.unwrap().0)?.value;
+        if let CommandOptionValue::Integer(option) = next {
+            Ok(Self(u64::try_from(*option)?))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "Integer (seed)".to_owned(),
+                next.clone(),
+            )))
         }
+    }
 
-        let ImageUrl(mut url) = combined_parsers(ctxt, label).await?;
-
-        // tenor urls only typically return a png, so this code visits the url
-        // and extracts the appropriate GIF url from the page.
-        if url.starts_with("https://tenor.com/view") {
-            let page = ctxt.cx.assyst().reqwest_client.get(&url).send().await?.text().await?;
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            IntegerBuilder::new(name, "seed, to reproduce a previous result")
+                .min_value(0)
+                .required(true)
+                .build(),
+        ]
+    }
+}
 
-            let gif_url = regex::TENOR_GIF.find(&page).ok_or(TagParseError::MediaDownloadFail)?;
-            url = gif_url.as_str().to_owned();
-        }
+/// A single word argument.
+#[derive(Debug)]
+pub struct Word(pub String);
 
-        Ok(Self(url))
+impl ParseArgument for Word {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        Ok(Self(ctxt.next_word(label)?.into_owned()))
     }
 
     async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        async fn combined_parsers(
-            ctxt: &mut InteractionCommandParseCtxt<'_>,
-            label: Label,
-        ) -> Result<ImageUrl, TagParseError> {
-            macro_rules! handle {
-                ($v:expr) => {
-                    match $v {
-                        Ok(r) => return Ok(r),
-                        Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
-                        _ => {},
-                    }
-                };
-            }
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
-            // if this is Some, this is a context menu command
-            // we must have our image defined here, instead of looking anywhere else
-            if let Some(ref r) = ctxt.cx.data.resolved_messages {
-                if let Some(m) = r.first()
-                    && let Some(i) = m.attachments.first()
-                {
-                    return ImageUrl::attachment(Some(i));
-                } else if let Some(m) = r.first()
-                    && let Some(e) = m.embeds.first()
-                {
-                    return ImageUrl::embed(Some(e));
-                } else {
-                    return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
-                }
-            }
+        if let CommandOptionValue::String(ref option) = word {
+            Ok(Word(option.clone()))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
 
-            let attachment_label = Some((
-                format!("{}-attachment", label.clone().unwrap().0),
-                label.clone().unwrap().1,
-            ));
-            let link_label = Some((format!("{}-link", label.clone().unwrap().0), label.clone().unwrap().1));
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "word input").required(true).build()]
+    }
+}
 
-            handle!(commit_if_ok!(
-                ctxt,
-                ImageUrl::from_attachment_interaction_command,
-                attachment_label
-            ));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_link_interaction_command, link_label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_mention_command_option, label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_url_argument_command_option, label));
-            handle!(commit_if_ok!(ctxt, ImageUrl::from_emoji_command_option, label));
-            handle!(ImageUrl::from_channel_history(ctxt.cx.assyst(), ctxt.cx.data.channel_id).await);
-            Err(TagParseError::NoImageFound)
-        }
+/// A single word argument, or several words surrounded by double quotes (e.g. `"some title"`).
+/// Unlike [`Word`], quoted text is taken literally: backslashes within it are kept rather than
+/// treated as escapes, which suits arguments such as file paths.
+#[derive(Debug)]
+pub struct QuotedWord(pub String);
 
-        let ImageUrl(mut url) = combined_parsers(ctxt, label).await?;
+impl ParseArgument for QuotedWord {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        Ok(Self(ctxt.next_quoted_word(label)?.into_owned()))
+    }
 
-        // tenor urls only typically return a png, so this code visits the url
-        // and extracts the appropriate GIF url from the page.
-        if url.starts_with("https://tenor.com/view") {
-            let page = ctxt.cx.assyst().reqwest_client.get(&url).send().await?.text().await?;
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
-            let gif_url = regex::TENOR_GIF.find(&page).ok_or(TagParseError::MediaDownloadFail)?;
-            url = gif_url.as_str().to_owned();
+        if let CommandOptionValue::String(ref option) = word {
+            Ok(QuotedWord(option.clone()))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String".to_owned(),
+                word.clone(),
+            )))
         }
-
-        Ok(Self(url))
     }
 
     fn as_command_options(name: &str) -> Vec<CommandOption> {
-        let attachment_name = format!("{name}-attachment");
-        let link_name = format!("{name}-link");
-
-        vec![
-            AttachmentBuilder::new(attachment_name, "attachment input")
-                .required(false)
-                .build(),
-            StringBuilder::new(link_name, "link input").required(false).build(),
-        ]
+        vec![StringBuilder::new(name, "text input").required(true).build()]
     }
 }
 
-pub struct Image(pub Vec<u8>);
+/// A single word argument, with autocompletion.
+#[derive(Debug)]
+pub struct WordAutocomplete(pub String);
 
-impl ParseArgument for Image {
+impl ParseArgument for WordAutocomplete {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        let ImageUrl(url) = ImageUrl::parse_raw_message(ctxt, label).await?;
-
-        let data = downloader::download_content(
-            &ctxt.cx.assyst().reqwest_client,
-            &url,
-            ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES,
-            true,
-        )
-        .await?;
-        Ok(Image(data))
+        Ok(Self(ctxt.next_word(label)?.into_owned()))
     }
 
     async fn parse_command_option(
         ctxt: &mut InteractionCommandParseCtxt<'_>,
         label: Label,
     ) -> Result<Self, TagParseError> {
-        let ImageUrl(url) = ImageUrl::parse_command_option(ctxt, label).await?;
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
 
-        let data = downloader::download_content(
-            &ctxt.cx.assyst().reqwest_client,
-            &url,
-            ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES,
-            true,
-        )
-        .await?;
-        Ok(Image(data))
+        if let CommandOptionValue::String(ref option) = word {
+            Ok(WordAutocomplete(option.clone()))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (Word Autocomplete)".to_owned(),
+                word.clone(),
+            )))
+        }
     }
 
     fn as_command_options(name: &str) -> Vec<CommandOption> {
-        let attachment_name = format!("{name}-attachment");
-        let link_name = format!("{name}-link");
-
         vec![
-            AttachmentBuilder::new(attachment_name, "attachment input")
-                .required(false)
+            StringBuilder::new(name, "word input")
+                .autocomplete(true)
+                .required(true)
                 .build(),
-            StringBuilder::new(link_name, "link input").required(false).build(),
         ]
     }
 }
+
+/// A codeblock argument (may also be plaintext).
+#[derive(Debug)]
+pub struct Codeblock {
+    /// The language tag of a fenced codeblock, if one was given.
+    pub language: Option<String>,
+    pub code: String,
+}
+impl Codeblock {
+    /// Strips fenced (```` ```lang\n...``` ````, ```` ```...``` ````) or inline (`` `...` ``)
+    /// markdown from the input. Anything else is treated as code with no language.
+    fn parse(input: &str) -> Self {
+        let trimmed = input.trim();
+
+        if let Some(inner) = trimmed.strip_prefix("```") {
+            // an unterminated fence just means everything after the opening fence is code
+            let inner = inner.strip_suffix("```").unwrap_or(inner);
+
+            // the first line is only a language tag if more code follows it on the next line, as in
+            // Discord's rendering, so that e.g. ```` ```hello``` ```` or ```` ```hello\n``` ```` is code
+            match inner.split_once('\n') {
+                Some((language, code))
+                    if !language.trim().is_empty() && !language.trim().contains(' ') && !code.trim().is_empty() =>
+                {
+                    Self {
+                        language: Some(language.trim().to_owned()),
+                        code: code.to_owned(),
+                    }
+                },
+                Some((first, code)) if first.trim().is_empty() => Self {
+                    language: None,
+                    code: code.to_owned(),
+                },
+                _ => Self {
+                    language: None,
+                    code: inner.to_owned(),
+                },
+            }
+        } else if let Some(inner) = trimmed.strip_prefix('`').and_then(|x| x.strip_suffix('`')) {
+            Self {
+                language: None,
+                code: inner.to_owned(),
+            }
+        } else {
+            Self {
+                language: None,
+                code: input.to_owned(),
+            }
+        }
+    }
+}
+impl ParseArgument for Codeblock {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let fork = ctxt.fork();
+        let all = fork.rest_all(label.clone());
+        if all.ends_with("```") {
+            Ok(Codeblock::parse(&ctxt.rest_all(label)))
+        } else {
+            Ok(Codeblock::parse(&ctxt.rest(label)?))
+        }
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::String(ref option) = word {
+            Ok(Codeblock::parse(option))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "code argument").required(true).build()]
+    }
+}
+
+/// A user argument (mention or ID)
+#[derive(Debug)]
+pub struct User(pub TwlUser);
+impl User {
+    /// Parses a user mention or ID, without fetching the user.
+    fn id_from_word(word: &str) -> Result<Id<UserMarker>, TagParseError> {
+        user_mention_to_id(word)
+            .or_else(|| word.parse::<u64>().ok())
+            .and_then(Id::<UserMarker>::new_checked)
+            .ok_or(TagParseError::NoMention)
+    }
+}
+impl ParseArgument for User {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let id = User::id_from_word(&ctxt.next_word(label)?)?;
+
+        Ok(User(ctxt.fetch_user(id).await?))
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        if let Some(ref us) = &ctxt.cx.data.resolved_users
+            && let Some(u) = us.first()
+        {
+            return Ok(User(u.clone()));
+        }
+
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::User(id) = word {
+            Ok(User(ctxt.fetch_user(*id).await?))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "User".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![UserBuilder::new(name, "user argument").required(true).build()]
+    }
+}
+
+/// The most users a `Vec<User>` argument accepts.
+pub const MAX_USERS_PER_ARGUMENT: usize = 25;
+
+/// Up to [`MAX_USERS_PER_ARGUMENT`] user mentions or IDs. All of the users are collected before
+/// any are fetched, so that they can be resolved together (see
+/// [`ParseCtxt::fetch_users`](super::ParseCtxt::fetch_users)) rather than one after another.
+/// Users that don't exist are left out. For interactions, this is a single string option of
+/// mentions or IDs, separated by whitespace.
+impl ParseArgument for Vec<User> {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
+        let mut ids = Vec::new();
+        while let Some(id) = ctxt.peek_word().and_then(|word| User::id_from_word(&word).ok()) {
+            if ids.len() == MAX_USERS_PER_ARGUMENT {
+                return Err(TagParseError::TooManyUsers(MAX_USERS_PER_ARGUMENT));
+            }
+
+            ids.push(id);
+            ctxt.next_word(None)?;
+        }
+
+        Ok(ctxt.fetch_users(&ids).await.into_iter().map(User).collect())
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let Word(text) = Word::parse_command_option(ctxt, label).await?;
+        let ids = text
+            .split_ascii_whitespace()
+            .map(User::id_from_word)
+            .collect::<Result<Vec<_>, _>>()?;
+        if ids.len() > MAX_USERS_PER_ARGUMENT {
+            return Err(TagParseError::TooManyUsers(MAX_USERS_PER_ARGUMENT));
+        }
+
+        Ok(ctxt.fetch_users(&ids).await.into_iter().map(User).collect())
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            StringBuilder::new(name, "space-separated user mentions or IDs")
+                .required(true)
+                .build(),
+        ]
+    }
+
+    fn usage(name: &str) -> String {
+        format!("<{name}[]>")
+    }
+}
+
+/// A raw Discord ID (snowflake) argument, such as `1099115731301449758`. The ID is only validated
+/// to look like a snowflake (including that its timestamp isn't in the future), and is not
+/// resolved.
+#[derive(Debug, Clone, Copy)]
+pub struct Snowflake<M>(pub Id<M>);
+
+/// The first millisecond of 2015, which snowflake timestamps are relative to.
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+/// How far in the future a snowflake's timestamp may be, to allow for clock skew.
+const SNOWFLAKE_CLOCK_SKEW_MS: u64 = 60_000;
+
+impl<M> Snowflake<M> {
+    /// When this ID was created, in milliseconds since the Unix epoch.
+    pub fn timestamp_ms(&self) -> u64 {
+        (self.0.get() >> 22) + DISCORD_EPOCH_MS
+    }
+}
+
+impl<M> FromStr for Snowflake<M> {
+    type Err = TagParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the same range of lengths that mentions accept
+        if !(16..=20).contains(&s.len()) || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(TagParseError::InvalidSnowflake(s.to_owned()));
+        }
+
+        s.parse::<u64>()
+            .ok()
+            .and_then(Id::new_checked)
+            .map(Self)
+            // an ID from the future is almost certainly just a long number
+            .filter(|id| id.timestamp_ms() <= unix_timestamp() + SNOWFLAKE_CLOCK_SKEW_MS)
+            .ok_or_else(|| TagParseError::InvalidSnowflake(s.to_owned()))
+    }
+}
+
+impl<M: Send + Sync> ParseArgument for Snowflake<M> {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        word.parse()
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        // snowflakes don't fit in an integer option, so they're usually passed as a string, but
+        // accept (smaller) integers too
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        match word {
+            CommandOptionValue::String(option) => option.trim().parse(),
+            CommandOptionValue::Integer(option) => option.to_string().parse(),
+            _ => Err(TagParseError::MismatchedCommandOptionType((
+                "String (ID)".to_owned(),
+                word.clone(),
+            ))),
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "ID argument").required(true).build()]
+    }
+}
+
+/// A channel mention or ID argument. Unlike [`Channel`], the channel is not fetched. For
+/// interactions, the resolved channel (e.g. to check its type) is in
+/// [`CommandData::interaction_channels`](super::CommandData::interaction_channels).
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelMention(pub Id<ChannelMarker>);
+
+impl ParseArgument for ChannelMention {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+
+        channel_mention_to_id(&word)
+            .and_then(Id::new_checked)
+            .map(Self)
+            .ok_or(TagParseError::NoMention)
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::Channel(id) = word {
+            Ok(Self(*id))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "Channel".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![ChannelBuilder::new(name, "channel argument").required(true).build()]
+    }
+}
+
+/// A mention of a user, role or channel, classified by its syntax (`<@id>` or `<@!id>`, `<@&id>`
+/// and `<#id>` respectively). For interactions, this is a mentionable option, which can only be a
+/// user or a role.
+#[derive(Debug, Clone, Copy)]
+pub enum Mention {
+    User(Id<UserMarker>),
+    Role(Id<RoleMarker>),
+    Channel(Id<ChannelMarker>),
+}
+impl FromStr for Mention {
+    type Err = TagParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inner = s
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or(TagParseError::NoMention)?;
+
+        let (kind, id) = if let Some(id) = inner.strip_prefix("@&") {
+            ("role", id)
+        } else if let Some(id) = inner.strip_prefix("@!").or_else(|| inner.strip_prefix('@')) {
+            ("user", id)
+        } else if let Some(id) = inner.strip_prefix('#') {
+            ("channel", id)
+        } else {
+            return Err(TagParseError::NoMention);
+        };
+
+        // rejects zero IDs as well as anything that isn't a number
+        let id = id
+            .parse::<u64>()
+            .ok()
+            .and_then(Id::new_checked)
+            .ok_or_else(|| TagParseError::InvalidSnowflake(s.to_owned()))?;
+
+        Ok(match kind {
+            "role" => Self::Role(id.cast()),
+            "user" => Self::User(id.cast()),
+            _ => Self::Channel(id),
+        })
+    }
+}
+
+impl ParseArgument for Mention {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        word.parse()
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        match word {
+            // mentionables don't say what they are, but any user will have been resolved
+            CommandOptionValue::Mentionable(id) if ctxt.is_known_user(id.cast()) => Ok(Self::User(id.cast())),
+            CommandOptionValue::Mentionable(id) => Ok(Self::Role(id.cast())),
+            CommandOptionValue::User(id) => Ok(Self::User(*id)),
+            CommandOptionValue::Role(id) => Ok(Self::Role(*id)),
+            CommandOptionValue::Channel(id) => Ok(Self::Channel(*id)),
+            _ => Err(TagParseError::MismatchedCommandOptionType((
+                "Mentionable".to_owned(),
+                word.clone(),
+            ))),
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            MentionableBuilder::new(name, "user or role mention")
+                .required(true)
+                .build(),
+        ]
+    }
+}
+
+/// A channel argument (mention or ID)
+#[derive(Debug)]
+pub struct Channel(pub TwlChannel);
+impl ParseArgument for Channel {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let next = ctxt.next_word(label)?;
+        let id = channel_mention_to_id(&next);
+
+        let channel = ctxt
+            .cx
+            .assyst()
+            .http_client
+            .channel(Id::<ChannelMarker>::new(id.unwrap_or(next.parse::<u64>().unwrap_or(1))))
+            .await
+            .map_err(|e| TagParseError::TwilightHttp(Box::new(e)))?
+            .model()
+            .await
+            .map_err(|e| TagParseError::TwilightDeserialize(Box::new(e)))?;
+
+        Ok(Channel(channel))
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::Channel(id) = word {
+            let channel = ctxt
+                .cx
+                .assyst()
+                .http_client
+                .channel(*id)
+                .await
+                .map_err(|e| TagParseError::TwilightHttp(Box::new(e)))?
+                .model()
+                .await
+                .map_err(|e| TagParseError::TwilightDeserialize(Box::new(e)))?;
+
+            Ok(Channel(channel))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "Channel".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![ChannelBuilder::new(name, "channel argument").required(true).build()]
+    }
+}
+
+/// The rest of a message as an argument. This should be the last argument if used.
+///
+/// Internal whitespace is kept as it was sent in both raw messages and interactions, so this is
+/// suitable for text where spacing matters, such as code or ASCII art.
+#[derive(Debug)]
+pub struct Rest(pub String);
+
+impl Rest {
+    /// Takes the string option verbatim: unlike `Vec<Word>`, it must not be split or trimmed, so
+    /// that runs of whitespace are preserved.
+    fn from_option(option: &CommandOptionValue) -> Result<Self, TagParseError> {
+        if let CommandOptionValue::String(option) = option {
+            Ok(Rest(option.clone()))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (Rest)".to_owned(),
+                option.clone(),
+            )))
+        }
+    }
+}
+
+impl ParseArgument for Rest {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        if let Ok(r) = ctxt.rest(label.clone()) {
+            Ok(Self(r))
+        } else if let Some(m) = ctxt.cx.data.message {
+            if let Some(ref r) = m.referenced_message {
+                Ok(Self(r.content.clone()))
+            } else {
+                Ok(Self(ctxt.rest(label)?))
+            }
+        } else {
+            Ok(Self(ctxt.rest(label)?))
+        }
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        // look for resolved messages: if so, it's a context menu command, and use that message
+        if let Some(ref ms) = ctxt.cx.data.resolved_messages
+            && let Some(m) = ms.first()
+        {
+            if m.content.is_empty() {
+                return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
+            }
+            return Ok(Rest(m.content.clone()));
+        }
+
+        Self::from_option(&ctxt.option_by_name(&label.unwrap().0)?.value)
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "text input").required(true).build()]
+    }
+
+    fn usage(name: &str) -> String {
+        format!("<...{name}>")
+    }
+}
+
+/// The rest of a message as an argument, and asserts that there is no following flag argument. This
+/// should be the last argument if used.
+#[derive(Debug)]
+pub struct RestNoFlags(pub String);
+
+impl ParseArgument for RestNoFlags {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let all = ctxt.rest_all(label.clone());
+        if !all.is_empty() {
+            Ok(Self(all))
+        } else if let Some(m) = ctxt.cx.data.message
+            && let Some(ref r) = m.referenced_message
+        {
+            Ok(Self(r.content.clone()))
+        } else {
+            Err(TagParseError::ArgsExhausted(ArgsExhausted(label)))
+        }
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        // look for resolved messages: if so, it's a context menu command, and use that message
+        if let Some(ref ms) = ctxt.cx.data.resolved_messages
+            && let Some(m) = ms.first()
+        {
+            if m.content.is_empty() {
+                return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
+            }
+            return Ok(RestNoFlags(m.content.clone()));
+        }
+
+        // treat Rest as same as Word because there is no option type which is just one
+        // whitespace-delimited word
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::String(ref option) = word {
+            Ok(RestNoFlags(option.clone()))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (Rest)".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "text input").required(true).build()]
+    }
+
+    fn usage(name: &str) -> String {
+        format!("<...{name}>")
+    }
+}
+
+/// The rest of a message, exactly as it was sent. Unlike [`Rest`] and [`RestNoFlags`], flags are
+/// not interpreted, whitespace and newlines (including trailing ones) are preserved, and there is
+/// no fallback to the replied-to message. This should be the last argument if used.
+#[derive(Debug)]
+pub struct Verbatim(pub String);
+
+impl ParseArgument for Verbatim {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        Ok(Self(ctxt.rest_verbatim(label)?.to_owned()))
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        // look for resolved messages: if so, it's a context menu command, and use that message
+        if let Some(ref ms) = ctxt.cx.data.resolved_messages
+            && let Some(m) = ms.first()
+        {
+            if m.content.is_empty() {
+                return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
+            }
+            return Ok(Verbatim(m.content.clone()));
+        }
+
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::String(ref option) = word {
+            Ok(Verbatim(option.clone()))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (Verbatim)".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![StringBuilder::new(name, "text input").required(true).build()]
+    }
+
+    fn usage(name: &str) -> String {
+        format!("<...{name}>")
+    }
+}
+
+/// Which sources [`ImageUrl`] may resolve an image from. Commands can restrict these through the
+/// `image_resolve` attribute, e.g. to avoid scanning channel history. By default, every source is
+/// tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageResolveConfig {
+    pub attachment: bool,
+    pub reply: bool,
+    pub history: bool,
+    pub mention: bool,
+    pub emoji: bool,
+}
+impl ImageResolveConfig {
+    pub const ALL: Self = Self {
+        attachment: true,
+        reply: true,
+        history: true,
+        mention: true,
+        emoji: true,
+    };
+}
+impl Default for ImageResolveConfig {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+impl ImageResolveConfig {
+    /// The sources a raw message command's image is resolved from, in the order they are tried.
+    fn raw_message_sources(self, precedence: ImageSourcePrecedence) -> Vec<ImageSource> {
+        // an attachment and a reply image can both be present, so the order of these two is
+        // configurable (see `image_source_precedence` in config.toml)
+        let (first, second) = match precedence {
+            ImageSourcePrecedence::Attachment => (
+                (ImageSource::Attachment, self.attachment),
+                (ImageSource::Reply, self.reply),
+            ),
+            ImageSourcePrecedence::Reply => (
+                (ImageSource::Reply, self.reply),
+                (ImageSource::Attachment, self.attachment),
+            ),
+        };
+
+        [
+            (ImageSource::Mention, self.mention),
+            (ImageSource::MessageLink, true),
+            (ImageSource::Url, true),
+            first,
+            second,
+            (ImageSource::Emoji, self.emoji),
+            (ImageSource::Sticker, true),
+            (ImageSource::History, self.history),
+        ]
+        .into_iter()
+        .filter_map(|(source, enabled)| enabled.then_some(source))
+        .collect()
+    }
+
+    /// The sources an interaction command's image is resolved from, in the order they are tried.
+    fn interaction_sources(self) -> Vec<ImageSource> {
+        [
+            (ImageSource::Attachment, self.attachment),
+            (ImageSource::Mention, self.mention),
+            (ImageSource::MessageLink, true),
+            (ImageSource::Url, true),
+            (ImageSource::Emoji, self.emoji),
+            (ImageSource::Link, true),
+            (ImageSource::History, self.history),
+        ]
+        .into_iter()
+        .filter_map(|(source, enabled)| enabled.then_some(source))
+        .collect()
+    }
+}
+
+/// A place [`ImageUrl`] can resolve an image from. See [`ImageResolveConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageSource {
+    Mention,
+    MessageLink,
+    Url,
+    Attachment,
+    Reply,
+    Emoji,
+    Sticker,
+    /// Text given to an interaction command, taken as a link as-is.
+    Link,
+    History,
+}
+
+/// Strips `prefix` from the start of `s`, ignoring ASCII case.
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.get(..prefix.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+        .then(|| &s[prefix.len()..])
+}
+
+/// Strips the scheme and any `www.` from a URL, leaving the host and path. Both are matched
+/// case-insensitively, so `HTTPS://WWW.` is stripped too.
+fn strip_url_scheme(url: &str) -> &str {
+    let url = strip_prefix_ignore_case(url, "https://")
+        .or_else(|| strip_prefix_ignore_case(url, "http://"))
+        .unwrap_or(url);
+
+    strip_prefix_ignore_case(url, "www.").unwrap_or(url)
+}
+
+/// Whether a URL points to a Tenor page, such as `tenor.com/view/...-gif-12345` or a short
+/// `tenor.com/xxxxx.gif` link, rather than directly to the media.
+fn is_tenor_page(url: &str) -> bool {
+    strip_url_scheme(url).starts_with("tenor.com/")
+}
+
+/// Gets the direct GIF URL for a Giphy page, such as `giphy.com/gifs/some-name-abc123` (where the
+/// ID is the part after the last `-`).
+fn giphy_media_url(url: &str) -> Option<String> {
+    let path = strip_url_scheme(url).strip_prefix("giphy.com/gifs/")?;
+    let slug = path.split(['?', '#', '/']).next()?;
+    let id = slug.rsplit('-').next()?;
+
+    (!id.is_empty()).then(|| format!("https://media.giphy.com/media/{id}/giphy.gif"))
+}
+
+/// Gets the direct image URL for an Imgur page for a single image, such as `imgur.com/abc123`.
+/// Albums and galleries are left alone, since they don't refer to a single image.
+fn imgur_media_url(url: &str) -> Option<String> {
+    let path = strip_url_scheme(url).strip_prefix("imgur.com/")?;
+    let id = path.split(['?', '#']).next()?;
+
+    (!id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric())).then(|| format!("https://i.imgur.com/{id}.png"))
+}
+
+/// Rewrites links to pages on media sites (Tenor, Giphy and Imgur) to links to the media itself.
+/// Links to anywhere else are returned unchanged.
+async fn normalize_media_url(assyst: &Assyst, url: String) -> Result<String, TagParseError> {
+    if is_tenor_page(&url) {
+        return resolve_tenor(assyst, &url).await;
+    }
+
+    Ok(giphy_media_url(&url).or_else(|| imgur_media_url(&url)).unwrap_or(url))
+}
+
+/// Extracts the post ID from a Tenor view URL, i.e. the trailing number in
+/// `tenor.com/view/some-name-gif-12345`.
+fn tenor_post_id(url: &str) -> Option<&str> {
+    let (_, view) = url.split_once("/view/")?;
+    let view = view.split(['?', '#', '/']).next()?;
+    let (_, id) = view.rsplit_once('-')?;
+
+    (!id.is_empty() && id.bytes().all(|b| b.is_ascii_digit())).then_some(id)
+}
+
+/// Looks up the GIF for a Tenor post through the Tenor API. Returns `None` if no API key is
+/// configured or the lookup fails for any reason, so that the caller can fall back.
+async fn tenor_api_gif(assyst: &Assyst, id: &str) -> Option<String> {
+    #[derive(Deserialize)]
+    struct TenorMedia {
+        pub url: String,
+    }
+
+    #[derive(Deserialize)]
+    struct TenorMediaFormats {
+        pub gif: TenorMedia,
+    }
+
+    #[derive(Deserialize)]
+    struct TenorPost {
+        pub media_formats: TenorMediaFormats,
+    }
+
+    #[derive(Deserialize)]
+    struct TenorPosts {
+        pub results: Vec<TenorPost>,
+    }
+
+    let key = CONFIG
+        .authentication
+        .tenor_api_key
+        .as_deref()
+        .filter(|k| !k.is_empty())?;
+
+    let posts = assyst
+        .reqwest_client
+        .get("https://tenor.googleapis.com/v2/posts")
+        .query(&[("ids", id), ("key", key), ("media_filter", "gif")])
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json::<TenorPosts>()
+        .await
+        .ok()?;
+
+    posts.results.into_iter().next().map(|p| p.media_formats.gif.url)
+}
+
+/// Resolves a Tenor page URL to the URL of the GIF itself. Tenor pages typically only embed a PNG,
+/// so the official API is preferred when a key is configured, falling back to scraping the GIF URL
+/// out of the page.
+async fn resolve_tenor(assyst: &Assyst, url: &str) -> Result<String, TagParseError> {
+    if let Some(id) = tenor_post_id(url)
+        && let Some(gif) = tenor_api_gif(assyst, id).await
+    {
+        return Ok(gif);
+    }
+
+    // short links redirect to the view page, which might give us a post ID to try again with
+    let response = assyst.reqwest_client.get(url).send().await?;
+
+    if let Some(id) = tenor_post_id(response.url().as_str())
+        && let Some(gif) = tenor_api_gif(assyst, id).await
+    {
+        return Ok(gif);
+    }
+
+    let page = response.text().await?;
+
+    regex::TENOR_GIF
+        .find(&page)
+        .map(|m| m.as_str().to_owned())
+        .ok_or(TagParseError::MediaDownloadFail)
+}
+
+/// An image URL argument. For raw messages, sources are tried in the following order: user
+/// mention, URL, attachment and reply (order configurable through `image_source_precedence`),
+/// emoji, sticker, and finally channel history. Sources can be disabled per command (see
+/// [`ImageResolveConfig`]).
+pub struct ImageUrl(pub String);
+
+/// Where emoji metadata, including the URL of each emoji's image, is looked up by codepoint.
+const EMOJI_METADATA_URL: &str = "https://bignutty.gitlab.io/emojipedia-data/data";
+
+/// Strips what commonly surrounds a URL pasted into a message: the angle brackets Discord uses to
+/// suppress embeds (`<https://...>`), and quotes or punctuation from the surrounding sentence. Only
+/// the ends are touched, so query strings and fragments are kept as they are. A closing
+/// parenthesis is only stripped if the URL has no matching opening one, so that links such as
+/// `https://en.wikipedia.org/wiki/Rust_(programming_language)` stay intact.
+fn clean_url(word: &str) -> &str {
+    let mut url = word.trim().trim_start_matches(['<', '(', '"', '\'']);
+
+    loop {
+        let trimmed = url.trim_end_matches(['>', '.', ',', ';', ':', '!', '?', '"', '\'']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(stripped) if trimmed.matches('(').count() < trimmed.matches(')').count() => stripped,
+            _ => trimmed,
+        };
+
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+impl ImageUrl {
+    async fn from_mention_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+
+        let user_id = id_from_mention(&word)
+            .and_then(Id::new_checked)
+            .ok_or(TagParseError::NoMention)?;
+
+        let user = ctxt.fetch_user(user_id).await?;
+
+        Ok(Self(get_avatar_url(&user)))
+    }
+
+    async fn from_mention_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::String(ref option) = word {
+            let user_id = id_from_mention(option)
+                .and_then(Id::new_checked)
+                .ok_or(TagParseError::NoMention)?;
+
+            let user = ctxt.fetch_user(user_id).await?;
+
+            Ok(Self(get_avatar_url(&user)))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (mention aregument)".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    async fn from_url_argument_raw_message(
+        ctxt: &mut RawMessageParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        let word = clean_url(&word);
+
+        // message links are handled by `from_message_link_raw_message`
+        if regex::URL.is_match(word) && !regex::MESSAGE_LINK.is_match(word) {
+            Ok(Self(word.to_owned()))
+        } else {
+            Err(TagParseError::NoUrl)
+        }
+    }
+
+    async fn from_url_argument_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::String(ref option) = word {
+            let option = clean_url(option);
+            if regex::URL.is_match(option) && !regex::MESSAGE_LINK.is_match(option) {
+                Ok(Self(option.to_owned()))
+            } else {
+                Err(TagParseError::NoUrl)
+            }
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (url argument)".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    /// Finds an image in the message a Discord message link (e.g.
+    /// `https://discord.com/channels/1/2/3`) points to. Failing to load the message is a low
+    /// severity error, so that other sources are still tried.
+    ///
+    /// Only messages the invoking user could read themselves are loaded: the link must be to the
+    /// current server (or, for DM links, the current channel), and the user must be able to view
+    /// the channel and read its history.
+    async fn message_link(cx: &mut CommandCtxt<'_>, link: &str) -> Result<Self, TagParseError> {
+        let captures = regex::MESSAGE_LINK.captures(link).ok_or(TagParseError::NoMessageLink)?;
+        let (Some(channel_id), Some(message_id)) = (
+            Id::new_checked(captures[2].parse()?),
+            Id::new_checked(captures[3].parse()?),
+        ) else {
+            return Err(TagParseError::NoMessageLink);
+        };
+
+        if channel_id != cx.data.channel_id {
+            let guild_id = match &captures[1] {
+                "@me" => None,
+                id => Id::new_checked(id.parse()?),
+            };
+
+            let Some(guild_id) = guild_id.filter(|&id| Some(id) == cx.data.guild_id) else {
+                return Err(TagParseError::LinkedMessageNotAccessible);
+            };
+
+            let can_read = cx
+                .assyst()
+                .rest_cache_handler
+                .user_can_read_channel(guild_id.get(), channel_id.get(), cx.data.author.id.get())
+                .await
+                .map_err(|_| TagParseError::FailedToGetLinkedMessage)?;
+
+            if !can_read {
+                return Err(TagParseError::LinkedMessageNotAccessible);
+            }
+        }
+
+        let message = match cx.assyst().http_client.message(channel_id, message_id).await {
+            Ok(m) => m.model().await?,
+            Err(_) => return Err(TagParseError::FailedToGetLinkedMessage),
+        };
+
+        Self::from_message(cx, &message)
+            .await?
+            .ok_or(TagParseError::NoImageFound)
+    }
+
+    async fn from_message_link_raw_message(
+        ctxt: &mut RawMessageParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        let word = clean_url(&word);
+        Self::message_link(&mut ctxt.cx, word).await
+    }
+
+    async fn from_message_link_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::String(ref option) = word {
+            let option = option.clone();
+            Self::message_link(&mut ctxt.cx, &option).await
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (message link)".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    /// Tries only the sources that consume a single word (mention, URL, emoji).
+    async fn from_word_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        macro_rules! handle {
+            ($v:expr) => {
+                match $v {
+                    Ok(r) => return Ok(r),
+                    Err(err) if let TagParseError::TwilightHttp(_) = err => {},
+                    Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
+                    _ => {},
+                }
+            };
+        }
+
+        handle!(commit_if_ok!(ctxt, ImageUrl::from_mention_raw_message, label));
+        handle!(commit_if_ok!(ctxt, ImageUrl::from_url_argument_raw_message, label));
+        handle!(commit_if_ok!(ctxt, ImageUrl::from_emoji_raw_message, label));
+        Err(TagParseError::NoImageFound)
+    }
+
+    /// Tries only the `{name}-attachment` and `{name}-link` options.
+    async fn from_options_interaction_command(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let (name, description) = label.unwrap();
+        let attachment_label = Some((format!("{name}-attachment"), description.clone()));
+        let link_label = Some((format!("{name}-link"), description));
+
+        match commit_if_ok!(ctxt, ImageUrl::from_attachment_interaction_command, attachment_label) {
+            Ok(r) => Ok(r),
+            Err(err) if err.get_severity() == ErrorSeverity::High => Err(err),
+            _ => commit_if_ok!(ctxt, ImageUrl::from_link_interaction_command, link_label),
+        }
+    }
+
+    /// Resolves a single word of an interaction's text option like a word in a raw message: a user
+    /// mention (as their avatar), a message link, a URL or an emoji.
+    async fn from_interaction_word(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        word: &str,
+    ) -> Result<Self, TagParseError> {
+        if let Some(user_id) = id_from_mention(word).and_then(Id::new_checked) {
+            let user = ctxt.fetch_user(user_id).await?;
+            return Ok(Self(get_avatar_url(&user)));
+        }
+
+        let word = clean_url(word);
+        if regex::MESSAGE_LINK.is_match(word) {
+            Self::message_link(&mut ctxt.cx, word).await
+        } else if regex::URL.is_match(word) {
+            Ok(Self(word.to_owned()))
+        } else {
+            Self::emoji(&mut ctxt.cx, word).await
+        }
+    }
+
+    /// Whether an attachment is an image or a GIF-like video (MP4/WebM), going by its content type,
+    /// or by its file extension if Discord didn't provide a content type.
+    fn is_media_attachment(attachment: &Attachment) -> bool {
+        const MEDIA_EXTENSIONS: &[&str] = &[
+            "png", "jpg", "jpeg", "gif", "webp", "bmp", "tiff", "avif", "mp4", "webm",
+        ];
+
+        match attachment.content_type.as_deref() {
+            Some(t) => t.starts_with("image/") || t == "video/mp4" || t == "video/webm",
+            None => attachment
+                .filename
+                .rsplit_once('.')
+                .is_some_and(|(_, ext)| MEDIA_EXTENSIONS.contains(&&*ext.to_ascii_lowercase())),
+        }
+    }
+
+    fn attachment(attachment: Option<&Attachment>) -> Result<Self, TagParseError> {
+        let attachment = attachment.ok_or(TagParseError::NoAttachment)?;
+        if attachment.size == 0 {
+            return Err(TagParseError::EmptyAttachment);
+        }
+        Ok(Self(attachment.url.clone()))
+    }
+
+    /// Picks the first non-empty media attachment, or the first media attachment if they're all
+    /// empty (so that [`ImageUrl::attachment`] reports it as such). Other files, such as text
+    /// files or PDFs, are skipped, so that other sources are tried instead.
+    fn first_attachment(attachments: &[Attachment]) -> Option<&Attachment> {
+        attachments
+            .iter()
+            .find(|a| a.size > 0 && Self::is_media_attachment(a))
+            .or_else(|| attachments.iter().find(|a| Self::is_media_attachment(a)))
+    }
+
+    /// Whether there are media attachments, but all of them are empty.
+    fn only_empty_media_attachments(attachments: &[Attachment]) -> bool {
+        let mut media = attachments.iter().filter(|a| Self::is_media_attachment(a)).peekable();
+        media.peek().is_some() && media.all(|a| a.size == 0)
+    }
+
+    async fn from_attachment_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
+        Self::attachment(Self::first_attachment(&ctxt.message()?.attachments))
+    }
+
+    async fn from_attachment_interaction_command(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::Attachment(ref option) = word {
+            let attachment = ctxt.cx.data.interaction_attachments.get(option);
+            let attachment = attachment.ok_or(TagParseError::NoAttachment)?;
+            Self::attachment(Some(attachment))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "Attachment".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    async fn from_link_interaction_command(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let word = &ctxt.option_by_name(&label.unwrap().0)?.value;
+
+        if let CommandOptionValue::String(option) = word {
+            let option = clean_url(option).to_owned();
+            if regex::MESSAGE_LINK.is_match(&option) {
+                return Self::message_link(&mut ctxt.cx, &option).await;
+            }
+
+            Ok(Self(option))
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "Link".to_owned(),
+                word.clone(),
+            )))
+        }
+    }
+
+    /// This only exists for raw message, since interactions don't carry replies. The interaction
+    /// equivalent is the target message of a context menu command.
+    async fn from_reply(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
+        let message = ctxt.message()?;
+        let fetched;
+
+        let target = ReplyTarget::new(
+            message.referenced_message.as_deref(),
+            message.reference.as_ref().map(|r| (r.channel_id, r.message_id)),
+            message.channel_id,
+        );
+        let reply = match target {
+            ReplyTarget::Resolved(reply) => reply,
+            ReplyTarget::Unresolved(channel_id, message_id) => {
+                fetched = match ctxt.cx.assyst().http_client.message(channel_id, message_id).await {
+                    Ok(m) => m.model().await?,
+                    Err(_) => return Err(TagParseError::FailedToGetReferencedMessage),
+                };
+                &fetched
+            },
+            ReplyTarget::NoReply => return Err(TagParseError::NoReply),
+        };
+
+        Self::from_message(&mut ctxt.cx, reply)
+            .await?
+            .ok_or(TagParseError::NoReply)
+    }
+
+    /// Finds an image in another message, such as the one being replied to, or the target of a
+    /// message context menu command. Tries attachments, stickers, embeds and finally emojis.
+    async fn from_message(cx: &mut CommandCtxt<'_>, message: &Message) -> Result<Option<Self>, TagParseError> {
+        if let Some(attachment) = message
+            .attachments
+            .iter()
+            .find(|a| a.size > 0 && Self::is_media_attachment(a))
+        {
+            return Ok(Some(Self(attachment.url.clone())));
+        }
+
+        macro_rules! handle {
+            ($v:expr) => {
+                match $v {
+                    Ok(v) => return Ok(Some(v)),
+                    Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
+                    _ => {},
+                }
+            };
+        }
+
+        handle!(Self::sticker(message.sticker_items.first()));
+        handle!(Self::embed(message.embeds.first()));
+        handle!(Self::emoji(cx, &message.content).await);
+
+        Ok(None)
+    }
+
+    fn embed(embed: Option<&Embed>) -> Result<Self, TagParseError> {
+        let embed = embed.ok_or(TagParseError::NoEmbed)?;
+
+        if let Some(url) = &embed.url
+            && is_tenor_page(url)
+        {
+            return Ok(Self(url.clone()));
+        }
+
+        if let Some(image) = &embed.image {
+            return Ok(Self(image.url.clone()));
+        }
+
+        if let Some(thumbnail) = &embed.thumbnail {
+            return Ok(Self(thumbnail.url.clone()));
+        }
+
+        if let Some(video) = &embed.video
+            && let Some(url) = &video.url
+        {
+            Ok(Self(url.clone()))
+        } else {
+            Err(TagParseError::NoEmbed)
+        }
+    }
+
+    async fn emoji(ctxt: &mut CommandCtxt<'_>, word: &str) -> Result<Self, TagParseError> {
+        if let Some(url) = Self::custom_emoji_url(word) {
+            Ok(Self(url))
+        } else if let Some(e) = emoji::lookup_by_glyph::lookup(word) {
+            let codepoint = e.codepoint.to_lowercase().replace(' ', "-").replace("-fe0f", "");
+            let assyst = ctxt.assyst();
+
+            Ok(Self(
+                Self::cached_emoji_image_url(
+                    &assyst.emoji_urls,
+                    &assyst.reqwest_client,
+                    EMOJI_METADATA_URL,
+                    codepoint,
+                )
+                .await,
+            ))
+        } else {
+            Err(TagParseError::NoEmoji)
+        }
+    }
+
+    /// Like [`ImageUrl::emoji_image_url`], but checks `cache` first, and adds the URL to it if the
+    /// lookup succeeded.
+    async fn cached_emoji_image_url(
+        cache: &Mutex<HashMap<String, String>>,
+        client: &Client,
+        base_url: &str,
+        codepoint: String,
+    ) -> String {
+        if let Some(url) = cache.lock().unwrap().get(&codepoint) {
+            return url.clone();
+        }
+
+        let (url, cacheable) = Self::emoji_image_url(client, base_url, &codepoint).await;
+
+        if cacheable {
+            cache.lock().unwrap().insert(codepoint, url.clone());
+        }
+
+        url
+    }
+
+    /// Looks up the image of the emoji with `codepoint` in the emoji metadata at `base_url`,
+    /// falling back to the twemoji CDN if the lookup fails. Also returns whether the URL came
+    /// from the metadata, since fallback URLs shouldn't be cached, so that the lookup is
+    /// retried next time.
+    async fn emoji_image_url(client: &Client, base_url: &str, codepoint: &str) -> (String, bool) {
+        #[derive(Deserialize)]
+        struct TwemojiVendorImage {
+            pub twitter: String,
+        }
+
+        #[derive(Deserialize)]
+        struct TwemojiLookup {
+            pub vendor_images: TwemojiVendorImage,
+        }
+
+        let lookup = async {
+            let response = client.get(format!("{base_url}/{codepoint}.json")).send().await?;
+            let status = response.status();
+            anyhow::ensure!(status == StatusCode::OK, "unexpected status {status}");
+            Ok::<_, anyhow::Error>(response.json::<TwemojiLookup>().await?.vendor_images.twitter)
+        };
+
+        match lookup.await {
+            Ok(url) => (url, true),
+            Err(err) => {
+                debug!("emoji metadata lookup for {codepoint} failed, falling back to twemoji CDN: {err:#}");
+                (
+                    format!("https://cdn.jsdelivr.net/gh/twitter/twemoji/assets/72x72/{codepoint}.png"),
+                    false,
+                )
+            },
+        }
+    }
+
+    /// The CDN URL of a custom Discord emoji, such as `<:name:id>`, or `<a:name:id>` if animated.
+    fn custom_emoji_url(word: &str) -> Option<String> {
+        let captures = regex::CUSTOM_EMOJI.captures(word)?;
+        // the leading `a` is only present on animated emojis
+        let extension = if captures.get(1).is_some() { "gif" } else { "png" };
+        let id = &captures[3];
+
+        Some(format!("https://cdn.discordapp.com/emojis/{id}.{extension}"))
+    }
+
+    /// The text of an emoji option. An emoji is never a URL, so any text is passed on to
+    /// [`ImageUrl::emoji`] as it is.
+    fn emoji_option(value: &CommandOptionValue) -> Result<&str, TagParseError> {
+        if let CommandOptionValue::String(option) = value {
+            Ok(option.trim())
+        } else {
+            Err(TagParseError::MismatchedCommandOptionType((
+                "String (emoji argument)".to_owned(),
+                value.clone(),
+            )))
+        }
+    }
+
+    async fn from_emoji_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let word = ctxt.next_word(label)?;
+        Self::emoji(&mut ctxt.cx, &word).await
+    }
+
+    async fn from_emoji_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let option = Self::emoji_option(&ctxt.option_by_name(&label.unwrap().0)?.value)?;
+        Self::emoji(&mut ctxt.cx, option).await
+    }
+
+    fn sticker(sticker: Option<&MessageSticker>) -> Result<Self, TagParseError> {
+        let sticker = sticker.ok_or(TagParseError::NoSticker)?;
+        match sticker.format_type {
+            // APNG stickers are served from the same URL as PNG ones, and keep their animation
+            StickerFormatType::Png | StickerFormatType::Apng => {
+                Ok(Self(format!("https://cdn.discordapp.com/stickers/{}.png", sticker.id)))
+            },
+            StickerFormatType::Gif => Ok(Self(format!(
+                "https://media.discordapp.net/stickers/{}.gif",
+                sticker.id
+            ))),
+            // Lottie stickers are vector animations (JSON), which can't be used as an image
+            _ => Err(TagParseError::UnsupportedSticker(sticker.format_type)),
+        }
+    }
+
+    /// This only exists for raw message
+    async fn from_sticker(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
+        Self::sticker(ctxt.message()?.sticker_items.first())
+    }
+
+    // Defined separately without a CommandCtxt because it is also used elsewhere where we don't
+    // have one (and this also doesn't need it)
+    pub async fn from_channel_history(
+        assyst: &Assyst,
+        channel_id: Id<ChannelMarker>,
+    ) -> Result<ImageUrl, TagParseError> {
+        Self::from_channel_history_nth(assyst, channel_id, 0).await
+    }
+
+    /// Like [`Self::from_channel_history`], but skips the `offset` most recent messages with an
+    /// image, so an offset of 0 is the most recent image. Offsets beyond the scanned messages give
+    /// [`TagParseError::NoImageInHistory`].
+    pub async fn from_channel_history_nth(
+        assyst: &Assyst,
+        channel_id: Id<ChannelMarker>,
+        offset: usize,
+    ) -> Result<ImageUrl, TagParseError> {
+        if offset >= usize::from(CONFIG.image_history_scan_limit()) {
+            return Err(TagParseError::NoImageInHistory);
+        }
+
+        let messages = match assyst
+            .http_client
+            .channel_messages(channel_id)
+            .limit(CONFIG.image_history_scan_limit())
+            .await
+        {
+            Ok(m) => m.models().await?,
+            Err(_) => return Err(TagParseError::FailedToGetMessageHistory),
+        };
+
+        // Ignore any error, even high severity ones, since not doing that would mean we bail when
+        // we see a "random" malformed message in a channel
+        messages
+            .iter()
+            .filter_map(|message| {
+                // embeds with only a video are handled here too
+                Self::embed(message.embeds.first())
+                    .or_else(|_| Self::sticker(message.sticker_items.first()))
+                    .or_else(|_| {
+                        Self::attachment(
+                            message
+                                .attachments
+                                .iter()
+                                .find(|a| a.size > 0 && Self::is_media_attachment(a)),
+                        )
+                    })
+                    .ok()
+            })
+            .nth(offset)
+            .ok_or(TagParseError::NoImageInHistory)
+    }
+}
+
+/// Where to find the message that a raw message command replied to.
+#[derive(Debug, PartialEq, Eq)]
+enum ReplyTarget<M> {
+    /// Discord resolved the replied-to message along with the reply.
+    Resolved(M),
+    /// Discord only sent the reference (e.g. because the message was deleted, or is otherwise
+    /// unavailable), so the message has to be fetched by its channel and message ID.
+    Unresolved(Id<ChannelMarker>, Id<MessageMarker>),
+    /// The command didn't reply to a message.
+    NoReply,
+}
+impl<M> ReplyTarget<M> {
+    /// `reference` is the channel and message IDs of the reply's message reference, if it has one.
+    /// The channel defaults to `channel_id`, the one the reply was sent in.
+    fn new(
+        resolved: Option<M>,
+        reference: Option<(Option<Id<ChannelMarker>>, Option<Id<MessageMarker>>)>,
+        channel_id: Id<ChannelMarker>,
+    ) -> Self {
+        match (resolved, reference) {
+            (Some(message), _) => Self::Resolved(message),
+            (None, Some((reference_channel_id, Some(message_id)))) => {
+                Self::Unresolved(reference_channel_id.unwrap_or(channel_id), message_id)
+            },
+            _ => Self::NoReply,
+        }
+    }
+}
+
+impl Display for ImageUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl ParseArgument for ImageUrl {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        async fn combined_parsers(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<ImageUrl, TagParseError> {
+            macro_rules! handle {
+                ($v:expr) => {
+                    match $v {
+                        Ok(r) => return Ok(r),
+                        Err(err) if let TagParseError::TwilightHttp(_) = err => {},
+                        Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
+                        _ => {},
+                    }
+                };
+            }
+
+            for source in ctxt.image_resolve.raw_message_sources(CONFIG.image_source_precedence) {
+                match source {
+                    ImageSource::Mention => handle!(commit_if_ok!(ctxt, ImageUrl::from_mention_raw_message, label)),
+                    ImageSource::MessageLink => {
+                        handle!(commit_if_ok!(ctxt, ImageUrl::from_message_link_raw_message, label))
+                    },
+                    ImageSource::Url => handle!(commit_if_ok!(ctxt, ImageUrl::from_url_argument_raw_message, label)),
+                    ImageSource::Attachment => {
+                        handle!(commit_if_ok!(ctxt, ImageUrl::from_attachment_raw_message, label))
+                    },
+                    ImageSource::Reply => handle!(commit_if_ok!(ctxt, ImageUrl::from_reply, label)),
+                    ImageSource::Emoji => handle!(commit_if_ok!(ctxt, ImageUrl::from_emoji_raw_message, label)),
+                    ImageSource::Sticker => handle!(commit_if_ok!(ctxt, ImageUrl::from_sticker, label)),
+                    ImageSource::History => {
+                        if ctxt.cx.data.command_from_install_context {
+                            return Err(TagParseError::MessageHistoryUnavailableInContext);
+                        }
+                        handle!(ImageUrl::from_channel_history(ctxt.cx.assyst(), ctxt.cx.data.channel_id).await);
+                    },
+                    // interaction commands only
+                    ImageSource::Link => {},
+                }
+            }
+
+            // if the only image provided was an empty attachment, say so rather than that none was
+            // found at all
+            if let Some(m) = ctxt.cx.data.message
+                && ImageUrl::only_empty_media_attachments(&m.attachments)
+            {
+                return Err(TagParseError::EmptyAttachment);
+            }
+            Err(TagParseError::NoImageFound)
+        }
+
+        let ImageUrl(url) = combined_parsers(ctxt, label).await?;
+
+        Ok(Self(normalize_media_url(ctxt.cx.assyst(), url).await?))
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        async fn combined_parsers(
+            ctxt: &mut InteractionCommandParseCtxt<'_>,
+            label: Label,
+        ) -> Result<ImageUrl, TagParseError> {
+            macro_rules! handle {
+                ($v:expr) => {
+                    match $v {
+                        Ok(r) => return Ok(r),
+                        Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
+                        _ => {},
+                    }
+                };
+            }
+
+            // if this is Some, this is a context menu command
+            // we must have our image defined here, instead of looking anywhere else. the target
+            // message is treated the same way as a reply to a raw message command
+            if let Some(ref r) = ctxt.cx.data.resolved_messages {
+                let image = match r.first() {
+                    Some(m) => ImageUrl::from_message(&mut ctxt.cx, m).await?,
+                    None => None,
+                };
+
+                return image.ok_or(TagParseError::ArgsExhausted(ArgsExhausted(label)));
+            }
+
+            let attachment_label = Some((
+                format!("{}-attachment", label.clone().unwrap().0),
+                label.clone().unwrap().1,
+            ));
+            let link_label = Some((format!("{}-link", label.clone().unwrap().0), label.clone().unwrap().1));
+
+            // the text option is resolved like a word in a raw message, and anything else given in
+            // it is taken as a link as-is, so that explicit input still wins over channel history
+            for source in ctxt.image_resolve.interaction_sources() {
+                match source {
+                    ImageSource::Attachment => handle!(commit_if_ok!(
+                        ctxt,
+                        ImageUrl::from_attachment_interaction_command,
+                        attachment_label
+                    )),
+                    ImageSource::Mention => {
+                        handle!(commit_if_ok!(ctxt, ImageUrl::from_mention_command_option, link_label))
+                    },
+                    ImageSource::MessageLink => handle!(commit_if_ok!(
+                        ctxt,
+                        ImageUrl::from_message_link_command_option,
+                        link_label
+                    )),
+                    ImageSource::Url => handle!(commit_if_ok!(
+                        ctxt,
+                        ImageUrl::from_url_argument_command_option,
+                        link_label
+                    )),
+                    ImageSource::Emoji => handle!(commit_if_ok!(ctxt, ImageUrl::from_emoji_command_option, link_label)),
+                    ImageSource::Link => {
+                        handle!(commit_if_ok!(ctxt, ImageUrl::from_link_interaction_command, link_label))
+                    },
+                    ImageSource::History => {
+                        handle!(ImageUrl::from_channel_history(ctxt.cx.assyst(), ctxt.cx.data.channel_id).await)
+                    },
+                    // raw message commands only
+                    ImageSource::Reply | ImageSource::Sticker => {},
+                }
+            }
+
+            // if the only image provided was an empty attachment, say so rather than that none was
+            // found at all
+            let attachments = &ctxt.cx.data.interaction_attachments;
+            if !attachments.is_empty() && attachments.values().all(|a| a.size == 0) {
+                return Err(TagParseError::EmptyAttachment);
+            }
+            Err(TagParseError::NoImageFound)
+        }
+
+        let ImageUrl(url) = combined_parsers(ctxt, label).await?;
+
+        Ok(Self(normalize_media_url(ctxt.cx.assyst(), url).await?))
+    }
+
+    /// A slash command option can't be both an attachment and text, so there are two optional
+    /// options: an attachment, and text which can be a URL, mention or emoji (as in raw messages).
+    /// The attachment is preferred if both are given.
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        let attachment_name = format!("{name}-attachment");
+        let link_name = format!("{name}-link");
+
+        vec![
+            AttachmentBuilder::new(attachment_name, "attachment input")
+                .required(false)
+                .build(),
+            StringBuilder::new(link_name, "url, user mention or emoji input")
+                .required(false)
+                .build(),
+        ]
+    }
+}
+
+/// Two images to compare (e.g., "before" and "after"), with an optional split position.
+///
+/// For raw messages, images are taken from words (mentions, URLs, emojis) first, then from the
+/// message attachments, and finally from the replied message.
+pub struct Comparison {
+    pub left: ImageUrl,
+    pub right: ImageUrl,
+    pub split: Option<Percentage>,
+}
+
+impl Comparison {
+    /// Adds non-empty media attachments to `urls`, until there are two images.
+    fn add_attachments(urls: &mut Vec<ImageUrl>, attachments: &[Attachment]) {
+        for attachment in attachments
+            .iter()
+            .filter(|a| a.size > 0 && ImageUrl::is_media_attachment(a))
+            .take(2usize.saturating_sub(urls.len()))
+        {
+            urls.push(ImageUrl(attachment.url.clone()));
+        }
+    }
+
+    /// Takes the first two images as the left and right ones, or errors if there are fewer.
+    fn pair(urls: Vec<ImageUrl>) -> Result<(ImageUrl, ImageUrl), TagParseError> {
+        let mut urls = urls.into_iter();
+        let (Some(left), Some(right)) = (urls.next(), urls.next()) else {
+            return Err(TagParseError::ComparisonRequiresTwoImages);
+        };
+
+        Ok((left, right))
+    }
+}
+
+impl ParseArgument for Comparison {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let mut urls = Vec::with_capacity(2);
+
+        while urls.len() < 2 {
+            match ImageUrl::from_word_raw_message(ctxt, label.clone()).await {
+                Ok(url) => urls.push(url),
+                Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
+                Err(_) => break,
+            }
+        }
+
+        Self::add_attachments(&mut urls, &ctxt.message()?.attachments);
+
+        if urls.len() < 2
+            && let Ok(url) = commit_if_ok!(ctxt, ImageUrl::from_reply, label)
+        {
+            urls.push(url);
+        }
+
+        let (left, right) = Self::pair(urls)?;
+        let split = Option::<Percentage>::parse_raw_message(ctxt, label).await?;
+
+        Ok(Self { left, right, split })
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let (name, description) = label.unwrap();
+        let left_label = Some((format!("{name}-left"), description.clone()));
+        let right_label = Some((format!("{name}-right"), description.clone()));
+        let split_label = Some((format!("{name}-split"), description));
+
+        let left = ImageUrl::from_options_interaction_command(ctxt, left_label)
+            .await
+            .map_err(|_| TagParseError::ComparisonRequiresTwoImages)?;
+        let right = ImageUrl::from_options_interaction_command(ctxt, right_label)
+            .await
+            .map_err(|_| TagParseError::ComparisonRequiresTwoImages)?;
+        let split = Option::<Percentage>::parse_command_option(ctxt, split_label).await?;
+
+        Ok(Self { left, right, split })
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        let mut options = ImageUrl::as_command_options(&format!("{name}-left"));
+        options.extend(ImageUrl::as_command_options(&format!("{name}-right")));
+        options.extend(Option::<Percentage>::as_command_options(&format!("{name}-split")));
+        options
+    }
+}
+
+/// Image data, along with the URL it was downloaded from, if it was downloaded (images produced by
+/// commands have no URL).
+pub struct Image(pub Vec<u8>, pub Option<String>);
+
+/// Image downloads give up on slow hosts rather than stalling the command, and pick up where they
+/// left off if the connection drops, since inputs (especially videos) can be large.
+const IMAGE_DOWNLOAD_OPTIONS: DownloadOptions = DownloadOptions {
+    timeout: Some(Duration::from_secs(30)),
+    retries: 1,
+    resume: true,
+};
+
+impl Image {
+    async fn download(assyst: &Assyst, url: &str) -> Result<Self, TagParseError> {
+        Self::download_limited(assyst, url, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES).await
+    }
+
+    /// Downloads an already resolved [`ImageUrl`], subject to the same size limit and signature
+    /// check as an `Image` argument. Useful for commands that need both the URL and the data, so
+    /// the image only has to be resolved once.
+    pub async fn from_url(assyst: &Assyst, ImageUrl(url): ImageUrl) -> Result<Self, TagParseError> {
+        Self::download(assyst, &url).await
+    }
+
+    /// The URL this image was downloaded from, if it was downloaded.
+    pub fn url(&self) -> Option<&str> {
+        self.1.as_deref()
+    }
+
+    /// Downloads an image, rejecting anything without a recognised media signature (such as a web
+    /// page), so that it doesn't fail confusingly once it reaches processing.
+    async fn download_limited(assyst: &Assyst, url: &str, limit: usize) -> Result<Self, TagParseError> {
+        let data = downloader::download_content_with_options(assyst, url, limit, true, IMAGE_DOWNLOAD_OPTIONS).await?;
+
+        if get_sig(&data).is_none() {
+            return Err(TagParseError::NotAnImage);
+        }
+
+        Ok(Image(data, Some(url.to_owned())))
+    }
+
+    /// The detected format of this image. Always `Some` for images parsed as arguments, since
+    /// anything without a recognised signature is rejected.
+    pub fn format(&self) -> Option<Type> {
+        get_sig(&self.0)
+    }
+
+    /// Whether this image is animated (e.g., an animated GIF or WebP, or an AVIF image sequence),
+    /// for commands that need a different pipeline for animated input.
+    pub fn is_animated(&self) -> bool {
+        is_animated(&self.0)
+    }
+}
+
+impl ParseArgument for Image {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let ImageUrl(url) = ImageUrl::parse_raw_message(ctxt, label).await?;
+        Image::download(ctxt.cx.assyst(), &url).await
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let ImageUrl(url) = ImageUrl::parse_command_option(ctxt, label).await?;
+        Image::download(ctxt.cx.assyst(), &url).await
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        ImageUrl::as_command_options(name)
+    }
+}
+
+/// An image which must be no larger than `MAX_BYTES`, for commands that can only handle small
+/// inputs. Larger images are rejected while downloading, rather than after downloading all of it.
+/// `MAX_BYTES` should not exceed [`ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES`].
+pub struct LimitedImage<const MAX_BYTES: usize>(pub Vec<u8>);
+
+impl<const MAX_BYTES: usize> ParseArgument for LimitedImage<MAX_BYTES> {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let ImageUrl(url) = ImageUrl::parse_raw_message(ctxt, label).await?;
+        let Image(data, _) = Image::download_limited(ctxt.cx.assyst(), &url, MAX_BYTES).await?;
+        Ok(Self(data))
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let ImageUrl(url) = ImageUrl::parse_command_option(ctxt, label).await?;
+        let Image(data, _) = Image::download_limited(ctxt.cx.assyst(), &url, MAX_BYTES).await?;
+        Ok(Self(data))
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        Image::as_command_options(name)
+    }
+}
+
+/// An image from the channel's recent history, optionally followed by how many image-bearing
+/// messages to skip: `0` (the default) is the most recent image, `1` the one before it, and so on.
+/// Only the last `image_history_scan_limit` (from the config) messages are searched.
+pub struct HistoryImage(pub Image);
+
+impl ParseArgument for HistoryImage {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, _: Label) -> Result<Self, TagParseError> {
+        let offset = match ctxt.peek_word().and_then(|word| word.parse::<usize>().ok()) {
+            Some(offset) => {
+                ctxt.next_word(None)?;
+                offset
+            },
+            None => 0,
+        };
+
+        let ImageUrl(url) =
+            ImageUrl::from_channel_history_nth(ctxt.cx.assyst(), ctxt.cx.data.channel_id, offset).await?;
+        Ok(Self(Image::download(ctxt.cx.assyst(), &url).await?))
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let offset = match ctxt.option_by_name(&label.unwrap().0).map(|o| &o.value) {
+            Ok(CommandOptionValue::Integer(offset)) => usize::try_from(*offset)?,
+            Ok(other) => {
+                return Err(TagParseError::MismatchedCommandOptionType((
+                    "Integer (offset)".to_owned(),
+                    other.clone(),
+                )));
+            },
+            Err(_) => 0,
+        };
+
+        let ImageUrl(url) =
+            ImageUrl::from_channel_history_nth(ctxt.cx.assyst(), ctxt.cx.data.channel_id, offset).await?;
+        Ok(Self(Image::download(ctxt.cx.assyst(), &url).await?))
+    }
+
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            IntegerBuilder::new(name, "how many recent images to skip (0 is the most recent)")
+                .min_value(0)
+                .max_value(i64::from(CONFIG.image_history_scan_limit()) - 1)
+                .required(false)
+                .build(),
+        ]
+    }
+
+    fn usage(name: &str) -> String {
+        format!("[{name}]")
+    }
+}
+
+/// The maximum number of images that can be given to a command taking multiple images.
+pub const MAX_IMAGES_PER_COMMAND: usize = 10;
+
+impl ParseArgument for Vec<Image> {
+    async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
+        let mut urls = Vec::new();
+
+        while urls.len() < MAX_IMAGES_PER_COMMAND {
+            match ImageUrl::from_word_raw_message(ctxt, label.clone()).await {
+                Ok(ImageUrl(url)) => urls.push(url),
+                Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
+                Err(_) => break,
+            }
+        }
+
+        let message = ctxt.message()?;
+        for attachment in message
+            .attachments
+            .iter()
+            .filter(|a| a.size > 0 && ImageUrl::is_media_attachment(a))
+            .take(MAX_IMAGES_PER_COMMAND - urls.len())
+        {
+            urls.push(attachment.url.clone());
+        }
+
+        if urls.is_empty() {
+            return Err(TagParseError::NoImageFound);
+        }
+
+        let mut images = Vec::with_capacity(urls.len());
+        for url in urls {
+            images.push(Image::download(ctxt.cx.assyst(), &url).await?);
+        }
+
+        Ok(images)
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut InteractionCommandParseCtxt<'_>,
+        label: Label,
+    ) -> Result<Self, TagParseError> {
+        let (name, description) = label.unwrap();
+        let mut urls = Vec::new();
+
+        let attachment_label = Some((format!("{name}-attachment"), description));
+        match ImageUrl::from_attachment_interaction_command(ctxt, attachment_label).await {
+            Ok(ImageUrl(url)) => urls.push(url),
+            Err(err) if err.get_severity() == ErrorSeverity::High => return Err(err),
+            Err(_) => {},
+        }
+
+        let links = match ctxt.option_by_name(&format!("{name}-links")).map(|o| &o.value) {
+            Ok(CommandOptionValue::String(links)) => links.clone(),
+            _ => String::new(),
+        };
+        for word in links.split_ascii_whitespace().take(MAX_IMAGES_PER_COMMAND - urls.len()) {
+            let ImageUrl(url) = ImageUrl::from_interaction_word(ctxt, word).await?;
+            urls.push(url);
+        }
+
+        if urls.is_empty() {
+            return Err(TagParseError::NoImageFound);
+        }
+
+        let mut images = Vec::with_capacity(urls.len());
+        for url in urls {
+            images.push(Image::download(ctxt.cx.assyst(), &url).await?);
+        }
+
+        Ok(images)
+    }
+
+    /// Discord has no option for several attachments, and an option per image would use up most of
+    /// the options a command can have, so there is one attachment option and a text option of
+    /// space-separated URLs, mentions or emojis.
+    fn as_command_options(name: &str) -> Vec<CommandOption> {
+        vec![
+            AttachmentBuilder::new(format!("{name}-attachment"), "attachment input")
+                .required(false)
+                .build(),
+            StringBuilder::new(format!("{name}-links"), "space-separated urls, user mentions or emojis")
+                .required(false)
+                .build(),
+        ]
+    }
+
+    fn usage(name: &str) -> String {
+        format!("<...{name}>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::command::ParseCtxt;
+    use crate::commit_group_if_ok;
+
+    #[test]
+    fn emoji_options_are_not_required_to_be_urls() {
+        let option = CommandOptionValue::String(" 🎉 ".to_owned());
+
+        let glyph = ImageUrl::emoji_option(&option).unwrap();
+        assert_eq!(glyph, "🎉");
+        assert!(emoji::lookup_by_glyph::lookup(glyph).is_some());
+    }
+
+    #[test]
+    fn custom_emojis_resolve_to_the_cdn() {
+        assert_eq!(
+            ImageUrl::custom_emoji_url("<:wave:123456789012345678>").as_deref(),
+            Some("https://cdn.discordapp.com/emojis/123456789012345678.png")
+        );
+        assert_eq!(
+            ImageUrl::custom_emoji_url("<a:party:123456789012345678>").as_deref(),
+            Some("https://cdn.discordapp.com/emojis/123456789012345678.gif")
+        );
+        assert_eq!(ImageUrl::custom_emoji_url("🎉"), None);
+    }
+
+    fn attachment(filename: &str, size: u64) -> Attachment {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "filename": filename,
+            "size": size,
+            "url": format!("https://cdn.discordapp.com/attachments/1/2/{filename}"),
+            "proxy_url": format!("https://media.discordapp.net/attachments/1/2/{filename}"),
+        }))
+        .unwrap()
+    }
+
+    fn typed_attachment(filename: &str, content_type: &str) -> Attachment {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "filename": filename,
+            "content_type": content_type,
+            "size": 10,
+            "url": format!("https://cdn.discordapp.com/attachments/1/2/{filename}"),
+            "proxy_url": format!("https://media.discordapp.net/attachments/1/2/{filename}"),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn non_media_attachments_are_skipped() {
+        let attachments = [
+            typed_attachment("report.pdf", "application/pdf"),
+            attachment("notes.txt", 10),
+            // the content type wins over the extension when Discord provides one
+            typed_attachment("fake.png", "text/plain"),
+            typed_attachment("clip", "video/mp4"),
+            attachment("cat.png", 10),
+        ];
+
+        let ImageUrl(url) = ImageUrl::attachment(ImageUrl::first_attachment(&attachments)).unwrap();
+        assert_eq!(url, "https://cdn.discordapp.com/attachments/1/2/clip");
+
+        let Err(err) = ImageUrl::attachment(ImageUrl::first_attachment(&attachments[..3])) else {
+            panic!("non-media attachment was accepted");
+        };
+        // the same error as no attachments at all, so that the other image sources are still tried
+        assert!(matches!(err, TagParseError::NoAttachment));
+    }
+
+    #[test]
+    fn empty_attachments_are_skipped_for_later_ones() {
+        let attachments = [
+            attachment("empty.png", 0),
+            attachment("notes.txt", 10),
+            attachment("cat.png", 10),
+        ];
+
+        let ImageUrl(url) = ImageUrl::attachment(ImageUrl::first_attachment(&attachments)).unwrap();
+        assert_eq!(url, "https://cdn.discordapp.com/attachments/1/2/cat.png");
+        assert!(!ImageUrl::only_empty_media_attachments(&attachments));
+    }
+
+    #[test]
+    fn only_empty_attachments_are_reported_as_empty() {
+        let attachments = [attachment("notes.txt", 10), attachment("empty.png", 0)];
+
+        let Err(err) = ImageUrl::attachment(ImageUrl::first_attachment(&attachments)) else {
+            panic!("empty attachment was accepted");
+        };
+        assert!(matches!(err, TagParseError::EmptyAttachment));
+        // low severity, so that the other image sources are still tried
+        assert!(err.get_severity() == ErrorSeverity::Low);
+        assert!(ImageUrl::only_empty_media_attachments(&attachments));
+
+        // without any media attachments, there is nothing to report
+        assert!(!ImageUrl::only_empty_media_attachments(&[attachment("notes.txt", 0)]));
+        assert!(!ImageUrl::only_empty_media_attachments(&[]));
+    }
+
+    #[test]
+    fn anchors_parse_in_any_separator_or_case() {
+        for (name, anchor) in Anchor::CHOICES {
+            assert_eq!(name.parse::<Anchor>().unwrap(), *anchor, "{name:?}");
+            assert_eq!(name.replace('-', " ").parse::<Anchor>().unwrap(), *anchor, "{name:?}");
+            assert_eq!(name.to_uppercase().parse::<Anchor>().unwrap(), *anchor, "{name:?}");
+        }
+
+        assert_eq!("  bottom_right ".parse::<Anchor>().unwrap(), Anchor::BottomRight);
+        assert_eq!("Top -- Left".parse::<Anchor>().unwrap(), Anchor::TopLeft);
+    }
+
+    #[test]
+    fn anchor_fractions_span_the_available_space() {
+        assert_eq!(Anchor::default(), Anchor::Center);
+        assert_eq!(Anchor::default().fractions(), (0.5, 0.5));
+        assert_eq!(Anchor::TopLeft.fractions(), (0.0, 0.0));
+        assert_eq!(Anchor::Right.fractions(), (1.0, 0.5));
+        assert_eq!(Anchor::BottomRight.fractions(), (1.0, 1.0));
+    }
+
+    #[test]
+    fn unknown_anchors_are_rejected_with_the_valid_names() {
+        for input in ["middle", "top-center", "", "left top"] {
+            let Err(TagParseError::InvalidChoice((_, valid))) = input.parse::<Anchor>() else {
+                panic!("{input:?} was not rejected");
+            };
+            assert_eq!(valid.len(), 9, "{input:?}");
+            assert!(valid.contains(&"top-left") && valid.contains(&"center"), "{input:?}");
+        }
+    }
+
+    fn urls(urls: &[&str]) -> Vec<ImageUrl> {
+        urls.iter().map(|url| ImageUrl((*url).to_owned())).collect()
+    }
+
+    #[test]
+    fn comparison_takes_two_urls_and_a_split() {
+        let mut images = urls(&["https://a.png", "https://b.png"]);
+        Comparison::add_attachments(&mut images, &[attachment("c.png", 10)]);
+
+        let (ImageUrl(left), ImageUrl(right)) = Comparison::pair(images).unwrap();
+        assert_eq!((&*left, &*right), ("https://a.png", "https://b.png"));
+        assert_eq!(Percentage::parse_str("30%").unwrap().as_fraction(), 0.3);
+    }
+
+    #[test]
+    fn comparison_fills_up_from_attachments() {
+        let attachments = [
+            attachment("a.png", 10),
+            attachment("notes.txt", 10),
+            attachment("b.png", 10),
+        ];
+
+        let mut images = Vec::new();
+        Comparison::add_attachments(&mut images, &attachments);
+        let (ImageUrl(left), ImageUrl(right)) = Comparison::pair(images).unwrap();
+        assert!(left.ends_with("/a.png") && right.ends_with("/b.png"));
+
+        // a URL given as a word comes first
+        let mut images = urls(&["https://first.png"]);
+        Comparison::add_attachments(&mut images, &attachments);
+        let (ImageUrl(left), ImageUrl(right)) = Comparison::pair(images).unwrap();
+        assert!(left == "https://first.png" && right.ends_with("/a.png"));
+    }
+
+    #[test]
+    fn comparison_of_one_image_is_rejected() {
+        let mut images = urls(&["https://a.png"]);
+        Comparison::add_attachments(&mut images, &[attachment("empty.png", 0), attachment("notes.txt", 10)]);
+        assert_eq!(images.len(), 1);
+
+        assert!(matches!(
+            Comparison::pair(images),
+            Err(TagParseError::ComparisonRequiresTwoImages)
+        ));
+        assert!(matches!(
+            Comparison::pair(Vec::new()),
+            Err(TagParseError::ComparisonRequiresTwoImages)
+        ));
+    }
+
+    #[test]
+    fn codeblock_language_needs_a_following_line() {
+        let block = Codeblock::parse("```rust\nfn main() {}\n```");
+        assert_eq!(block.language.as_deref(), Some("rust"));
+        assert_eq!(block.code, "fn main() {}\n");
+
+        for input in ["```hello```", "```hello\n```", "`hello`", "hello"] {
+            let block = Codeblock::parse(input);
+            assert_eq!(block.language, None, "{input:?}");
+            assert_eq!(block.code.trim(), "hello", "{input:?}");
+        }
+    }
+
+    #[test]
+    fn unterminated_codeblock_runs_to_the_end() {
+        let block = Codeblock::parse("```py\nprint(1)");
+        assert_eq!(block.language.as_deref(), Some("py"));
+        assert_eq!(block.code, "print(1)");
+
+        let block = Codeblock::parse("```\nprint(1)");
+        assert_eq!(block.language, None);
+        assert_eq!(block.code, "print(1)");
+    }
+
+    #[test]
+    fn time_formats_back_into_units_it_parses() {
+        for (input, formatted) in [
+            ("1h20m30s", "1h 20m 30s"),
+            ("2d3h", "2d 3h"),
+            ("500ms", "500ms"),
+            ("1s500ms", "1s 500ms"),
+            ("90", "1m 30s"),
+            ("0s", "0s"),
+        ] {
+            let time = Time::parse(input).unwrap();
+            assert_eq!(time.to_string(), formatted);
+            assert_eq!(Time::parse(formatted).unwrap().millis, time.millis);
+        }
+    }
+
+    #[test]
+    fn milliseconds_are_not_read_as_minutes() {
+        assert_eq!(Time::parse("5ms").unwrap().millis, 5);
+        assert_eq!(Time::parse("5m").unwrap().millis, 5 * 60 * 1000);
+    }
+
+    #[test]
+    fn fps_parses_with_or_without_a_suffix() {
+        assert_eq!(Fps::parse_str("30").unwrap().0, 30.0);
+        assert_eq!(Fps::parse_str("24fps").unwrap().0, 24.0);
+        assert_eq!(Fps::parse_str("12.5 FPS").unwrap().0, 12.5);
+    }
+
+    #[test]
+    fn fps_outside_the_range_is_rejected() {
+        assert!(matches!(Fps::parse_str("0"), Err(TagParseError::FpsOutOfRange(fps)) if fps == 0.0));
+        assert!(matches!(Fps::parse_str("120fps"), Err(TagParseError::FpsOutOfRange(fps)) if fps == 120.0));
+        assert!(matches!(Fps::parse_str("fast"), Err(TagParseError::ParseFloatError(_))));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Effect {
+        Blur,
+        Invert,
+        Sharpen,
+    }
+
+    impl Choice for Effect {
+        const CHOICES: &'static [(&'static str, Self)] = &[
+            ("blur", Self::Blur),
+            ("invert", Self::Invert),
+            ("sharpen", Self::Sharpen),
+        ];
+    }
+
+    #[test]
+    fn set_keeps_the_order_values_were_given_in() {
+        let Set(effects) = "sharpen, BLUR,invert".parse::<Set<Effect>>().unwrap();
+        assert_eq!(effects, [Effect::Sharpen, Effect::Blur, Effect::Invert]);
+    }
+
+    #[test]
+    fn set_collapses_duplicates() {
+        let Set(effects) = "blur,invert,Blur,,blur".parse::<Set<Effect>>().unwrap();
+        assert_eq!(effects, [Effect::Blur, Effect::Invert]);
+    }
+
+    #[test]
+    fn set_rejects_any_invalid_value() {
+        let result = "blur,sepia,invert".parse::<Set<Effect>>();
+        assert!(matches!(
+            result,
+            Err(TagParseError::InvalidChoice((name, choices))) if name == "sepia" && choices.len() == 3
+        ));
+
+        assert!(matches!(
+            " , ".parse::<Set<Effect>>(),
+            Err(TagParseError::InvalidChoice(_))
+        ));
+    }
+
+    fn parse_snowflake<M>(input: &str) -> Result<u64, TagParseError> {
+        input.parse::<Snowflake<M>>().map(|Snowflake(id)| id.get())
+    }
+
+    #[test]
+    fn snowflakes_parse_for_any_marker() {
+        let id = "1099115731301449758";
+
+        assert_eq!(parse_snowflake::<UserMarker>(id).unwrap(), 1099115731301449758);
+        assert_eq!(parse_snowflake::<ChannelMarker>(id).unwrap(), 1099115731301449758);
+        assert_eq!(
+            parse_snowflake::<RoleMarker>("80351110224678912").unwrap(),
+            80351110224678912
+        );
+        assert_eq!(
+            "80351110224678912"
+                .parse::<Snowflake<UserMarker>>()
+                .unwrap()
+                .timestamp_ms(),
+            1_439_227_597_529
+        );
+    }
+
+    #[test]
+    fn invalid_snowflakes_are_rejected() {
+        for input in [
+            "12345",
+            "123456789012345678901",
+            "80351110224678912a",
+            "-8035111022467891",
+            "0000000000000000",
+            "18446744073709551615",
+        ] {
+            assert!(
+                matches!(parse_snowflake::<UserMarker>(input), Err(TagParseError::InvalidSnowflake(s)) if s == input),
+                "{input:?}"
+            );
+            assert!(parse_snowflake::<ChannelMarker>(input).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn bare_integers_are_seconds() {
+        assert_eq!(Time::parse("30").unwrap().millis, 30_000);
+        assert_eq!(Time::parse("0").unwrap().millis, 0);
+        assert!(matches!(
+            Time::parse("18446744073709552"),
+            Err(ParseToMillisError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn malformed_times_are_rejected() {
+        for input in ["30x", "abc", "", "-5"] {
+            assert!(
+                matches!(Time::parse(input), Err(ParseToMillisError::InvalidFormat)),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn times_with_units_are_parsed_as_before() {
+        for input in ["1m30", "1m30s", "2h", "1s500ms"] {
+            assert_eq!(
+                Time::parse(input).unwrap().millis,
+                parse_to_millis(input).unwrap(),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn urls_lose_surrounding_brackets_and_punctuation() {
+        for input in [
+            "https://example.com/a.png",
+            "<https://example.com/a.png>",
+            "(https://example.com/a.png)",
+            "\"https://example.com/a.png\",",
+            "https://example.com/a.png.",
+            "(<https://example.com/a.png>)!?",
+        ] {
+            assert_eq!(clean_url(input), "https://example.com/a.png", "{input:?}");
+        }
+    }
+
+    #[test]
+    fn url_contents_are_kept() {
+        for url in [
+            "https://example.com/a.png?size=1024&x=(1)",
+            "https://example.com/page#section",
+            "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+        ] {
+            assert_eq!(clean_url(url), url);
+            assert_eq!(clean_url(&format!("({url}).")), url);
+        }
+    }
+
+    #[test]
+    fn url_schemes_are_stripped_regardless_of_case() {
+        for url in [
+            "https://tenor.com/view/cat-gif-12345",
+            "HTTPS://tenor.com/view/cat-gif-12345",
+            "Http://www.tenor.com/view/cat-gif-12345",
+            "hTtPs://WWW.tenor.com/view/cat-gif-12345",
+        ] {
+            assert_eq!(strip_url_scheme(url), "tenor.com/view/cat-gif-12345", "{url:?}");
+            assert!(is_tenor_page(url), "{url:?}");
+        }
+
+        assert_eq!(
+            giphy_media_url("HTTPS://giphy.com/gifs/dance-abc123").as_deref(),
+            Some("https://media.giphy.com/media/abc123/giphy.gif")
+        );
+        assert_eq!(
+            imgur_media_url("Http://imgur.com/abc123").as_deref(),
+            Some("https://i.imgur.com/abc123.png")
+        );
+        // too short to have a scheme, or the scheme's length falls within a character
+        assert_eq!(strip_url_scheme("htt"), "htt");
+        assert_eq!(strip_url_scheme("aéééé"), "aéééé");
+    }
+
+    /// Parses an optional number like `Option<u64>` does, followed by a word.
+    async fn number_then_word(input: &str) -> Result<(Option<u64>, Word), TagParseError> {
+        let mut ctxt = ParseCtxt::for_args(input);
+
+        let number = optional(commit_group_if_ok!(&mut ctxt, |fork| {
+            let word = fork.next_word(None)?;
+            parse_number::<u64>(&word, "a positive whole number")
+        }))?;
+        let word = ctxt.next_word(None)?;
+
+        Ok((number, Word(word.into_owned())))
+    }
+
+    #[tokio::test]
+    async fn optional_number_takes_a_number() {
+        let (number, Word(word)) = number_then_word("12 hello").await.unwrap();
+        assert_eq!(number, Some(12));
+        assert_eq!(word, "hello");
+    }
+
+    #[tokio::test]
+    async fn optional_number_leaves_other_words_for_the_next_argument() {
+        for input in ["hello", "hello world", "-3 world"] {
+            let (number, Word(word)) = number_then_word(input).await.unwrap();
+            assert_eq!(number, None, "{input:?}");
+            assert_eq!(word, input.split(' ').next().unwrap(), "{input:?}");
+        }
+
+        assert!(matches!(
+            number_then_word("").await,
+            Err(TagParseError::ArgsExhausted(_))
+        ));
+    }
+
+    #[test]
+    fn optional_keeps_high_severity_errors() {
+        assert!(matches!(
+            optional::<u64>(Err(TagParseError::FpsOutOfRange(0.0))),
+            Err(TagParseError::FpsOutOfRange(_))
+        ));
+    }
+
+    /// Parses an optional seed like `Option<Seed>` does, and the RNG it gives.
+    async fn optional_seed(input: &str) -> Result<(Option<Seed>, StdRng), TagParseError> {
+        let mut ctxt = ParseCtxt::for_args(input);
+
+        let seed = optional(commit_group_if_ok!(&mut ctxt, |fork| {
+            let word = fork.next_word(None)?;
+            Seed::parse_str(&word)
+        }))?;
+
+        Ok((seed, seed.unwrap_or_else(Seed::random).rng()))
+    }
+
+    #[tokio::test]
+    async fn same_seed_gives_the_same_randomness() {
+        let (first, mut first_rng) = optional_seed("1234").await.unwrap();
+        let (second, mut second_rng) = optional_seed("1234").await.unwrap();
+        assert_eq!(first, Some(Seed(1234)));
+        assert_eq!(first, second);
+
+        let first_values = (0..8).map(|_| first_rng.gen::<u64>()).collect::<Vec<_>>();
+        let second_values = (0..8).map(|_| second_rng.gen::<u64>()).collect::<Vec<_>>();
+        assert_eq!(first_values, second_values);
+    }
+
+    #[tokio::test]
+    async fn missing_seed_uses_a_random_one() {
+        for input in ["", "hello", "-1"] {
+            let (seed, _) = optional_seed(input).await.unwrap();
+            assert_eq!(seed, None, "{input:?}");
+        }
+
+        for _ in 0..100 {
+            // always small enough to be passed back in through a slash command
+            assert!(Seed::random().0 < 1 << 53);
+        }
+    }
+
+    #[test]
+    fn strength_defaults_when_not_given_a_number() {
+        for word in ["", "hello", "%", "50.5"] {
+            assert!(Strength::<50>::parse_word(word).is_none(), "{word:?}");
+        }
+
+        assert_eq!(Strength::<50>::default().0, 50);
+        assert_eq!(Strength::<80, true>::default().0, 80);
+        // a default above the maximum is capped
+        assert_eq!(Strength::<200>::default().0, 100);
+    }
+
+    #[test]
+    fn strength_in_range_is_kept() {
+        for (word, strength) in [("0", 0), ("75", 75), ("75%", 75), ("100", 100)] {
+            let Strength(parsed) = Strength::<50>::parse_word(word).unwrap().unwrap();
+            assert_eq!(parsed, strength, "{word:?}");
+        }
+
+        assert_eq!(Strength::<50>(25).as_fraction(), 0.25);
+    }
+
+    #[test]
+    fn strength_out_of_range_is_rejected_or_clamped() {
+        assert!(matches!(
+            Strength::<50>::parse_word("150"),
+            Some(Err(TagParseError::StrengthOutOfRange(150)))
+        ));
+        assert!(matches!(
+            Strength::<50>::parse_word("-1%"),
+            Some(Err(TagParseError::StrengthOutOfRange(-1)))
+        ));
+
+        let Strength(high) = Strength::<50, true>::parse_word("150").unwrap().unwrap();
+        let Strength(low) = Strength::<50, true>::parse_word("-1%").unwrap().unwrap();
+        assert_eq!((low, high), (0, 100));
+    }
+
+    fn crf(quality: &str, codec: Codec) -> Result<u8, TagParseError> {
+        quality.parse::<Quality>()?.crf(codec)
+    }
+
+    #[test]
+    fn quality_percentages_map_onto_each_codecs_range() {
+        for (quality, h264, vp9) in [("100", 0, 0), ("75%", 13, 16), ("0.5", 26, 32), ("0", 51, 63)] {
+            assert_eq!(crf(quality, Codec::H264).unwrap(), h264, "{quality:?}");
+            assert_eq!(crf(quality, Codec::Vp9).unwrap(), vp9, "{quality:?}");
+        }
+
+        assert!(matches!(
+            crf("150", Codec::H264),
+            Err(TagParseError::PercentageOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn crf_values_are_checked_against_the_codecs_range() {
+        assert_eq!(crf("crf:23", Codec::H264).unwrap(), 23);
+        assert_eq!(crf("crf:51", Codec::H264).unwrap(), 51);
+        assert_eq!(crf("crf:60", Codec::Vp9).unwrap(), 60);
+
+        let err = crf("crf:60", Codec::H264).unwrap_err();
+        assert!(matches!(err, TagParseError::CrfOutOfRange((60, Codec::H264))));
+        assert_eq!(
+            err.to_string(),
+            "a CRF of 60 is out of range for h264 (must be between 0 and 51)"
+        );
+        assert!(matches!(
+            crf("crf:64", Codec::Vp9),
+            Err(TagParseError::CrfOutOfRange((64, Codec::Vp9)))
+        ));
+        assert!(crf("crf:-1", Codec::Vp9).is_err());
+    }
+
+    #[test]
+    fn rest_option_keeps_spacing() {
+        let input = "\t ascii  art\n  line two \t";
+        let Rest(rest) = Rest::from_option(&CommandOptionValue::String(input.to_owned())).unwrap();
+        assert_eq!(rest, input);
+
+        assert!(matches!(
+            Rest::from_option(&CommandOptionValue::Integer(1)),
+            Err(TagParseError::MismatchedCommandOptionType(_))
+        ));
+    }
+
+    #[test]
+    fn relative_time_is_in_the_past_by_default() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let two_hours_ago = now - Duration::from_secs(2 * 60 * 60);
+
+        assert_eq!(RelativeTime::parse_from("2h", now).unwrap().at, two_hours_ago);
+        assert_eq!(RelativeTime::parse_from("-2h", now).unwrap().at, two_hours_ago);
+        assert_eq!(
+            RelativeTime::parse_from("1h20m", now).unwrap().at,
+            now - Duration::from_secs(80 * 60)
+        );
+    }
+
+    #[test]
+    fn relative_time_with_a_plus_is_in_the_future() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        assert_eq!(
+            RelativeTime::parse_from("+2h", now).unwrap().at,
+            now + Duration::from_secs(2 * 60 * 60)
+        );
+        assert_eq!(
+            RelativeTime::parse_from("+30", now).unwrap().at,
+            now + Duration::from_secs(30)
+        );
+
+        for input in ["+", "-", "soon"] {
+            assert!(RelativeTime::parse_from(input, now).is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn every_image_source_is_tried_by_default() {
+        use ImageSource::*;
+
+        assert_eq!(
+            ImageResolveConfig::ALL.raw_message_sources(ImageSourcePrecedence::Attachment),
+            [Mention, MessageLink, Url, Attachment, Reply, Emoji, Sticker, History]
+        );
+        assert_eq!(
+            ImageResolveConfig::ALL.raw_message_sources(ImageSourcePrecedence::Reply),
+            [Mention, MessageLink, Url, Reply, Attachment, Emoji, Sticker, History]
+        );
+        assert_eq!(
+            ImageResolveConfig::ALL.interaction_sources(),
+            [Attachment, Mention, MessageLink, Url, Emoji, Link, History]
+        );
+    }
+
+    #[test]
+    fn attachments_win_over_reply_images_by_default() {
+        let position = |sources: &[ImageSource], source| sources.iter().position(|s| *s == source).unwrap();
+
+        // with both an attachment and a reply image present, whichever source is tried first wins
+        let sources = ImageResolveConfig::ALL.raw_message_sources(ImageSourcePrecedence::default());
+        assert!(position(&sources, ImageSource::Attachment) < position(&sources, ImageSource::Reply));
+
+        let sources = ImageResolveConfig::ALL.raw_message_sources(ImageSourcePrecedence::Reply);
+        assert!(position(&sources, ImageSource::Reply) < position(&sources, ImageSource::Attachment));
+    }
+
+    #[test]
+    fn disabled_image_sources_are_never_tried() {
+        let no_history = ImageResolveConfig {
+            history: false,
+            ..ImageResolveConfig::ALL
+        };
+
+        for precedence in [ImageSourcePrecedence::Attachment, ImageSourcePrecedence::Reply] {
+            assert!(
+                !no_history
+                    .raw_message_sources(precedence)
+                    .contains(&ImageSource::History)
+            );
+        }
+        assert!(!no_history.interaction_sources().contains(&ImageSource::History));
+
+        let explicit_only = ImageResolveConfig {
+            attachment: false,
+            reply: false,
+            history: false,
+            mention: false,
+            emoji: false,
+        };
+        assert_eq!(
+            explicit_only.raw_message_sources(ImageSourcePrecedence::Attachment),
+            [ImageSource::MessageLink, ImageSource::Url, ImageSource::Sticker]
+        );
+        assert_eq!(
+            explicit_only.interaction_sources(),
+            [ImageSource::MessageLink, ImageSource::Url, ImageSource::Link]
+        );
+    }
+
+    #[test]
+    fn unresolved_replies_are_fetched_by_id() {
+        let channel_id = Id::new(1);
+        let message_id = Id::new(2);
+
+        // e.g. the replied-to message was deleted, so Discord only sent the reference
+        assert_eq!(
+            ReplyTarget::<()>::new(None, Some((None, Some(message_id))), channel_id),
+            ReplyTarget::Unresolved(channel_id, message_id)
+        );
+        assert_eq!(
+            ReplyTarget::<()>::new(None, Some((Some(Id::new(3)), Some(message_id))), channel_id),
+            ReplyTarget::Unresolved(Id::new(3), message_id)
+        );
+
+        // the fetch failing is reported, rather than treated as there being no reply
+        assert!(TagParseError::FailedToGetReferencedMessage.get_severity() == ErrorSeverity::High);
+    }
+
+    #[test]
+    fn resolved_or_missing_replies_need_no_fetch() {
+        let channel_id = Id::new(1);
+
+        assert_eq!(
+            ReplyTarget::new(Some("reply"), Some((None, Some(Id::new(2)))), channel_id),
+            ReplyTarget::Resolved("reply")
+        );
+        assert_eq!(ReplyTarget::<()>::new(None, None, channel_id), ReplyTarget::NoReply);
+        assert_eq!(
+            ReplyTarget::<()>::new(None, Some((Some(channel_id), None)), channel_id),
+            ReplyTarget::NoReply
+        );
+    }
+
+    fn word(word: &str) -> impl Future<Output = Result<Word, TagParseError>> {
+        std::future::ready(Ok(Word(word.to_owned())))
+    }
+
+    #[tokio::test]
+    async fn either_prefers_the_first_type() {
+        let number = parse_number::<u64>("42", "a positive whole number");
+        let either = Either::<u64, Word>::or_else(number, || word("42")).await;
+
+        assert!(matches!(either, Ok(Either::Left(42))));
+    }
+
+    #[tokio::test]
+    async fn either_falls_back_when_the_first_type_does_not_parse() {
+        for input in ["abc", "-1", "4.2"] {
+            let number = parse_number::<u64>(input, "a positive whole number");
+            let either = Either::<u64, Word>::or_else(number, || word(input)).await;
+
+            assert!(matches!(either, Ok(Either::Right(Word(w))) if w == input), "{input:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn either_does_not_fall_back_from_high_severity_errors() {
+        let either = Either::<u64, Word>::or_else(Err(TagParseError::FpsOutOfRange(0.0)), || word("0")).await;
+
+        assert!(matches!(either, Err(TagParseError::FpsOutOfRange(_))));
+    }
+
+    #[tokio::test]
+    async fn failed_emoji_lookups_fall_back_to_the_cdn() {
+        // nothing listens on port 1, so the connection is refused straight away
+        let (url, cacheable) = ImageUrl::emoji_image_url(&Client::new(), "http://127.0.0.1:1", "1f389").await;
+
+        assert_eq!(
+            url,
+            "https://cdn.jsdelivr.net/gh/twitter/twemoji/assets/72x72/1f389.png"
+        );
+        assert!(!cacheable);
+    }
+
+    /// Serves emoji metadata on a local port, answering every request with `response`, and counts
+    /// the requests made.
+    async fn serve_emoji_metadata(response: &'static [u8]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counted.fetch_add(1, Ordering::SeqCst);
+
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+
+                _ = socket.write_all(response).await;
+            }
+        });
+
+        (url, requests)
+    }
+
+    const EMOJI_METADATA: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 61\r\nConnection: close\r\n\r\n\
+        {\"vendor_images\":{\"twitter\":\"https://example.com/1f389.png\"}}";
+
+    #[tokio::test]
+    async fn emoji_lookups_are_cached() {
+        let (base_url, requests) = serve_emoji_metadata(EMOJI_METADATA).await;
+        let cache = Mutex::new(HashMap::new());
+        let client = Client::new();
+
+        for _ in 0..2 {
+            let url = ImageUrl::cached_emoji_image_url(&cache, &client, &base_url, "1f389".to_owned()).await;
+            assert_eq!(url, "https://example.com/1f389.png");
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // each emoji is looked up separately
+        ImageUrl::cached_emoji_image_url(&cache, &client, &base_url, "1f600".to_owned()).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_emoji_lookups_are_not_cached() {
+        let (base_url, requests) =
+            serve_emoji_metadata(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await;
+        let cache = Mutex::new(HashMap::new());
+        let client = Client::new();
+
+        for _ in 0..2 {
+            let url = ImageUrl::cached_emoji_image_url(&cache, &client, &base_url, "1f389".to_owned()).await;
+            assert_eq!(
+                url,
+                "https://cdn.jsdelivr.net/gh/twitter/twemoji/assets/72x72/1f389.png"
+            );
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert!(cache.lock().unwrap().is_empty());
+    }
+}
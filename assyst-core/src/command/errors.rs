@@ -1,11 +1,13 @@
 use std::fmt::Display;
-use std::num::{ParseFloatError, ParseIntError};
+use std::num::{ParseFloatError, ParseIntError, TryFromIntError};
 use std::time::Duration;
 
-use assyst_common::util::ParseToMillisError;
+use assyst_common::util::colour::ParseColourError;
+use assyst_common::util::{format_time, ParseFileSizeError, ParseToMillisError};
 use twilight_model::application::interaction::application_command::CommandOptionValue;
 use twilight_model::channel::message::sticker::StickerFormatType;
 
+use super::arguments::{Codec, Fps};
 use super::Label;
 use crate::downloader::DownloadError;
 use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
@@ -13,6 +15,8 @@ use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSever
 #[derive(Debug)]
 pub enum ExecutionError {
     Parse(TagParseError),
+    /// An argument of a raw message command failed to parse.
+    Argument(ArgumentError),
     Command(anyhow::Error),
     MetadataCheck(MetadataCheckError),
 }
@@ -36,6 +40,7 @@ impl Display for ExecutionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ExecutionError::Parse(p) => p.fmt(f),
+            ExecutionError::Argument(a) => a.fmt(f),
             ExecutionError::Command(c) => c.fmt(f),
             ExecutionError::MetadataCheck(m) => m.fmt(f),
         }
@@ -43,6 +48,52 @@ impl Display for ExecutionError {
 }
 impl std::error::Error for ExecutionError {}
 
+/// A positional argument that failed to parse, along with what the command expected, so that the
+/// user can be shown the command's full usage rather than just the error for one argument.
+#[derive(Debug)]
+pub struct ArgumentError {
+    /// The position of the argument that failed to parse, among the command's arguments.
+    pub index: usize,
+    /// The name of the argument that failed to parse.
+    pub name: &'static str,
+    /// The usage of each of the command's arguments, in order (e.g., `<width>`).
+    pub usage: Vec<String>,
+    /// The subcommand that was run, if the command is a group.
+    pub subcommand: Option<String>,
+    pub error: TagParseError,
+}
+impl ArgumentError {
+    /// Formats the error along with the command's usage, pointing out the argument that failed.
+    /// `invocation` is how the command was invoked (the prefix and command name).
+    pub fn usage_message(&self, invocation: &str) -> String {
+        let mut line = format!("Usage: {invocation}");
+        if let Some(subcommand) = &self.subcommand {
+            line = format!("{line} {subcommand}");
+        }
+
+        let mut marker = String::new();
+        for (i, usage) in self.usage.iter().enumerate() {
+            line.push(' ');
+            if i == self.index {
+                marker = format!(
+                    "{}{}",
+                    " ".repeat(line.chars().count()),
+                    "^".repeat(usage.chars().count())
+                );
+            }
+            line.push_str(usage);
+        }
+
+        format!("`{}`: {}\n```\n{line}\n{marker}\n```", self.name, self.error)
+    }
+}
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.error)
+    }
+}
+impl std::error::Error for ArgumentError {}
+
 #[derive(Debug)]
 pub enum MetadataCheckError {
     CommandOnCooldown(Duration),
@@ -84,33 +135,64 @@ pub enum TagParseError {
     ArgsExhausted(ArgsExhausted),
     SubcommandArgsExhausted(String),
     ParseIntError(ParseIntError),
+    TryFromIntError(TryFromIntError),
     ParseFloatError(ParseFloatError),
+    /// A word that couldn't be parsed as a number, along with a description of what was expected.
+    InvalidNumber((String, &'static str)),
+    ParseBoolError(String),
     ParseToMillisError(ParseToMillisError),
+    ParseFileSizeError(ParseFileSizeError),
+    ParseColourError(ParseColourError),
+    InvalidRegex(regex::Error),
+    InvalidChoice((String, Vec<&'static str>)),
+    FpsOutOfRange(f64),
+    PercentageOutOfRange(f64),
+    StrengthOutOfRange(i64),
+    CrfOutOfRange((u8, Codec)),
+    TimeOutOfRange((u64, u64, u64)),
+    ComparisonRequiresTwoImages,
     // NB: boxed to reduce size -- twilight errors are very large (100+b), which would cause the
     // size of this enum to explode
     // these are very unlikely to occur, so it's okay
     TwilightHttp(Box<twilight_http::Error>),
     TwilightDeserialize(Box<twilight_http::response::DeserializeBodyError>),
     DownloadError(DownloadError),
+    DownloadTimeout(Duration),
+    /// Reading all of a command's arguments took longer than the configured budget.
+    ArgumentsTimeout(Duration),
     UnsupportedSticker(StickerFormatType),
     Reqwest(reqwest::Error),
     NoAttachment,
+    EmptyAttachment,
     NoMention,
+    InvalidSnowflake(String),
+    /// More users were given than a user list argument accepts (the maximum).
+    TooManyUsers(usize),
     NoUrl,
+    NoMessageLink,
+    FailedToGetLinkedMessage,
+    /// The linked message is in another server, or in a channel the invoking user can't read.
+    LinkedMessageNotAccessible,
     NoReply,
+    FailedToGetReferencedMessage,
     NoEmbed,
     NoEmoji,
     NoSticker,
     NoImageInHistory,
     NoImageFound,
+    NotAnImage,
     MediaDownloadFail,
     InvalidSubcommand(String),
     NoInteractionSubcommandProvided,
     InteractionCommandIsBaseSubcommand,
     MismatchedCommandOptionType((String, CommandOptionValue)),
     FlagParseError(anyhow::Error),
+    /// The list of valid flags, requested with `--help` (see [`super::flags::FlagsHelp`]).
+    FlagsHelp(String),
     FailedToGetMessageHistory,
     MessageHistoryUnavailableInContext,
+    /// A parser that reads the invoking message was used without one.
+    NoMessageContext,
 }
 
 impl GetErrorSeverity for TagParseError {
@@ -119,9 +201,18 @@ impl GetErrorSeverity for TagParseError {
             Self::TwilightHttp(..)
             | Self::TwilightDeserialize(..)
             | Self::DownloadError(..)
+            | Self::DownloadTimeout(..)
+            | Self::ArgumentsTimeout(..)
+            | Self::NotAnImage
             | Self::UnsupportedSticker(..)
             | Self::Reqwest(..)
+            | Self::FpsOutOfRange(..)
+            | Self::PercentageOutOfRange(..)
+            | Self::StrengthOutOfRange(..)
+            | Self::CrfOutOfRange(..)
+            | Self::TimeOutOfRange(..)
             | Self::FailedToGetMessageHistory
+            | Self::FailedToGetReferencedMessage
             | Self::MessageHistoryUnavailableInContext
             | Self::NoInteractionSubcommandProvided => ErrorSeverity::High,
             _ => ErrorSeverity::Low,
@@ -148,12 +239,69 @@ impl Display for TagParseError {
             TagParseError::ParseIntError(err) => {
                 write!(f, "failed to parse an argument as a whole number: {err}")
             },
+            TagParseError::TryFromIntError(err) => {
+                write!(f, "a whole number argument is out of range: {err}")
+            },
             TagParseError::ParseFloatError(err) => {
                 write!(f, "failed to parse an argument as a decimal number: {err}")
             },
+            TagParseError::InvalidNumber((word, expected)) => {
+                write!(f, "expected {expected}, got `{word}`")
+            },
+            TagParseError::ParseBoolError(word) => {
+                write!(
+                    f,
+                    "failed to parse an argument as true or false: {word} is not one of true/false/yes/no/on/off"
+                )
+            },
             TagParseError::ParseToMillisError(err) => {
                 write!(f, "failed to parse an argument as time: {err}")
             },
+            TagParseError::ParseFileSizeError(err) => {
+                write!(f, "failed to parse an argument as a file size: {err}")
+            },
+            TagParseError::ParseColourError(err) => {
+                write!(f, "failed to parse an argument as a colour: {err}")
+            },
+            TagParseError::InvalidRegex(err) => {
+                write!(f, "failed to parse an argument as a regular expression: {err}")
+            },
+            TagParseError::InvalidChoice((value, valid)) => {
+                write!(f, "unknown value {value} (expected one of: {})", valid.join(", "))
+            },
+            TagParseError::FpsOutOfRange(fps) => {
+                write!(
+                    f,
+                    "a frame rate of {fps} is out of range (must be between {} and {})",
+                    Fps::MIN,
+                    Fps::MAX
+                )
+            },
+            TagParseError::PercentageOutOfRange(percentage) => {
+                write!(f, "a percentage of {percentage} is out of range (must be between 0 and 100)")
+            },
+            TagParseError::StrengthOutOfRange(strength) => {
+                write!(f, "a strength of {strength} is out of range (must be between 0 and 100)")
+            },
+            TagParseError::CrfOutOfRange((crf, codec)) => {
+                write!(
+                    f,
+                    "a CRF of {crf} is out of range for {codec} (must be between 0 and {})",
+                    codec.max_crf()
+                )
+            },
+            TagParseError::TimeOutOfRange((millis, min, max)) => {
+                write!(
+                    f,
+                    "a duration of {} is out of range (must be between {} and {})",
+                    format_time(*millis),
+                    format_time(*min),
+                    format_time(*max)
+                )
+            },
+            TagParseError::ComparisonRequiresTwoImages => {
+                f.write_str("two images are required for a comparison, but fewer were found")
+            },
             TagParseError::TwilightHttp(err) => {
                 write!(f, "failed to send a request to discord: {err}")
             },
@@ -161,14 +309,39 @@ impl Display for TagParseError {
                 write!(f, "failed to parse a response from discord: {err}")
             },
             TagParseError::DownloadError(err) => write!(f, "failed to download media: {err}"),
+            TagParseError::DownloadTimeout(t) => {
+                write!(f, "downloading the media took too long (over {} seconds)", t.as_secs())
+            },
+            TagParseError::ArgumentsTimeout(t) => {
+                write!(f, "reading the command's arguments timed out (took over {} seconds)", t.as_secs())
+            },
+            TagParseError::UnsupportedSticker(StickerFormatType::Lottie) => f.write_str(
+                "Lottie stickers are animated vector files and can't be used as images (only PNG, APNG and GIF stickers can be)",
+            ),
             TagParseError::UnsupportedSticker(sticker) => {
-                write!(f, "an unsupported sticker was found: {sticker:?}")
+                write!(
+                    f,
+                    "an unsupported sticker was found: {sticker:?} (only PNG, APNG and GIF stickers can be used)"
+                )
             },
             TagParseError::Reqwest(err) => write!(f, "failed to send a request: {err}"),
             TagParseError::NoAttachment => f.write_str("an attachment was expected but none were found"),
+            TagParseError::EmptyAttachment => f.write_str("the provided attachment is empty (0 bytes)"),
             TagParseError::NoMention => f.write_str("a mention argument was expected but none were found"),
+            TagParseError::InvalidSnowflake(input) => write!(f, "{input} is not a valid Discord ID"),
+            TagParseError::TooManyUsers(max) => write!(f, "at most {max} users can be given"),
             TagParseError::NoUrl => f.write_str("a URL argument was expected but none were found"),
+            TagParseError::NoMessageLink => f.write_str("a message link was expected but none were found"),
+            TagParseError::FailedToGetLinkedMessage => {
+                f.write_str("the linked message couldn't be loaded (the bot may not be able to see it)")
+            },
+            TagParseError::LinkedMessageNotAccessible => {
+                f.write_str("the linked message is in a server or channel you can't read from here")
+            },
             TagParseError::NoReply => f.write_str("a reply was expected but none were found"),
+            TagParseError::FailedToGetReferencedMessage => {
+                f.write_str("the replied-to message couldn't be loaded (it may have been deleted)")
+            },
             TagParseError::NoEmbed => f.write_str("an embed was expected but none were found"),
             TagParseError::NoEmoji => f.write_str("an emoji argument was expected but none were found"),
             TagParseError::NoSticker => f.write_str("a sticker was expected but none were found"),
@@ -178,6 +351,9 @@ impl Display for TagParseError {
             TagParseError::NoImageFound => {
                 f.write_str("an image was expected as an argument, but no image could be found")
             },
+            TagParseError::NotAnImage => {
+                f.write_str("the provided link doesn't point to an image or video (it may be a web page)")
+            },
             TagParseError::MediaDownloadFail => f.write_str("failed to download media content"),
             TagParseError::InvalidSubcommand(name) => {
                 write!(f, "no subcommand found for given subcommand name {name}")
@@ -195,12 +371,14 @@ impl Display for TagParseError {
                 f.write_str("Interaction subcommand is base subcommand")
             },
             TagParseError::FlagParseError(x) => write!(f, "Error parsing command flags ({x})"),
+            TagParseError::FlagsHelp(x) => write!(f, "Valid flags:\n{x}"),
             TagParseError::FailedToGetMessageHistory => f.write_str(
                 "Failed to get message history. Make sure Assyst has permission to do this. Assyst also cannot search for images through a global user install.",
             ),
             TagParseError::MessageHistoryUnavailableInContext => f.write_str(
                 "Assyst can't search the channel for images in a user install. Please provide an image to operate on.",
             ),
+            TagParseError::NoMessageContext => f.write_str("no message was available to read this argument from"),
         }
     }
 }
@@ -208,7 +386,11 @@ impl std::error::Error for TagParseError {}
 
 impl From<DownloadError> for TagParseError {
     fn from(v: DownloadError) -> Self {
-        Self::DownloadError(v)
+        match v {
+            DownloadError::Timeout(t) => Self::DownloadTimeout(t),
+            DownloadError::Shared(ref e) if let DownloadError::Timeout(t) = **e => Self::DownloadTimeout(t),
+            v => Self::DownloadError(v),
+        }
     }
 }
 
@@ -230,6 +412,24 @@ impl From<ParseToMillisError> for TagParseError {
     }
 }
 
+impl From<ParseFileSizeError> for TagParseError {
+    fn from(v: ParseFileSizeError) -> Self {
+        Self::ParseFileSizeError(v)
+    }
+}
+
+impl From<ParseColourError> for TagParseError {
+    fn from(v: ParseColourError) -> Self {
+        Self::ParseColourError(v)
+    }
+}
+
+impl From<regex::Error> for TagParseError {
+    fn from(v: regex::Error) -> Self {
+        Self::InvalidRegex(v)
+    }
+}
+
 impl From<ArgsExhausted> for TagParseError {
     fn from(value: ArgsExhausted) -> Self {
         Self::ArgsExhausted(value)
@@ -240,6 +440,11 @@ impl From<ParseIntError> for TagParseError {
         Self::ParseIntError(value)
     }
 }
+impl From<TryFromIntError> for TagParseError {
+    fn from(value: TryFromIntError) -> Self {
+        Self::TryFromIntError(value)
+    }
+}
 impl From<ParseFloatError> for TagParseError {
     fn from(value: ParseFloatError) -> Self {
         Self::ParseFloatError(value)
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use anyhow::{bail, Context};
 
+use super::errors::TagParseError;
+
 #[macro_export]
 macro_rules! int_arg_u64 {
     ($ctxt:expr, $s:expr, $d:expr) => {{
@@ -66,67 +69,539 @@ pub enum FlagType {
     WithValue,
     NoValue,
 }
+impl FlagType {
+    /// Gives this flag a single-character alias, e.g. `-q` for `--quality`.
+    pub fn with_short(self, short: char) -> Flag {
+        Flag::from(self).with_short(short)
+    }
 
-type ValidFlags = HashMap<&'static str, FlagType>;
+    /// Gives this flag a value to use when it isn't provided. Only meaningful for
+    /// [`FlagType::WithValue`].
+    pub fn with_default(self, default: &'static str) -> Flag {
+        Flag::from(self).with_default(default)
+    }
+
+    /// Describes what this flag does, for listing with `--help`.
+    pub fn with_description(self, description: &'static str) -> Flag {
+        Flag::from(self).with_description(description)
+    }
+}
+
+/// A valid flag, with an optional single-character alias, default value and description.
+pub struct Flag {
+    pub kind: FlagType,
+    pub short: Option<char>,
+    pub default: Option<&'static str>,
+    pub description: Option<&'static str>,
+}
+impl Flag {
+    pub fn with_short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    pub fn with_default(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+}
+impl From<FlagType> for Flag {
+    fn from(kind: FlagType) -> Self {
+        Self {
+            kind,
+            short: None,
+            default: None,
+            description: None,
+        }
+    }
+}
+
+/// The flags a command accepts, by their full name.
+pub type ValidFlags = HashMap<&'static str, Flag>;
+
+/// Flags that list every valid flag instead of being parsed, unless a command accepts a flag with
+/// the same name, in which case that takes priority.
+const HELP_FLAGS: &[&str] = &["help", "flags"];
+
+/// Returned (as an error) by the flag parsers when the user asked for the list of valid flags with
+/// `--help` or `--flags`, instead of any flags being parsed. Displays as that list.
+#[derive(Debug)]
+pub struct FlagsHelp(pub String);
+impl std::fmt::Display for FlagsHelp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl std::error::Error for FlagsHelp {}
+
+/// Lists every valid flag, one per line and sorted by name, along with its alias, whether it takes
+/// a value, its default and its description.
+pub fn describe_flags(valid_flags: &ValidFlags) -> String {
+    let mut flags = valid_flags.iter().collect::<Vec<_>>();
+    flags.sort_by_key(|(name, _)| *name);
+
+    flags
+        .into_iter()
+        .map(|(name, flag)| {
+            let mut line = format!("--{name}");
+            if let Some(short) = flag.short {
+                line += &format!(" (-{short})");
+            }
+            if let FlagType::WithValue = flag.kind {
+                line += " <value>";
+            }
+            if let Some(default) = flag.default {
+                line += &format!(" [default: {default}]");
+            }
+            if let Some(description) = flag.description {
+                line += &format!(": {description}");
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Converts an error from [`FlagDecode::from_str`] into a parse error, keeping a request for the
+/// list of valid flags (see [`FlagsHelp`]) distinct from a failure to parse them.
+pub fn flag_parse_error(error: anyhow::Error) -> TagParseError {
+    match error.downcast::<FlagsHelp>() {
+        Ok(help) => TagParseError::FlagsHelp(help.0),
+        Err(error) => TagParseError::FlagParseError(error),
+    }
+}
 
 pub trait FlagDecode {
+    /// Every flag this type accepts. Parsers should use this rather than building their own map, so
+    /// that the flags can also be listed elsewhere, such as in help text.
+    fn valid_flags() -> ValidFlags
+    where
+        Self: Sized;
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized;
+
+    /// Any values not attached to a flag, in the order they were given. Decoders that read
+    /// positionals (see [`flags_and_positionals_from_str`]) should override this.
+    fn positionals(&self) -> &[String] {
+        &[]
+    }
+}
+
+/// Parses the explicit value of a [`FlagType::NoValue`] flag, as in `--release=false`.
+fn parse_bool_value(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Parses flags, ignoring any values not attached to a flag. A value directly after a
+/// [`FlagType::NoValue`] flag (other than `true` or `false`) is an error, since it was most likely
+/// meant for that flag.
+///
+/// [`FlagType::NoValue`] flags map to `None` when given bare (`--release`), or to `Some("true")` or
+/// `Some("false")` when given a value explicitly (`--release=false` or `--release false`). Use
+/// [`flag_enabled`] to read them.
+pub fn flags_from_str(
+    input: &str,
+    valid_flags: HashMap<&'static str, impl Into<Flag>>,
+) -> anyhow::Result<HashMap<String, Option<String>>> {
+    parse_flags(input, valid_flags, false).map(|(flags, _)| flags)
+}
+
+/// Finds the canonical name of a flag, if `arg` is one. Short flags (e.g. `-q`) are only recognised
+/// if they are registered as an alias, so that values such as `-` or `-5` aren't mistaken for
+/// flags.
+fn flag_name(arg: &str, valid_flags: &ValidFlags) -> Option<String> {
+    if arg.starts_with("--") && arg.len() > 2 {
+        Some(arg[2..].to_owned())
+    } else if let Some(name) = arg.strip_prefix('—')
+        && !name.is_empty()
+    {
+        Some(name.to_owned())
+    } else if let Some(short) = arg.strip_prefix('-')
+        && short.len() == 1
+        && let Some(short) = short.chars().next()
+        && short.is_ascii_alphabetic()
+    {
+        valid_flags
+            .iter()
+            .find(|(_, flag)| flag.short == Some(short))
+            .map(|(name, _)| (*name).to_owned())
+    } else {
+        None
+    }
+}
+
+/// The number of single-character insertions, deletions or substitutions needed to turn `a` into
+/// `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Finds the valid flag closest to an unrecognised one, if any is close enough to likely be what
+/// was meant.
+fn closest_flag<'a>(name: &str, valid_flags: &'a ValidFlags) -> Option<&'a str> {
+    // allow roughly one typo per three characters, so short flags don't match everything
+    let threshold = (name.chars().count() / 3).max(1);
+
+    valid_flags
+        .keys()
+        .map(|flag| (*flag, edit_distance(name, flag)))
+        .filter(|(_, distance)| *distance <= threshold)
+        .min_by_key(|(flag, distance)| (*distance, *flag))
+        .map(|(flag, _)| flag)
+}
+
+/// Parsed flags, by name. See [`flags_from_str`] for how values are represented.
+pub type Flags = HashMap<String, Option<String>>;
+
+/// Parses flags, also returning any values not attached to a flag (positionals), in order. Unlike
+/// [`flags_from_str`], a value directly after a [`FlagType::NoValue`] flag is taken as a
+/// positional.
+pub fn flags_and_positionals_from_str(
+    input: &str,
+    valid_flags: HashMap<&'static str, impl Into<Flag>>,
+) -> anyhow::Result<(Flags, Vec<String>)> {
+    parse_flags(input, valid_flags, true)
 }
 
-pub fn flags_from_str(input: &str, valid_flags: ValidFlags) -> anyhow::Result<HashMap<String, Option<String>>> {
-    let args = input.split_ascii_whitespace();
-    let mut current_flag: Option<String> = None;
+fn parse_flags(
+    input: &str,
+    valid_flags: HashMap<&'static str, impl Into<Flag>>,
+    allow_positionals: bool,
+) -> anyhow::Result<(Flags, Vec<String>)> {
+    let valid_flags: ValidFlags = valid_flags
+        .into_iter()
+        .map(|(name, flag)| (name, flag.into()))
+        .collect();
+
+    let asked_for_help = input
+        .split_ascii_whitespace()
+        .filter_map(|arg| arg.strip_prefix("--"))
+        .any(|name| HELP_FLAGS.contains(&name) && !valid_flags.contains_key(name));
+    if asked_for_help {
+        return Err(FlagsHelp(describe_flags(&valid_flags)).into());
+    }
+
+    // positions are 1-indexed word numbers, for pointing users at the problem in error messages
+    let args = input.split_ascii_whitespace().enumerate().map(|(i, arg)| (i + 1, arg));
+    let mut current_flag: Option<(String, usize)> = None;
     let mut entries: HashMap<String, Option<String>> = HashMap::new();
+    let mut positionals: Vec<String> = Vec::new();
 
-    for arg in args {
-        if (arg.starts_with("--") && arg.len() > 2) || (arg.starts_with("—") && arg.len() > 1) {
-            let arglen = if arg.starts_with("--") { 2 } else { 1 };
+    let get_flag = |name: &str, position: usize| {
+        valid_flags
+            .get(name)
+            .with_context(|| match closest_flag(name, &valid_flags) {
+                Some(closest) => {
+                    format!("Unrecognised flag `{name}` at position {position} (did you mean `--{closest}`?)")
+                },
+                None => format!("Unrecognised flag `{name}` at position {position}"),
+            })
+    };
 
+    for (position, arg) in args {
+        if let Some(name) = flag_name(arg, &valid_flags) {
             // prev flag present but no value, write to hashmap
-            if let Some(ref c) = current_flag {
-                let flag = valid_flags
-                    .get(&c.as_ref())
-                    .context(format!("Unrecognised flag: {c}"))?;
+            if let Some((ref c, c_position)) = current_flag {
+                let flag = get_flag(c, c_position)?;
 
-                if let FlagType::NoValue = flag {
+                if let FlagType::NoValue = flag.kind {
                     entries.insert(c.clone(), None);
-                    current_flag = Some(arg.chars().skip(arglen).collect::<String>());
+                    current_flag = None;
                 } else {
-                    bail!("Flag {c} expects a value, but none was provided");
+                    bail!("Flag `{c}` at position {c_position} expects a value, but none was provided");
+                }
+            }
+
+            // value given inline, as in --name=value
+            if let Some((name, value)) = name.split_once('=') {
+                let flag = get_flag(name, position)?;
+
+                if let FlagType::NoValue = flag.kind {
+                    let Some(value) = parse_bool_value(value) else {
+                        bail!("Flag `{name}` at position {position} can only be set to true or false");
+                    };
+
+                    entries.insert(name.to_owned(), Some(value.to_string()));
+                    continue;
+                } else if value.is_empty() {
+                    bail!("Flag `{name}` at position {position} expects a value, but none was provided");
                 }
+
+                entries.insert(name.to_owned(), Some(value.to_owned()));
             } else {
-                current_flag = Some(arg.chars().skip(arglen).collect::<String>());
+                current_flag = Some((name, position));
             }
         } else {
             // current flag present, this arg is its value
-            if let Some(ref c) = current_flag {
-                let flag = valid_flags
-                    .get(&c.as_ref())
-                    .context(format!("Unrecognised flag: {c}"))?;
+            if let Some((ref c, c_position)) = current_flag {
+                let flag = get_flag(c, c_position)?;
 
-                if let FlagType::WithValue = flag {
+                if let FlagType::WithValue = flag.kind {
                     entries.insert(c.clone(), Some(arg.to_owned()));
-                    current_flag = None;
+                } else if let Some(value) = parse_bool_value(arg) {
+                    // explicitly enabled or disabled, as in --release false
+                    entries.insert(c.clone(), Some(value.to_string()));
+                } else if allow_positionals {
+                    // flag doesn't take a value, so this is a positional after it
+                    entries.insert(c.clone(), None);
+                    positionals.push(arg.to_owned());
                 } else {
-                    bail!("Flag {c} does not expect a value, even though one was provided");
+                    bail!("Flag `{c}` at position {c_position} doesn't take a value, but `{arg}` was provided");
                 }
+                current_flag = None;
+            } else {
+                positionals.push(arg.to_owned());
             }
         }
     }
 
     // handle case where we assign current flag in last arg, and return
-    if let Some(c) = current_flag {
-        let flag = valid_flags
-            .get(&c.as_ref())
-            .context(format!("Unrecognised flag: {c}"))?;
-        if let FlagType::WithValue = flag {
-            bail!("Flag {c} expects a value, but none was provided");
+    if let Some((c, c_position)) = current_flag {
+        let flag = get_flag(&c, c_position)?;
+        if let FlagType::WithValue = flag.kind {
+            bail!("Flag `{c}` at position {c_position} expects a value, but none was provided");
         } else {
-            entries.insert(c.clone(), None);
+            entries.insert(c, None);
+        }
+    }
+
+    for (name, flag) in &valid_flags {
+        if let FlagType::WithValue = flag.kind
+            && let Some(default) = flag.default
+        {
+            entries
+                .entry((*name).to_owned())
+                .or_insert_with(|| Some(default.to_owned()));
         }
     }
 
-    Ok(entries)
+    Ok((entries, positionals))
+}
+
+/// Whether a [`FlagType::NoValue`] flag is enabled: either given bare (`--release`) or explicitly
+/// set to true (`--release=true`). Flags that weren't provided, or were set to false, are disabled.
+pub fn flag_enabled(flags: &HashMap<String, Option<String>>, name: &str) -> bool {
+    match flags.get(name) {
+        Some(None) => true,
+        Some(Some(value)) => parse_bool_value(value).unwrap_or(false),
+        None => false,
+    }
+}
+
+/// Parses the value of a flag with `parse`, if it was provided (or has a default). The error names
+/// the flag and the value it was given.
+pub fn parse_flag_with<T, E>(
+    flags: &HashMap<String, Option<String>>,
+    name: &str,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> anyhow::Result<Option<T>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let Some(Some(value)) = flags.get(name) else {
+        return Ok(None);
+    };
+
+    parse(value)
+        .map(Some)
+        .with_context(|| format!("Invalid value {value} for flag {name}"))
+}
+
+/// Parses the value of a flag with its [`FromStr`] implementation. See [`parse_flag_with`].
+pub fn parse_flag<T>(flags: &HashMap<String, Option<String>>, name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    parse_flag_with(flags, name, str::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download_flags() -> ValidFlags {
+        HashMap::from([
+            ("audio", FlagType::NoValue.into()),
+            ("quality", FlagType::WithValue.with_short('q')),
+        ])
+    }
+
+    #[test]
+    fn value_after_a_value_less_flag_is_an_error() {
+        let error = flags_from_str("--audio 720", download_flags()).unwrap_err();
+
+        assert!(error.to_string().contains("doesn't take a value"), "{error}");
+    }
+
+    #[test]
+    fn value_after_a_value_less_flag_is_a_positional_when_allowed() {
+        let (flags, positionals) =
+            flags_and_positionals_from_str("--audio https://example.com -q 720", download_flags()).unwrap();
+
+        assert!(flag_enabled(&flags, "audio"));
+        assert_eq!(flags.get("quality"), Some(&Some("720".to_owned())));
+        assert_eq!(positionals, ["https://example.com"]);
+    }
+
+    #[test]
+    fn inline_and_separate_values_can_be_mixed() {
+        let flags = flags_from_str("--quality=720 --audio=false -q 1080", download_flags()).unwrap();
+        assert_eq!(flags.get("quality"), Some(&Some("1080".to_owned())));
+        assert!(!flag_enabled(&flags, "audio"));
+
+        let flags = flags_from_str("--audio --quality=best=ever", download_flags()).unwrap();
+        assert!(flag_enabled(&flags, "audio"));
+        assert_eq!(flags.get("quality"), Some(&Some("best=ever".to_owned())));
+    }
+
+    #[test]
+    fn inline_value_must_not_be_empty() {
+        let error = flags_from_str("--quality= 720", download_flags()).unwrap_err();
+
+        assert!(error.to_string().contains("expects a value"), "{error}");
+    }
+
+    #[test]
+    fn defaults_fill_in_missing_flags() {
+        let valid = || {
+            HashMap::from([
+                ("quality", FlagType::WithValue.with_default("720")),
+                ("start", FlagType::WithValue.into()),
+            ])
+        };
+
+        let flags = flags_from_str("", valid()).unwrap();
+        assert_eq!(parse_flag::<u32>(&flags, "quality").unwrap(), Some(720));
+        assert_eq!(parse_flag::<u32>(&flags, "start").unwrap(), None);
+
+        let flags = flags_from_str("--quality 1080", valid()).unwrap();
+        assert_eq!(parse_flag::<u32>(&flags, "quality").unwrap(), Some(1080));
+    }
+
+    #[test]
+    fn typed_flag_errors_name_the_flag_and_value() {
+        let flags = flags_from_str("--quality best", download_flags()).unwrap();
+        let error = parse_flag::<u32>(&flags, "quality").unwrap_err();
+
+        assert_eq!(error.to_string(), "Invalid value best for flag quality");
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_word() {
+        let error = |input| flags_from_str(input, download_flags()).unwrap_err().to_string();
+
+        assert_eq!(
+            error("url --audio --quality"),
+            "Flag `quality` at position 3 expects a value, but none was provided"
+        );
+        assert_eq!(
+            error("--quality --audio"),
+            "Flag `quality` at position 1 expects a value, but none was provided"
+        );
+        assert_eq!(error("a b --nope"), "Unrecognised flag `nope` at position 3");
+        assert_eq!(
+            error("--audio=maybe"),
+            "Flag `audio` at position 1 can only be set to true or false"
+        );
+    }
+
+    #[test]
+    fn close_typos_suggest_the_intended_flag() {
+        let error = flags_from_str("--qualty 720", download_flags()).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Unrecognised flag `qualty` at position 1 (did you mean `--quality`?)"
+        );
+    }
+
+    #[test]
+    fn distant_flags_suggest_nothing() {
+        for input in ["--resolution 720", "--x"] {
+            let error = flags_from_str(input, download_flags()).unwrap_err();
+
+            assert!(!error.to_string().contains("did you mean"), "{error}");
+        }
+    }
+
+    #[test]
+    fn help_lists_every_flag() {
+        let valid = || {
+            HashMap::from([
+                ("audio", FlagType::NoValue.with_description("download only the audio")),
+                (
+                    "quality",
+                    FlagType::WithValue
+                        .with_short('q')
+                        .with_default("720")
+                        .with_description("video resolution"),
+                ),
+            ])
+        };
+
+        for input in ["--help", "https://example.com --flags"] {
+            let error = flags_from_str(input, valid()).unwrap_err();
+
+            let TagParseError::FlagsHelp(help) = flag_parse_error(error) else {
+                panic!("{input:?} didn't ask for the list of flags");
+            };
+            assert_eq!(
+                help,
+                "--audio: download only the audio\n--quality (-q) <value> [default: 720]: video resolution"
+            );
+        }
+    }
+
+    #[test]
+    fn a_commands_own_help_flag_takes_priority() {
+        let valid: ValidFlags = HashMap::from([
+            ("help", FlagType::NoValue.into()),
+            ("quality", FlagType::WithValue.into()),
+        ]);
+
+        let flags = flags_from_str("--help", valid).unwrap();
+        assert!(flag_enabled(&flags, "help"));
+
+        // other errors are still parse errors
+        let error = flags_from_str("--nope", download_flags()).unwrap_err();
+        assert!(matches!(flag_parse_error(error), TagParseError::FlagParseError(_)));
+    }
+
+    #[test]
+    fn value_less_flags_accept_explicit_booleans() {
+        let enabled = |input| flag_enabled(&flags_from_str(input, download_flags()).unwrap(), "audio");
+
+        assert!(enabled("--audio"));
+        assert!(enabled("--audio=true"));
+        assert!(enabled("--audio TRUE"));
+        assert!(!enabled("--audio=false"));
+        assert!(!enabled("--audio false"));
+        assert!(!enabled(""));
+    }
 }
@@ -11,10 +11,10 @@ use twilight_model::id::Id;
 use twilight_util::builder::command::BooleanBuilder;
 
 use crate::assyst::ThreadSafeAssyst;
-use crate::command::arguments::{ParseArgument, Word, WordAutocomplete};
+use crate::command::arguments::{Colour, ParseArgument, Word, WordAutocomplete};
 use crate::command::autocomplete::AutocompleteData;
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{flag_enabled, flag_parse_error, flags_from_str, FlagDecode, FlagType, ValidFlags};
 use crate::command::{Availability, Category, CommandCtxt};
 use crate::{define_commandgroup, int_arg_bool};
 
@@ -65,7 +65,7 @@ pub async fn colour_role_autocomplete(assyst: ThreadSafeAssyst, autocomplete_dat
     usage = "[name] [colour code]",
     examples = ["red #ff0000"],
 )]
-pub async fn add(ctxt: CommandCtxt<'_>, name: Word, code: Word) -> anyhow::Result<()> {
+pub async fn add(ctxt: CommandCtxt<'_>, name: Word, code: Colour) -> anyhow::Result<()> {
     if let Some(id) = ctxt.data.guild_id.map(twilight_model::id::Id::get) {
         if name.0.contains(' ') {
             bail!("Colour role names cannot contain spaces");
@@ -90,15 +90,12 @@ pub async fn add(ctxt: CommandCtxt<'_>, name: Word, code: Word) -> anyhow::Resul
             bail!("A colour role with the name `{}` already exists in this server.", name);
         }
 
-        let colour =
-            u32::from_str_radix(code.0.strip_prefix("#").unwrap_or(&code.0), 16).context("Invalid colour code")?;
-
         let role = ctxt
             .assyst()
             .http_client
             .create_role(Id::<GuildMarker>::new(id))
             .name(&name)
-            .color(colour)
+            .color(code.0)
             .await
             .context("Failed to create colour role")?
             .model()
@@ -422,16 +419,23 @@ pub struct ColourRemoveAllFlags {
     pub i_am_sure: bool,
 }
 impl FlagDecode for ColourRemoveAllFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert(
+            "i-am-sure",
+            FlagType::NoValue.with_description("Confirm this operation"),
+        );
+
+        valid_flags
+    }
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("i-am-sure", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
         let result = Self {
-            i_am_sure: raw_decode.contains_key("i-am-sure"),
+            i_am_sure: flag_enabled(&raw_decode, "i-am-sure"),
         };
 
         Ok(result)
@@ -451,7 +455,7 @@ impl ParseArgument for ColourRemoveAllFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -9,7 +9,7 @@ use twilight_util::builder::command::{BooleanBuilder, IntegerBuilder};
 
 use crate::command::arguments::{ParseArgument, Rest, Word};
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{flag_enabled, flag_parse_error, flags_from_str, FlagDecode, FlagType, ValidFlags};
 use crate::command::{Availability, Category, CommandCtxt};
 use crate::rest::bad_translation::{
     bad_translate as bad_translate_default, bad_translate_with_count, get_languages, translate_single, TranslateResult,
@@ -23,15 +23,22 @@ pub struct BadTranslateFlags {
     pub count: Option<u64>,
 }
 impl FlagDecode for BadTranslateFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert("chain", FlagType::NoValue.with_description("Show language chain"));
+        valid_flags.insert(
+            "count",
+            FlagType::WithValue.with_description("Set the amount of translations to perform"),
+        );
+
+        valid_flags
+    }
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("chain", FlagType::NoValue);
-        valid_flags.insert("count", FlagType::WithValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
 
         let count = raw_decode
             .get("count")
@@ -44,7 +51,7 @@ impl FlagDecode for BadTranslateFlags {
         };
 
         let result = Self {
-            chain: raw_decode.contains_key("chain"),
+            chain: flag_enabled(&raw_decode, "chain"),
             count,
         };
 
@@ -70,7 +77,7 @@ impl ParseArgument for BadTranslateFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -81,7 +81,8 @@ macro_rules! define_commandgroup {
                         guild_only: $crate::defaults!(guild_only $($guild_only)?),
                         flag_descriptions: std::collections::HashMap::new(),
                         context_menu_message_command: $crate::defaults!(context_menu_message_command),
-                        context_menu_user_command: $crate::defaults!(context_menu_user_command)
+                        context_menu_user_command: $crate::defaults!(context_menu_user_command),
+                        image_resolve: crate::command::arguments::ImageResolveConfig::ALL
                     })
                 }
 
@@ -241,11 +242,21 @@ pub async fn execute_subcommand_raw_message(
         .next_word(None)
         .map_err(|_| ExecutionError::Parse(TagParseError::SubcommandArgsExhausted("unknown".to_owned())))?;
 
-    let command = find_subcommand(subcommand, commands).ok_or(ExecutionError::Parse(
-        TagParseError::InvalidSubcommand(subcommand.to_owned()),
+    let command = find_subcommand(&subcommand, commands).ok_or(ExecutionError::Parse(
+        TagParseError::InvalidSubcommand(subcommand.to_string()),
     ))?;
 
-    command.execute_raw_message(ctxt).await
+    command.execute_raw_message(ctxt).await.map_err(|err| match err {
+        ExecutionError::Argument(mut err) => {
+            // nested groups prepend their subcommand as the error propagates outwards
+            err.subcommand = Some(match err.subcommand {
+                Some(inner) => format!("{subcommand} {inner}"),
+                None => subcommand.to_string(),
+            });
+            ExecutionError::Argument(err)
+        },
+        err => err,
+    })
 }
 
 pub fn find_subcommand_interaction_command(sub: &str, cmds: &[(&str, TCommand)]) -> Option<TCommand> {
@@ -7,7 +7,7 @@ use twilight_util::builder::command::IntegerBuilder;
 
 use crate::command::arguments::{Image, ParseArgument};
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{flag_parse_error, flags_from_str, FlagDecode, FlagType, ValidFlags};
 use crate::command::{Availability, Category, CommandCtxt};
 use crate::int_arg_u64_opt;
 
@@ -18,13 +18,26 @@ pub struct BloomFlags {
     pub sharpness: Option<u64>,
 }
 impl FlagDecode for BloomFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self> {
+    fn valid_flags() -> ValidFlags {
         let mut valid_flags = HashMap::new();
-        valid_flags.insert("radius", FlagType::WithValue);
-        valid_flags.insert("sharpness", FlagType::WithValue);
-        valid_flags.insert("brightness", FlagType::WithValue);
+        valid_flags.insert(
+            "radius",
+            FlagType::WithValue.with_description("Bloom radius as a number"),
+        );
+        valid_flags.insert(
+            "sharpness",
+            FlagType::WithValue.with_description("Bloom sharpness as a number"),
+        );
+        valid_flags.insert(
+            "brightness",
+            FlagType::WithValue.with_description("Bloom brightness as a number"),
+        );
+
+        valid_flags
+    }
 
-        let raw_decode = flags_from_str(input, valid_flags)?;
+    fn from_str(input: &str) -> anyhow::Result<Self> {
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
         let result = Self {
             radius: raw_decode
                 .get("radius")
@@ -67,7 +80,7 @@ impl ParseArgument for BloomFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
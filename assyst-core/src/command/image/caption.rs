@@ -6,7 +6,7 @@ use twilight_util::builder::command::BooleanBuilder;
 
 use crate::command::arguments::{Image, ParseArgument, Rest};
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{flag_enabled, flag_parse_error, flags_from_str, FlagDecode, FlagType, ValidFlags};
 use crate::command::{Availability, Category, CommandCtxt};
 use crate::int_arg_bool;
 
@@ -47,19 +47,26 @@ pub struct CaptionFlags {
     pub black: bool,
 }
 impl FlagDecode for CaptionFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert(
+            "bottom",
+            FlagType::NoValue.with_description("Put the caption on the bottom of the image"),
+        );
+        valid_flags.insert("black", FlagType::NoValue.with_description("Invert the caption"));
+
+        valid_flags
+    }
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("bottom", FlagType::NoValue);
-        valid_flags.insert("black", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
 
         let result = Self {
-            bottom: raw_decode.contains_key("bottom"),
-            black: raw_decode.contains_key("black"),
+            bottom: flag_enabled(&raw_decode, "bottom"),
+            black: flag_enabled(&raw_decode, "black"),
         };
 
         Ok(result)
@@ -82,7 +89,7 @@ impl ParseArgument for CaptionFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use twilight_model::application::interaction::application_command::CommandOptionValue;
+use twilight_util::builder::command::{IntegerBuilder, NumberBuilder, StringBuilder};
+
+use crate::command::arguments::ParseArgument;
+use crate::command::errors::TagParseError;
+use crate::command::flags::{flag_parse_error, flags_from_str, parse_flag, FlagDecode, FlagType, ValidFlags};
+use crate::int_arg_u64_opt;
+
+/// Output formats that can be requested with `--format`.
+pub const IMAGE_FORMATS: &[&str] = &["png", "gif", "webp"];
+/// The smallest factor that an image can be scaled by with `--scale`.
+pub const MIN_SCALE: f64 = 0.1;
+/// The largest factor that an image can be scaled by with `--scale`.
+pub const MAX_SCALE: f64 = 10.0;
+
+/// Flags shared across image commands, controlling the output rather than the effect itself.
+#[derive(Debug, Default)]
+pub struct ImageFlags {
+    /// The output format, one of [`IMAGE_FORMATS`].
+    pub format: Option<String>,
+    /// Factor to scale the output by, between [`MIN_SCALE`] and [`MAX_SCALE`].
+    pub scale: Option<f64>,
+    /// Maximum number of frames to process.
+    pub frames: Option<u64>,
+}
+impl ImageFlags {
+    fn validate_format(format: String) -> anyhow::Result<String> {
+        let format = format.to_ascii_lowercase();
+
+        if !IMAGE_FORMATS.contains(&format.as_str()) {
+            bail!(
+                "Unsupported format {format} (expected one of {})",
+                IMAGE_FORMATS.join(", ")
+            );
+        }
+
+        Ok(format)
+    }
+
+    fn validate_scale(scale: f64) -> anyhow::Result<f64> {
+        if !(MIN_SCALE..=MAX_SCALE).contains(&scale) {
+            bail!("Scale must be between {MIN_SCALE} and {MAX_SCALE}, but {scale} was provided");
+        }
+
+        Ok(scale)
+    }
+}
+impl FlagDecode for ImageFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert("format", FlagType::WithValue.with_description("Set the output format"));
+        valid_flags.insert(
+            "scale",
+            FlagType::WithValue.with_description("Scale the output by this factor"),
+        );
+        valid_flags.insert(
+            "frames",
+            FlagType::WithValue.with_description("Set the maximum number of frames to process"),
+        );
+
+        valid_flags
+    }
+
+    fn from_str(input: &str) -> anyhow::Result<Self> {
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
+        let result = Self {
+            format: parse_flag(&raw_decode, "format")?
+                .map(Self::validate_format)
+                .transpose()?,
+            scale: parse_flag(&raw_decode, "scale")?
+                .map(Self::validate_scale)
+                .transpose()?,
+            frames: parse_flag(&raw_decode, "frames")?,
+        };
+
+        Ok(result)
+    }
+}
+impl ParseArgument for ImageFlags {
+    fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
+        vec![
+            StringBuilder::new("format", "output image format")
+                .required(false)
+                .choices(IMAGE_FORMATS.iter().map(|f| (*f, *f)))
+                .build(),
+            NumberBuilder::new("scale", "factor to scale the output by")
+                .required(false)
+                .min_value(MIN_SCALE)
+                .max_value(MAX_SCALE)
+                .build(),
+            IntegerBuilder::new("frames", "maximum number of frames to process")
+                .required(false)
+                .min_value(1)
+                .build(),
+        ]
+    }
+
+    async fn parse_raw_message(
+        ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
+        label: crate::command::Label,
+    ) -> Result<Self, crate::command::errors::TagParseError> {
+        let args = ctxt.rest_all(label);
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
+        Ok(parsed)
+    }
+
+    async fn parse_command_option(
+        ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
+        _: crate::command::Label,
+    ) -> Result<Self, TagParseError> {
+        let format = match ctxt.option_by_name("format").map(|o| o.value.clone()) {
+            Ok(CommandOptionValue::String(format)) => {
+                Some(Self::validate_format(format).map_err(TagParseError::FlagParseError)?)
+            },
+            _ => None,
+        };
+        let scale = match ctxt.option_by_name("scale").map(|o| o.value.clone()) {
+            Ok(CommandOptionValue::Number(scale)) => {
+                Some(Self::validate_scale(scale).map_err(TagParseError::FlagParseError)?)
+            },
+            _ => None,
+        };
+        let frames = int_arg_u64_opt!(ctxt, "frames");
+
+        Ok(Self { format, scale, frames })
+    }
+}
@@ -14,6 +14,7 @@ use crate::command::{Availability, Category, CommandCtxt};
 pub mod audio;
 pub mod bloom;
 pub mod caption;
+pub mod flags;
 pub mod makesweet;
 pub mod randomize;
 pub mod speechbubble;
@@ -180,13 +181,12 @@ pub async fn frames(ctxt: CommandCtxt<'_>, source: Image) -> anyhow::Result<()>
         .await?;
 
     let response: MessageBuilder = MessageBuilder {
-        content: None,
-        attachment: Some(Attachment {
+        attachments: vec![Attachment {
             name: "frames.zip".to_owned().into_boxed_str(),
             data: result,
-        }),
-        component_ctxt: None,
-        components: None,
+            output: None,
+        }],
+        ..Default::default()
     };
 
     ctxt.reply(response).await?;
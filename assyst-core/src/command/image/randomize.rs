@@ -4,9 +4,9 @@ use std::time::Duration;
 use anyhow::Context;
 use assyst_flux_iface::flux_request::FluxRequest;
 use assyst_proc_macro::command;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-use crate::command::arguments::Image;
+use crate::command::arguments::{Image, Seed};
 use crate::command::{Availability, Category, CommandCtxt};
 
 const VALID_EFFECTS: &[&str] = &[
@@ -27,22 +27,13 @@ const VALID_EFFECTS: &[&str] = &[
     "rainbow",
 ];
 
-#[command(
-    description = "apply random effects to an image",
-    aliases = ["random", "randomise", "badcmd"],
-    cooldown = Duration::from_secs(2),
-    access = Availability::Public,
-    category = Category::Image,
-    usage = "[image] <effect count: 1-5>",
-    examples = ["https://link.to.my/image.png 3"],
-    send_processing = true
-)]
-pub async fn randomize(ctxt: CommandCtxt<'_>, source: Image, count: Option<u64>) -> anyhow::Result<()> {
+/// Picks `count` random effects, never applying the same effect twice in a row.
+fn pick_effects(rng: &mut impl Rng, count: u64) -> Vec<&'static str> {
     let mut effects: Vec<&str> = Vec::new();
 
-    for _ in 0..count.unwrap_or(3).clamp(1, 5) {
+    for _ in 0..count {
         let next = loop {
-            let tmp = VALID_EFFECTS[thread_rng().gen_range(0..VALID_EFFECTS.len())];
+            let tmp = VALID_EFFECTS[rng.gen_range(0..VALID_EFFECTS.len())];
             if effects.last() != Some(&tmp) {
                 break tmp;
             }
@@ -51,6 +42,28 @@ pub async fn randomize(ctxt: CommandCtxt<'_>, source: Image, count: Option<u64>)
         effects.push(next);
     }
 
+    effects
+}
+
+#[command(
+    description = "apply random effects to an image",
+    aliases = ["random", "randomise", "badcmd"],
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Image,
+    usage = "[image] <effect count: 1-5> <seed>",
+    examples = ["https://link.to.my/image.png 3", "https://link.to.my/image.png 3 1234"],
+    send_processing = true
+)]
+pub async fn randomize(
+    ctxt: CommandCtxt<'_>,
+    source: Image,
+    count: Option<u64>,
+    seed: Option<Seed>,
+) -> anyhow::Result<()> {
+    let seed = seed.unwrap_or_else(Seed::random);
+    let effects = pick_effects(&mut seed.rng(), count.unwrap_or(3).clamp(1, 5));
+
     let limits = ctxt
         .assyst()
         .flux_handler
@@ -74,11 +87,38 @@ pub async fn randomize(ctxt: CommandCtxt<'_>, source: Image, count: Option<u64>)
     ctxt.reply((
         result,
         &format!(
-            "Applied effects: {}",
-            effects.iter().map(|e| format!("`{e}`")).collect::<Vec<_>>().join(", ")
+            "Applied effects: {} (seed: {})",
+            effects.iter().map(|e| format!("`{e}`")).collect::<Vec<_>>().join(", "),
+            seed.0
         )[..],
     ))
     .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_picks_the_same_effects() {
+        for seed in [0, 1, 1234, (1 << 53) - 1] {
+            let first = pick_effects(&mut Seed(seed).rng(), 5);
+            let second = pick_effects(&mut Seed(seed).rng(), 5);
+            assert_eq!(first, second, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn effects_are_never_repeated_back_to_back() {
+        for seed in 0..100 {
+            let effects = pick_effects(&mut Seed(seed).rng(), 5);
+            assert_eq!(effects.len(), 5);
+            assert!(
+                effects.windows(2).all(|pair| pair[0] != pair[1]),
+                "seed {seed}: {effects:?}"
+            );
+        }
+    }
+}
@@ -6,7 +6,7 @@ use twilight_util::builder::command::BooleanBuilder;
 
 use crate::command::arguments::{Image, ParseArgument};
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{flag_enabled, flag_parse_error, flags_from_str, FlagDecode, FlagType, ValidFlags};
 use crate::command::{Availability, Category, CommandCtxt};
 use crate::int_arg_bool;
 
@@ -44,17 +44,24 @@ pub struct SpeechBubbleFlags {
     pub solid: bool,
 }
 impl FlagDecode for SpeechBubbleFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert(
+            "solid",
+            FlagType::NoValue.with_description("Make the speech bubble solid white instead of transparent"),
+        );
+
+        valid_flags
+    }
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("solid", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
 
         let result = Self {
-            solid: raw_decode.contains_key("solid"),
+            solid: flag_enabled(&raw_decode, "solid"),
         };
 
         Ok(result)
@@ -74,7 +81,7 @@ impl ParseArgument for SpeechBubbleFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -1,13 +1,24 @@
 use assyst_common::util::filetype::{get_sig, Type};
-use twilight_model::channel::message::Component;
+use twilight_model::channel::message::{AllowedMentions, Component};
 
 use super::arguments::Image;
 use super::componentctxt::ComponentCtxtRegister;
+use super::image::flags::ImageFlags;
 
 #[derive(Debug)]
 pub struct Attachment {
     pub name: Box<str>,
     pub data: Vec<u8>,
+    /// How to rescale or re-encode this attachment before it is sent, if at all. Applied by the
+    /// [`ImageOutputTransform`](super::middleware::ImageOutputTransform) middleware.
+    pub output: Option<ImageFlags>,
+}
+impl Attachment {
+    /// Rescales or re-encodes this attachment according to `output` before it is sent.
+    pub fn with_output(mut self, output: ImageFlags) -> Self {
+        self.output = Some(output);
+        self
+    }
 }
 
 impl From<Image> for Attachment {
@@ -16,24 +27,60 @@ impl From<Image> for Attachment {
         Attachment {
             name: format!("attachment.{ext}").into(),
             data: value.0,
+            output: None,
         }
     }
 }
 
+/// The maximum length of a message's content, in characters.
+pub const MAX_CONTENT_LENGTH: usize = 2000;
+
+#[derive(Default)]
 pub struct MessageBuilder {
     pub content: Option<String>,
-    pub attachment: Option<Attachment>,
+    pub attachments: Vec<Attachment>,
     pub components: Option<Vec<Component>>,
     pub component_ctxt: Option<ComponentCtxtRegister>,
+    /// If set, content too long to send is cut off, instead of being sent as a text file.
+    pub truncate_overflow: bool,
+    /// If set, only the invoking user can see the reply. Only supported for interaction commands;
+    /// raw message commands reply publicly as usual.
+    pub ephemeral: bool,
+    /// Who the reply is allowed to ping. If unset, nobody is pinged, so this only needs to be set
+    /// by commands that are meant to notify someone.
+    pub allowed_mentions: Option<AllowedMentions>,
+}
+
+impl MessageBuilder {
+    /// Sets the only attachment to send, replacing any others.
+    pub fn set_attachment(&mut self, attachment: Attachment) {
+        self.attachments = vec![attachment];
+    }
+
+    /// How many more characters the content can hold before reaching [`MAX_CONTENT_LENGTH`].
+    pub fn remaining_content_budget(&self) -> usize {
+        let used = self.content.as_deref().map_or(0, |c| c.chars().count());
+        MAX_CONTENT_LENGTH.saturating_sub(used)
+    }
+
+    /// Appends text to the content, but only if it fits within [`MAX_CONTENT_LENGTH`]. Returns
+    /// whether it was appended, so that the caller can decide what to do with output that doesn't
+    /// fit, such as sending it as a file instead.
+    pub fn try_push_str(&mut self, text: &str) -> bool {
+        if text.chars().count() > self.remaining_content_budget() {
+            return false;
+        }
+
+        self.content.get_or_insert_with(String::new).push_str(text);
+        true
+    }
 }
 
 impl From<&str> for MessageBuilder {
     fn from(value: &str) -> Self {
         Self {
             content: Some(value.into()),
-            attachment: None,
-            components: None,
-            component_ctxt: None,
+            ..Default::default()
         }
     }
 }
@@ -41,9 +88,7 @@ impl From<String> for MessageBuilder {
     fn from(value: String) -> Self {
         Self {
             content: Some(value),
-            attachment: None,
-            components: None,
-            component_ctxt: None,
+            ..Default::default()
         }
     }
 }
@@ -51,10 +96,8 @@ impl From<String> for MessageBuilder {
 impl From<Attachment> for MessageBuilder {
     fn from(value: Attachment) -> Self {
         Self {
-            content: None,
-            attachment: Some(value),
-            components: None,
-            component_ctxt: None,
+            attachments: vec![value],
+            ..Default::default()
         }
     }
 }
@@ -63,9 +106,8 @@ impl From<(Attachment, String)> for MessageBuilder {
     fn from(value: (Attachment, String)) -> Self {
         Self {
             content: Some(value.1),
-            attachment: Some(value.0),
-            components: None,
-            component_ctxt: None,
+            attachments: vec![value.0],
+            ..Default::default()
         }
     }
 }
@@ -73,40 +115,34 @@ impl From<(Attachment, String)> for MessageBuilder {
 impl From<Image> for MessageBuilder {
     fn from(value: Image) -> Self {
         Self {
-            content: None,
-            attachment: Some(value.into()),
-            components: None,
-            component_ctxt: None,
+            attachments: vec![value.into()],
+            ..Default::default()
         }
     }
 }
 impl From<(Image, &str)> for MessageBuilder {
     fn from((image, text): (Image, &str)) -> Self {
         Self {
-            attachment: Some(image.into()),
+            attachments: vec![image.into()],
             content: Some(text.into()),
-            components: None,
-            component_ctxt: None,
+            ..Default::default()
         }
     }
 }
 impl From<Vec<u8>> for MessageBuilder {
     fn from(value: Vec<u8>) -> Self {
         Self {
-            attachment: Some(Image(value).into()),
-            content: None,
-            components: None,
-            component_ctxt: None,
+            attachments: vec![Image(value, None).into()],
+            ..Default::default()
         }
     }
 }
 impl From<(Vec<u8>, &str)> for MessageBuilder {
     fn from((value, text): (Vec<u8>, &str)) -> Self {
         Self {
-            attachment: Some(Image(value).into()),
+            attachments: vec![Image(value, None).into()],
             content: Some(text.into()),
-            components: None,
-            component_ctxt: None,
+            ..Default::default()
         }
     }
 }
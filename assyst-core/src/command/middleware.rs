@@ -0,0 +1,373 @@
+//! Output middleware for command replies.
+//!
+//! Middleware is registered globally on [`crate::assyst::Assyst`] and is applied, in registration
+//! order, to every [`MessageBuilder`] passed to [`CommandCtxt::reply`] before it is sent. This
+//! keeps reply-path concerns (upload fallbacks, footers, spoilering, etc.) in one place instead of
+//! scattered across individual commands.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::bail;
+use assyst_common::config::CONFIG;
+use assyst_common::util::filetype::{get_sig, Type};
+use async_trait::async_trait;
+
+use super::messagebuilder::{Attachment, MessageBuilder};
+use super::source::Source;
+use super::CommandCtxt;
+use crate::gateway_handler::reply::paginate_content_fully;
+use crate::rest::filer::upload_to_filer;
+use crate::rest::NORMAL_DISCORD_UPLOAD_LIMIT_BYTES;
+
+// Used as a trait object, so we still need #[async_trait] here (see `Command`)
+/// Generic over the context it is given so that it can be tested without one; registered
+/// middleware always gets the [`CommandCtxt`] of the reply (see [`TOutputMiddleware`]).
+#[async_trait]
+pub trait OutputMiddleware<C: Sync> {
+    /// Inspects, and optionally modifies, an outgoing reply before it is sent.
+    async fn process(&self, ctxt: &C, builder: &mut MessageBuilder) -> anyhow::Result<()>;
+}
+
+/// An output middleware for command replies as a trait object, with other necessary bounds.
+pub type DynOutputMiddleware = dyn for<'a> OutputMiddleware<CommandCtxt<'a>> + Send + Sync;
+
+/// Just a type alias for a shared [`DynOutputMiddleware`].
+pub type TOutputMiddleware = Arc<DynOutputMiddleware>;
+
+/// Registered output middleware, applied in registration order.
+pub struct OutputMiddlewares<M: ?Sized>(Mutex<Vec<Arc<M>>>);
+impl<M: ?Sized> Default for OutputMiddlewares<M> {
+    fn default() -> Self {
+        Self(Mutex::new(Vec::new()))
+    }
+}
+impl<M: ?Sized> OutputMiddlewares<M> {
+    pub fn register(&self, middleware: Arc<M>) {
+        self.0.lock().unwrap().push(middleware);
+    }
+
+    /// Runs a reply through every registered middleware, in registration order, stopping at the
+    /// first error. The list is cloned first so that the lock isn't held while processing.
+    pub async fn apply<C: Sync>(&self, ctxt: &C, builder: &mut MessageBuilder) -> anyhow::Result<()>
+    where
+        M: OutputMiddleware<C>,
+    {
+        let middlewares = self.0.lock().unwrap().clone();
+        for middleware in middlewares {
+            middleware.process(ctxt, builder).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends content too long to fit in a reply as a text file instead, so that it isn't cut off. Must
+/// be registered before [`FilerUploadFallback`], so that a text file that is too large to upload
+/// is handled too.
+pub struct TextAttachmentOverflow;
+
+#[async_trait]
+impl<'a> OutputMiddleware<CommandCtxt<'a>> for TextAttachmentOverflow {
+    async fn process(&self, ctxt: &CommandCtxt<'a>, builder: &mut MessageBuilder) -> anyhow::Result<()> {
+        // raw message replies roll over into further messages, interaction replies don't
+        let max_pages = match ctxt.data.source {
+            Source::RawMessage => CONFIG.max_reply_pages.max(1),
+            Source::Interaction => 1,
+        };
+
+        self.apply(builder, max_pages);
+        Ok(())
+    }
+}
+
+impl TextAttachmentOverflow {
+    /// Moves the content into a text file if it doesn't fit in `max_pages` messages.
+    fn apply(&self, builder: &mut MessageBuilder, max_pages: usize) {
+        if builder.truncate_overflow || !builder.attachments.is_empty() {
+            return;
+        }
+
+        let Some(ref content) = builder.content else {
+            return;
+        };
+
+        if !overflows(content, max_pages) {
+            return;
+        }
+
+        // checked above
+        let content = builder.content.take().unwrap();
+
+        // a reply that is entirely a codeblock doesn't need its fences in a text file
+        let text = match content.strip_prefix("```").and_then(|c| c.strip_suffix("```")) {
+            Some(inner) if let Some((_, code)) = inner.split_once('\n') => code.to_owned(),
+            _ => content,
+        };
+
+        builder.set_attachment(Attachment {
+            name: "output.txt".into(),
+            data: text.into_bytes(),
+            output: None,
+        });
+        builder.content = Some("The output was too long to send, so it has been attached as a file.".to_owned());
+    }
+}
+
+/// Whether content would be cut off if sent in at most `max_pages` messages. This is worked out by
+/// actually paginating it, since codeblocks reopened on later pages take up some of their space.
+fn overflows(content: &str, max_pages: usize) -> bool {
+    paginate_content_fully(content, max_pages).is_none()
+}
+
+/// Rescales and re-encodes any attachment that was given an output spec (see
+/// [`Attachment::with_output`]), e.g. from a user's `--scale` and `--format` flags. Attachments
+/// without one are left as they are. Must be registered before [`FilerUploadFallback`], so that
+/// the final output is what's checked against the upload limit.
+///
+/// Only scaling is supported; requesting a format other than the one the attachment is already in
+/// is an error. The frame limit only applies to inputs, so it is ignored here.
+pub struct ImageOutputTransform;
+
+#[async_trait]
+impl<'a> OutputMiddleware<CommandCtxt<'a>> for ImageOutputTransform {
+    async fn process(&self, ctxt: &CommandCtxt<'a>, builder: &mut MessageBuilder) -> anyhow::Result<()> {
+        for attachment in &mut builder.attachments {
+            let Some(output) = attachment.output.take() else {
+                continue;
+            };
+
+            if let Some(format) = output.format {
+                let current = get_sig(&attachment.data).map(|t| t.as_str());
+                if current != Some(format.as_str()) {
+                    bail!(
+                        "Converting the output to {format} isn't supported (it is {})",
+                        current.unwrap_or("in an unknown format")
+                    );
+                }
+            }
+
+            if let Some(scale) = output.scale
+                && scale != 1.0
+            {
+                attachment.data = ctxt
+                    .flux_handler()
+                    .resize_scale(
+                        std::mem::take(&mut attachment.data),
+                        scale as f32,
+                        ctxt.data.author.id.get(),
+                        ctxt.data.guild_id.map(twilight_model::id::Id::get),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Uploads attachments to Filer when together they exceed the guild's upload limit, largest first
+/// until the rest fit, replacing each with a link appended to the message content.
+pub struct FilerUploadFallback;
+
+#[async_trait]
+impl<'a> OutputMiddleware<CommandCtxt<'a>> for FilerUploadFallback {
+    async fn process(&self, ctxt: &CommandCtxt<'a>, builder: &mut MessageBuilder) -> anyhow::Result<()> {
+        if total_size(&builder.attachments) <= NORMAL_DISCORD_UPLOAD_LIMIT_BYTES as usize {
+            return Ok(());
+        }
+
+        let guild_upload_limit = if let Some(guild_id) = ctxt.data.guild_id {
+            ctxt.assyst()
+                .rest_cache_handler
+                .get_guild_upload_limit_bytes(guild_id.get())
+                .await?
+        } else {
+            NORMAL_DISCORD_UPLOAD_LIMIT_BYTES
+        };
+
+        for attachment in take_largest_until_within(&mut builder.attachments, guild_upload_limit as usize) {
+            let mime = get_sig(&attachment.data).unwrap_or(Type::PNG).as_mime();
+            let filer_url = upload_to_filer(&ctxt.assyst().reqwest_client, attachment.data, mime).await?;
+
+            builder.content = Some(match builder.content.take() {
+                Some(content) => format!("{content} {filer_url}"),
+                None => filer_url,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn total_size(attachments: &[Attachment]) -> usize {
+    attachments.iter().map(|a| a.data.len()).sum()
+}
+
+/// Removes attachments, largest first, until the total size of those left is at most `limit`
+/// bytes. Returns the removed attachments, and leaves the rest in their original order.
+fn take_largest_until_within(attachments: &mut Vec<Attachment>, limit: usize) -> Vec<Attachment> {
+    let mut total = total_size(attachments);
+    let mut taken = Vec::new();
+
+    while total > limit {
+        // can unwrap: the total can only exceed the limit while there are attachments left
+        let (largest, _) = attachments
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, a)| a.data.len())
+            .unwrap();
+
+        let attachment = attachments.remove(largest);
+        total -= attachment.data.len();
+        taken.push(attachment);
+    }
+
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends its text to the reply's content.
+    struct Append(&'static str);
+
+    #[async_trait]
+    impl OutputMiddleware<()> for Append {
+        async fn process(&self, _: &(), builder: &mut MessageBuilder) -> anyhow::Result<()> {
+            builder.content = Some(builder.content.take().unwrap_or_default() + self.0);
+            Ok(())
+        }
+    }
+
+    struct Fail;
+
+    #[async_trait]
+    impl OutputMiddleware<()> for Fail {
+        async fn process(&self, _: &(), _: &mut MessageBuilder) -> anyhow::Result<()> {
+            bail!("failed")
+        }
+    }
+
+    fn attachment(name: &str, size: usize) -> Attachment {
+        Attachment {
+            name: name.into(),
+            data: vec![0; size],
+            output: None,
+        }
+    }
+
+    fn names(attachments: &[Attachment]) -> Vec<&str> {
+        attachments.iter().map(|a| &*a.name).collect()
+    }
+
+    #[tokio::test]
+    async fn registered_middleware_is_applied_in_order() {
+        let middlewares = OutputMiddlewares::<dyn OutputMiddleware<()> + Send + Sync>::default();
+        middlewares.register(Arc::new(Append(" first")));
+        middlewares.register(Arc::new(Append(" second")));
+
+        let mut builder = MessageBuilder::from("reply");
+        middlewares.apply(&(), &mut builder).await.unwrap();
+
+        assert_eq!(builder.content.as_deref(), Some("reply first second"));
+    }
+
+    #[tokio::test]
+    async fn middleware_after_an_error_is_not_applied() {
+        let middlewares = OutputMiddlewares::<dyn OutputMiddleware<()> + Send + Sync>::default();
+        middlewares.register(Arc::new(Append(" first")));
+        middlewares.register(Arc::new(Fail));
+        middlewares.register(Arc::new(Append(" second")));
+
+        let mut builder = MessageBuilder::from("reply");
+        assert!(middlewares.apply(&(), &mut builder).await.is_err());
+
+        assert_eq!(builder.content.as_deref(), Some("reply first"));
+    }
+
+    #[test]
+    fn attachments_within_the_limit_are_kept() {
+        let mut attachments = vec![attachment("a", 40), attachment("b", 60)];
+
+        assert!(take_largest_until_within(&mut attachments, 100).is_empty());
+        assert_eq!(names(&attachments), ["a", "b"]);
+    }
+
+    #[test]
+    fn the_largest_attachments_are_taken_until_the_total_fits() {
+        // each fits on its own, but not all together
+        let mut attachments = vec![
+            attachment("a", 30),
+            attachment("b", 50),
+            attachment("c", 10),
+            attachment("d", 40),
+        ];
+
+        let taken = take_largest_until_within(&mut attachments, 60);
+
+        assert_eq!(names(&taken), ["b", "d"]);
+        assert_eq!(names(&attachments), ["a", "c"]);
+    }
+
+    #[test]
+    fn every_attachment_is_taken_when_none_fit() {
+        let mut attachments = vec![attachment("a", 150), attachment("b", 120)];
+
+        let taken = take_largest_until_within(&mut attachments, 100);
+
+        assert_eq!(names(&taken), ["a", "b"]);
+        assert!(attachments.is_empty());
+    }
+
+    #[test]
+    fn content_that_fits_in_the_pages_does_not_overflow() {
+        assert!(!overflows("short", 1));
+        assert!(!overflows(&"a".repeat(3900), 2));
+    }
+
+    #[test]
+    fn page_overhead_is_taken_into_account() {
+        // exactly two messages' worth of characters, but every cut page keeps room for a closing
+        // codeblock, so it takes three
+        assert!(overflows(&"a".repeat(4000), 2));
+
+        // under two messages' worth, but reopening the codeblock on the second page pushes it over
+        let content = format!("```\n{}```", "a".repeat(3990));
+        assert!(content.chars().count() < 4000);
+        assert!(overflows(&content, 2));
+    }
+
+    #[test]
+    fn overflowing_content_is_moved_into_a_text_file() {
+        let code = "let x = 1;\n".repeat(300);
+        let mut builder = MessageBuilder::from(format!("```rust\n{code}```"));
+
+        TextAttachmentOverflow.apply(&mut builder, 1);
+
+        assert_eq!(
+            builder.content.as_deref(),
+            Some("The output was too long to send, so it has been attached as a file.")
+        );
+        assert_eq!(builder.attachments.len(), 1);
+        assert_eq!(&*builder.attachments[0].name, "output.txt");
+        // the codeblock's fences and language aren't part of the file
+        assert_eq!(builder.attachments[0].data, code.into_bytes());
+    }
+
+    #[test]
+    fn content_is_left_alone_when_it_fits_or_overflow_is_truncated() {
+        let content = "a".repeat(3000);
+
+        let mut builder = MessageBuilder::from(content.clone());
+        TextAttachmentOverflow.apply(&mut builder, 2);
+        assert_eq!(builder.content.as_deref(), Some(&*content));
+        assert!(builder.attachments.is_empty());
+
+        let mut builder = MessageBuilder::from(content.clone());
+        builder.truncate_overflow = true;
+        TextAttachmentOverflow.apply(&mut builder, 1);
+        assert_eq!(builder.content.as_deref(), Some(&*content));
+        assert!(builder.attachments.is_empty());
+    }
+}
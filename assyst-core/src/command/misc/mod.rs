@@ -13,7 +13,7 @@ use assyst_database::model::guild_disabled_command::GuildDisabledCommand;
 use assyst_proc_macro::command;
 use assyst_string_fmt::{Ansi, Markdown};
 
-use super::arguments::{Codeblock, Image, ImageUrl, RestNoFlags, Word};
+use super::arguments::{Codeblock, Image, ImageUrl, RestNoFlags, User, Word};
 use super::registry::get_or_init_commands;
 use super::{Category, CommandCtxt};
 use crate::command::Availability;
@@ -113,6 +113,31 @@ pub async fn exec(ctxt: CommandCtxt<'_>, script: RestNoFlags) -> anyhow::Result<
     Ok(())
 }
 
+#[command(
+    description = "toggle whether a user is globally blacklisted from using assyst",
+    cooldown = Duration::from_millis(1),
+    access = Availability::Dev,
+    category = Category::Misc,
+    examples = ["@user", "233667448887312385"]
+)]
+pub async fn blacklist(ctxt: CommandCtxt<'_>, user: User) -> anyhow::Result<()> {
+    let user_id = user.0.id.get();
+    let blacklisted = !ctxt.assyst().user_globally_blacklisted(user_id);
+
+    ctxt.assyst()
+        .set_user_globally_blacklisted(user_id, blacklisted)
+        .await
+        .context("Failed to update global blacklist")?;
+
+    if blacklisted {
+        ctxt.reply(format!("{} is now blacklisted.", user.0.name)).await?;
+    } else {
+        ctxt.reply(format!("{} is no longer blacklisted.", user.0.name)).await?;
+    }
+
+    Ok(())
+}
+
 #[command(
     description = "evaluate javascript code",
     cooldown = Duration::from_millis(1),
@@ -124,7 +149,7 @@ pub async fn exec(ctxt: CommandCtxt<'_>, script: RestNoFlags) -> anyhow::Result<
 pub async fn eval(ctxt: CommandCtxt<'_>, script: Codeblock) -> anyhow::Result<()> {
     let result = fake_eval(
         &ctxt.assyst().reqwest_client,
-        script.0,
+        script.code,
         true,
         ctxt.data.message,
         Vec::new(),
@@ -307,9 +332,7 @@ pub async fn topcommands(ctxt: CommandCtxt<'_>, command: Option<Word>) -> anyhow
         .await
         .context("Failed to get command usage stats")?;
 
-        let rate = diff_lock
-            .get_mut(command_name)
-            .map_or(0, assyst_common::util::rate_tracker::RateTracker::get_rate);
+        let rate = diff_lock.get_rate(command_name);
 
         ctxt.reply(format!(
             "Command `{command_name}` has been used **{}** times. ({rate}/hr)",
@@ -325,9 +348,7 @@ pub async fn topcommands(ctxt: CommandCtxt<'_>, command: Option<Word>) -> anyhow
             .iter()
             .take(20)
             .map(|t| {
-                let rate = diff_lock
-                    .get_mut(&t.command_name[..])
-                    .map_or(0, assyst_common::util::rate_tracker::RateTracker::get_rate);
+                let rate = diff_lock.get_rate(&t.command_name[..]);
                 (
                     &t.command_name[..],
                     format!("{} {}", t.uses, format!("({rate}/hr)").fg_green()),
@@ -39,7 +39,7 @@ pub async fn default(ctxt: CommandCtxt<'_>) -> anyhow::Result<()> {
     category = Category::Misc,
     examples = ["-", "%"],
 )]
-pub async fn set(ctxt: CommandCtxt<'_>, new: Word) -> anyhow::Result<()> {
+pub async fn set(ctxt: CommandCtxt<'_>, #[description = "the new prefix"] new: Word) -> anyhow::Result<()> {
     let Some(guild_id) = ctxt.data.guild_id else {
         bail!("Prefix getting and setting can only be used in guilds.")
     };
@@ -2,7 +2,6 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{bail, Context};
 use assyst_common::util::discord::format_discord_timestamp;
-use assyst_common::util::format_time;
 use assyst_database::model::reminder::Reminder;
 use assyst_proc_macro::command;
 
@@ -52,11 +51,8 @@ pub async fn default(ctxt: CommandCtxt<'_>, when: Time, text: Option<Rest>) -> a
         .await
         .context("Failed to insert reminder to database")?;
 
-    ctxt.reply(format!(
-        "Reminder successfully set for {} from now.",
-        format_time(when.millis)
-    ))
-    .await?;
+    ctxt.reply(format!("Reminder successfully set for {when} from now."))
+        .await?;
 
     Ok(())
 }
@@ -16,7 +16,7 @@ use twilight_util::builder::command::{BooleanBuilder, IntegerBuilder};
 
 use crate::command::arguments::{Codeblock, ParseArgument};
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{flag_enabled, flag_parse_error, flags_from_str, FlagDecode, FlagType, ValidFlags};
 use crate::command::messagebuilder::{Attachment, MessageBuilder};
 use crate::command::{Availability, Category, CommandCtxt};
 use crate::downloader::download_content;
@@ -38,17 +38,27 @@ pub struct ChargeFlags {
     pub valgrind: bool,
 }
 impl FlagDecode for ChargeFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert("verbose", FlagType::NoValue.with_description("Show detailed output"));
+        valid_flags.insert("llir", FlagType::NoValue.with_description("Show the generated LLVM IR"));
+        valid_flags.insert(
+            "opt",
+            FlagType::WithValue.with_description("Set the optimisation level (default 0)"),
+        );
+        valid_flags.insert(
+            "valgrind",
+            FlagType::NoValue.with_description("Run the program under Valgrind"),
+        );
+
+        valid_flags
+    }
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("verbose", FlagType::NoValue);
-        valid_flags.insert("llir", FlagType::NoValue);
-        valid_flags.insert("opt", FlagType::WithValue);
-        valid_flags.insert("valgrind", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
         let opt = raw_decode
             .get("opt")
             .and_then(|x| x.as_deref())
@@ -56,10 +66,10 @@ impl FlagDecode for ChargeFlags {
             .context("Failed to parse optimisation level")?;
 
         let result = Self {
-            verbose: raw_decode.contains_key("verbose"),
-            llir: raw_decode.contains_key("llir"),
+            verbose: flag_enabled(&raw_decode, "verbose"),
+            llir: flag_enabled(&raw_decode, "llir"),
             opt,
-            valgrind: raw_decode.contains_key("valgrind"),
+            valgrind: flag_enabled(&raw_decode, "valgrind"),
         };
 
         if result.llir && result.valgrind {
@@ -88,7 +98,7 @@ impl ParseArgument for ChargeFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -134,7 +144,7 @@ pub async fn charge(ctxt: CommandCtxt<'_>, script: Codeblock, flags: ChargeFlags
     };
 
     exec_sync(&format!("cd {dir} && git pull"))?;
-    std::fs::write(format!("{dir}/input"), script.0).context("Failed to write input file")?;
+    std::fs::write(format!("{dir}/input"), script.code).context("Failed to write input file")?;
     exec_sync(&format!("cd {dir} && npm i --save-dev @types/node && tsc"))?;
 
     let commit_hash = exec_sync(&format!("cd {dir} && git rev-parse HEAD"))
@@ -209,13 +219,12 @@ pub async fn charge(ctxt: CommandCtxt<'_>, script: Codeblock, flags: ChargeFlags
             ctxt.reply(stdout.codeblock("llvm").to_string()).await?;
         } else {
             ctxt.reply(MessageBuilder {
-                content: None,
-                attachment: Some(Attachment {
+                attachments: vec![Attachment {
                     name: "out.txt".into(),
                     data: stdout.as_bytes().to_vec(),
-                }),
-                components: None,
-                component_ctxt: None,
+                    output: None,
+                }],
+                ..Default::default()
             })
             .await?;
         }
@@ -233,24 +242,28 @@ pub struct RustFlags {
     pub release: bool,
 }
 impl FlagDecode for RustFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert("miri", FlagType::NoValue.with_description("Run the program under Miri"));
+        valid_flags.insert("release", FlagType::NoValue.with_description("Build in release mode"));
+        valid_flags.insert("asm", FlagType::NoValue.with_description("Show the generated assembly"));
+        valid_flags.insert("clippy", FlagType::NoValue.with_description("Run Clippy on the code"));
+        valid_flags.insert("bench", FlagType::NoValue.with_description("Run benchmarks"));
+
+        valid_flags
+    }
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("miri", FlagType::NoValue);
-        valid_flags.insert("release", FlagType::NoValue);
-        valid_flags.insert("asm", FlagType::NoValue);
-        valid_flags.insert("clippy", FlagType::NoValue);
-        valid_flags.insert("bench", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
         let result = Self {
-            miri: raw_decode.contains_key("miri"),
-            asm: raw_decode.contains_key("asm"),
-            release: raw_decode.contains_key("release"),
-            clippy: raw_decode.contains_key("clippy"),
-            bench: raw_decode.contains_key("bench"),
+            miri: flag_enabled(&raw_decode, "miri"),
+            asm: flag_enabled(&raw_decode, "asm"),
+            release: flag_enabled(&raw_decode, "release"),
+            clippy: flag_enabled(&raw_decode, "clippy"),
+            bench: flag_enabled(&raw_decode, "bench"),
         };
 
         Ok(result)
@@ -276,7 +289,7 @@ impl ParseArgument for RustFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -318,15 +331,15 @@ pub async fn rust(ctxt: CommandCtxt<'_>, script: Codeblock, flags: RustFlags) ->
     };
 
     let result = if flags.miri {
-        run_miri(&ctxt.assyst().reqwest_client, &script.0, "nightly", opt).await?
+        run_miri(&ctxt.assyst().reqwest_client, &script.code, "nightly", opt).await?
     } else if flags.asm {
-        run_godbolt(&ctxt.assyst().reqwest_client, &script.0).await?
+        run_godbolt(&ctxt.assyst().reqwest_client, &script.code).await?
     } else if flags.clippy {
-        run_clippy(&ctxt.assyst().reqwest_client, &script.0, "nightly", opt).await?
+        run_clippy(&ctxt.assyst().reqwest_client, &script.code, "nightly", opt).await?
     } else if flags.bench {
-        run_benchmark(&ctxt.assyst().reqwest_client, &script.0).await?
+        run_benchmark(&ctxt.assyst().reqwest_client, &script.code).await?
     } else {
-        run_binary(&ctxt.assyst().reqwest_client, &script.0, "nightly", opt).await?
+        run_binary(&ctxt.assyst().reqwest_client, &script.code, "nightly", opt).await?
     };
 
     ctxt.reply(result.format().codeblock("rs")).await
@@ -344,7 +357,7 @@ pub async fn rust(ctxt: CommandCtxt<'_>, script: Codeblock, flags: RustFlags) ->
 pub async fn dash(ctxt: CommandCtxt<'_>, script: Codeblock) -> anyhow::Result<()> {
     let str_result = {
         let mut vm = Vm::new(Default::default());
-        let result = vm.eval(&script.0, Default::default());
+        let result = vm.eval(&script.code, Default::default());
         let mut scope = vm.scope();
         match result {
             Ok(result) => {
@@ -446,7 +459,7 @@ fn main() {
     send_processing = true,
 )]
 pub async fn rustc(ctxt: CommandCtxt<'_>, script: Codeblock) -> anyhow::Result<()> {
-    let script = RUSTC_BOILERPLATE.replace("{code}", &script.0);
+    let script = RUSTC_BOILERPLATE.replace("{code}", &script.code);
     let project_dir = "/tmp/_assyst_rustc_dev";
 
     if fs::metadata(project_dir).await.is_err() {
@@ -495,7 +508,7 @@ pub async fn rustc(ctxt: CommandCtxt<'_>, script: Codeblock) -> anyhow::Result<(
 
     // download toolchain so we use correct compiler for clippy_utils and other internal crates
     let raw = download_content(
-        &ctxt.assyst().reqwest_client,
+        ctxt.assyst(),
         "https://raw.githubusercontent.com/rust-lang/rust-clippy/master/rust-toolchain",
         usize::MAX,
         false,
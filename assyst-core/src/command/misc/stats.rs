@@ -130,13 +130,7 @@ pub async fn stats(
             .with_label_values(&["installs"])
             .get()
             .to_string();
-        let guilds_rate = ctxt
-            .assyst()
-            .metrics_handler
-            .guilds_rate_tracker
-            .lock()
-            .unwrap()
-            .get_rate();
+        let guilds_rate = ctxt.assyst().metrics_handler.guilds_rate_tracker.get_rate();
 
         let stats_table = key_value(&[
             ("Guilds".fg_cyan(), format!("{guilds_total} ({guilds_rate}/hr)")),
@@ -30,7 +30,7 @@ use crate::command::componentctxt::{
     ComponentMetadata,
 };
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{flag_parse_error, flags_from_str, FlagDecode, FlagType, ValidFlags};
 use crate::command::messagebuilder::{Attachment, MessageBuilder};
 use crate::command::{Availability, Category};
 use crate::downloader::{download_content, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES};
@@ -370,14 +370,21 @@ pub struct TagListFlags {
     pub page: u64,
 }
 impl FlagDecode for TagListFlags {
+    fn valid_flags() -> ValidFlags {
+        let mut valid_flags = HashMap::new();
+        valid_flags.insert(
+            "page",
+            FlagType::WithValue.with_description("Start at this page number"),
+        );
+
+        valid_flags
+    }
+
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("page", FlagType::WithValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
+        let raw_decode = flags_from_str(input, Self::valid_flags())?;
         let page = raw_decode
             .get("page")
             .and_then(|x| x.as_deref())
@@ -399,7 +406,7 @@ impl ParseArgument for TagListFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -512,7 +519,6 @@ pub async fn list(ctxt: CommandCtxt<'_>, user: Option<User>, flags: TagListFlags
 
     ctxt.reply(MessageBuilder {
         content: Some(message),
-        attachment: None,
         components: Some(vec![
             Component::Button(button_emoji_new(
                 &page_prev,
@@ -558,6 +564,7 @@ pub async fn list(ctxt: CommandCtxt<'_>, user: Option<User>, flags: TagListFlags
                 }),
             ),
         )),
+        ..Default::default()
     })
     .await?;
 
@@ -630,6 +637,7 @@ pub async fn raw(
     ctxt.reply(Attachment {
         name: format!("tag-{}.txt", name.0).into_boxed_str(),
         data: tag.data.into_bytes(),
+        output: None,
     })
     .await?;
 
@@ -727,7 +735,6 @@ pub async fn search(ctxt: CommandCtxt<'_>, query: Word, user: Option<User>) -> a
 
     ctxt.reply(MessageBuilder {
         content: Some(message),
-        attachment: None,
         components: Some(vec![
             Component::Button(button_emoji_new(
                 &page_prev,
@@ -773,6 +780,7 @@ pub async fn search(ctxt: CommandCtxt<'_>, query: Word, user: Option<User>) -> a
                 }),
             ),
         )),
+        ..Default::default()
     })
     .await?;
 
@@ -829,6 +837,7 @@ pub async fn backup(ctxt: CommandCtxt<'_>) -> anyhow::Result<()> {
     ctxt.reply(Attachment {
         name: "tags.zip".into(),
         data: out,
+        output: None,
     })
     .await?;
 
@@ -1005,7 +1014,7 @@ pub async fn default(
             output,
             attachment: Some((data, _)),
         }) => {
-            ctxt.reply((Image(data), output.as_str())).await?;
+            ctxt.reply((Image(data, None), output.as_str())).await?;
         },
         Err(err) => {
             ctxt.reply(assyst_tag::errors::format_error(&tag.data, err).codeblock("ansi"))
@@ -1070,7 +1079,7 @@ impl assyst_tag::Context for TagContext {
     fn download(&self, url: &str) -> anyhow::Result<String> {
         self.tokio
             .block_on(download_content(
-                &self.assyst.reqwest_client,
+                &self.assyst,
                 url,
                 ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES,
                 true,
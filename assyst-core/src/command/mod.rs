@@ -26,9 +26,12 @@
 //!   entry point (and the only relevant for the outside) is [`registry::find_command_by_name`],
 //!   which does the mapping mentioned above.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::str::SplitAsciiWhitespace;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use assyst_common::config::CONFIG;
@@ -37,18 +40,24 @@ use assyst_flux_iface::FluxHandler;
 use async_trait::async_trait;
 use autocomplete::AutocompleteData;
 use errors::TagParseError;
+use futures_util::future::BoxFuture;
+use tokio::time::timeout;
 use twilight_model::application::command::{CommandOption, CommandOptionChoice};
 use twilight_model::application::interaction::application_command::{CommandDataOption, CommandOptionValue};
+use twilight_model::application::interaction::InteractionChannel;
 use twilight_model::channel::{Attachment, Message};
 use twilight_model::http::interaction::InteractionResponse;
-use twilight_model::id::marker::{AttachmentMarker, ChannelMarker, GuildMarker, InteractionMarker};
+use twilight_model::id::marker::{AttachmentMarker, ChannelMarker, GuildMarker, InteractionMarker, UserMarker};
 use twilight_model::id::Id;
 use twilight_model::user::User;
 use twilight_util::builder::command::SubCommandBuilder;
 
+use self::arguments::ImageResolveConfig;
 use self::errors::{ArgsExhausted, ExecutionError, MetadataCheckError};
 use self::messagebuilder::MessageBuilder;
 use self::source::Source;
+use self::tokens::Tokens;
+use super::gateway_handler::message_parser::preprocess::message_mention_prefix;
 use super::gateway_handler::reply as gateway_reply;
 use crate::assyst::ThreadSafeAssyst;
 
@@ -61,10 +70,12 @@ pub mod fun;
 pub mod group;
 pub mod image;
 pub mod messagebuilder;
+pub mod middleware;
 pub mod misc;
 pub mod registry;
 pub mod services;
 pub mod source;
+pub mod tokens;
 
 /// Defines who can use a command in a server.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -109,6 +120,8 @@ pub struct CommandMetadata {
     pub context_menu_message_command: &'static str,
     pub context_menu_user_command: &'static str,
     pub guild_only: bool,
+    /// Which sources image arguments may be resolved from.
+    pub image_resolve: ImageResolveConfig,
 }
 
 #[derive(Debug)]
@@ -153,6 +166,33 @@ impl CommandGroupingInteractionInfo {
 #[derive(Debug, Clone)]
 pub struct CommandInteractionInfo {
     pub command_options: Vec<CommandOption>,
+    /// The arguments the command parses, in order, so that they can be checked against
+    /// `command_options`.
+    pub arguments: Vec<ArgumentInfo>,
+}
+
+/// An argument parsed by a command, and the names of the options it reads when parsing an
+/// interaction.
+#[derive(Debug, Clone)]
+pub struct ArgumentInfo {
+    pub name: &'static str,
+    pub option_names: Vec<String>,
+}
+impl ArgumentInfo {
+    /// How the argument is given in a slash command, e.g. `text:`, or `size-width: size-height:`
+    /// for an argument read from several options. Used in place of the raw message usage when an
+    /// interaction command's argument fails to parse.
+    pub fn interaction_usage(&self) -> String {
+        if self.option_names.is_empty() {
+            return self.name.to_owned();
+        }
+
+        self.option_names
+            .iter()
+            .map(|name| format!("{name}:"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
@@ -271,6 +311,9 @@ pub struct CommandData<'a> {
     pub assyst: &'a ThreadSafeAssyst,
     pub execution_timings: ExecutionTimings,
     pub calling_prefix: String,
+    /// The name of the command being run. This is always its canonical name, even if it was
+    /// invoked using an alias.
+    pub command_name: &'static str,
     pub channel_id: Id<ChannelMarker>,
     pub guild_id: Option<Id<GuildMarker>>,
     pub author: User,
@@ -279,6 +322,8 @@ pub struct CommandData<'a> {
     pub interaction_token: Option<String>,
     pub interaction_id: Option<Id<InteractionMarker>>,
     pub interaction_attachments: HashMap<Id<AttachmentMarker>, Attachment>,
+    /// Channels resolved from interaction channel options, e.g. to check their type.
+    pub interaction_channels: HashMap<Id<ChannelMarker>, InteractionChannel>,
     pub command_from_install_context: bool,
     /// None if not a context menu message command.
     pub resolved_messages: Option<Vec<Message>>,
@@ -286,33 +331,90 @@ pub struct CommandData<'a> {
     pub resolved_users: Option<Vec<User>>,
 }
 
-pub type RawMessageArgsIter<'a> = SplitAsciiWhitespace<'a>;
+pub type RawMessageArgsIter<'a> = Tokens<'a>;
 pub type InteractionMessageArgs<'a> = HashMap<String, &'a CommandDataOption>;
 
 /// A parsing context. Parsing contexts can either be for raw message commands or interaction
 /// commands, and the parsing method differs for each.
+///
+/// `C` is the context of the command being parsed. It is only something else in tests, which need
+/// a parsing context without a running bot (see `ParseCtxt::for_args`).
 #[derive(Clone)]
-pub struct ParseCtxt<'a, T> {
-    pub cx: CommandCtxt<'a>,
+pub struct ParseCtxt<'a, T, C = CommandCtxt<'a>> {
+    pub cx: C,
+    /// The image sources the command being parsed allows. Set from [`CommandMetadata`] before
+    /// parsing arguments.
+    pub image_resolve: ImageResolveConfig,
+    /// Users fetched so far while parsing, shared between forks.
+    users: Arc<Mutex<HashMap<Id<UserMarker>, User>>>,
     args: T,
+    _lifetime: PhantomData<&'a ()>,
 }
-impl<'a, T: Clone> ParseCtxt<'a, T> {
+impl<T: Clone, C: Clone> ParseCtxt<'_, T, C> {
     /// Cheaply forks this context. Useful for trying different combinations
     /// and throwing the fork away after failing.
     /// Also look at `commit_if_ok`.
     pub fn fork(&self) -> Self {
-        // if you change either type and these lines starts erroring, check that
-        // these are still cheap to clone.
+        // if you change the args type and this line starts erroring, check that it is still cheap
+        // to clone. The command context is a reference.
         let _: &T = &self.args;
-        let _: &CommandCtxt<'a> = &self.cx;
 
         Self {
             cx: self.cx.clone(),
+            image_resolve: self.image_resolve,
+            users: self.users.clone(),
             args: self.args.clone(),
+            _lifetime: PhantomData,
         }
     }
 }
 
+impl<T> ParseCtxt<'_, T> {
+    /// Fetches a user, caching it for the rest of this parse so that multiple arguments resolving
+    /// the same user (e.g., a `User` and an avatar `ImageUrl`) only fetch it once. Users included
+    /// in an interaction's resolved data are already cached, and users in the REST cache aren't
+    /// fetched either.
+    pub async fn fetch_user(&self, id: Id<UserMarker>) -> Result<User, TagParseError> {
+        let cached = self.users.lock().unwrap().get(&id).cloned();
+        if let Some(user) = cached {
+            return Ok(user);
+        }
+
+        let rest_cache = &self.cx.assyst().rest_cache_handler;
+        let user = match rest_cache.get_user(id) {
+            Some(user) => user,
+            None => {
+                let user = self.cx.assyst().http_client.user(id).await?.model().await?;
+                rest_cache.set_user(user.clone());
+                user
+            },
+        };
+        self.users.lock().unwrap().insert(id, user.clone());
+
+        Ok(user)
+    }
+
+    /// Resolves several users at once, in the given order, leaving out any that don't exist. Users
+    /// already known to this parse are reused, and the rest are resolved together by
+    /// [`Assyst::resolve_users`](crate::assyst::Assyst::resolve_users) (from the invoking guild's
+    /// members where possible).
+    pub async fn fetch_users(&self, ids: &[Id<UserMarker>]) -> Vec<User> {
+        let missing = {
+            let users = self.users.lock().unwrap();
+            ids.iter()
+                .copied()
+                .filter(|id| !users.contains_key(id))
+                .collect::<Vec<_>>()
+        };
+
+        let resolved = self.cx.assyst().resolve_users(self.cx.data.guild_id, &missing).await;
+
+        let mut users = self.users.lock().unwrap();
+        users.extend(resolved);
+        ids.iter().filter_map(|id| users.get(id).cloned()).collect()
+    }
+}
+
 /// Calls the function with a fork of this context (allowing some arbitrary mutations)
 /// and only actually applies the changes made to the fork if it returns `Ok`.
 ///
@@ -334,59 +436,214 @@ macro_rules! commit_if_ok {
     }};
 }
 
+/// Like [`commit_if_ok`], but for a group of arguments: runs the block with a fork of the context
+/// bound to the given name, and only applies the fork if the block evaluates to `Ok`. If any
+/// argument in the group fails to parse, none of the input consumed by the group is.
+///
+/// The block is evaluated inside an async block, so `?` and `.await` can be used within it, and
+/// `?` only exits the group rather than the enclosing function. The error type may need to be
+/// specified for `?` to infer it:
+///
+/// ```ignore
+/// let res = commit_group_if_ok!(ctxt, |fork| {
+///     let name = Word::parse_raw_message(fork, label.clone()).await?;
+///     let count = u64::parse_raw_message(fork, label.clone()).await?;
+///     Ok::<_, TagParseError>((name, count))
+/// });
+/// ```
+#[macro_export]
+macro_rules! commit_group_if_ok {
+    ($ctxt:expr, |$fork:ident| $body:block) => {{
+        // any context with a `fork` method works, which lets the rollback be tested on its own
+        let ctxt = &mut *$ctxt;
+        let mut fork = ctxt.fork();
+        let res = async {
+            let $fork = &mut fork;
+            $body
+        }
+        .await;
+        if res.is_ok() {
+            *ctxt = fork;
+        }
+        res
+    }};
+}
+
+/// Repeatedly evaluates the block, which parses one value from the context bound to the given name
+/// and evaluates to `Result<Option<T>, E>`, collecting the values until it gives `Ok(None)` or an
+/// `Err` (which is returned).
+///
+/// A value parsed without consuming any input (such as an argument that falls back to a default
+/// when there is nothing left) also ends the list, and isn't included in it, since parsing again
+/// would give the same value forever. The context needs a `remaining_input` method for this.
+///
+/// Like [`commit_group_if_ok`], the block is evaluated inside an async block.
+#[macro_export]
+macro_rules! collect_while_advancing {
+    ($ctxt:expr, |$c:ident| $body:block) => {{
+        let ctxt = &mut *$ctxt;
+        let mut items = Vec::new();
+        loop {
+            let before = ctxt.remaining_input().map(str::len);
+            let res = async {
+                let $c = &mut *ctxt;
+                $body
+            }
+            .await;
+            match res {
+                Ok(Some(value)) if ctxt.remaining_input().map(str::len) != before => items.push(value),
+                Ok(_) => break Ok(items),
+                Err(err) => break Err(err),
+            }
+        }
+    }};
+}
+
 /// A label for a command argument.
 pub type Label = Option<(String, String)>;
 
+/// The invoking message, or [`TagParseError::NoMessageContext`] if there is none. That error has a
+/// low severity, so parsers that try several sources (such as images) move on to the next one.
+fn require_message(message: Option<&Message>) -> Result<&Message, TagParseError> {
+    message.ok_or(TagParseError::NoMessageContext)
+}
+
 impl<'a> ParseCtxt<'a, RawMessageArgsIter<'a>> {
     pub fn new(ctxt: CommandCtxt<'a>, args: &'a str) -> Self {
         Self {
-            args: args.split_ascii_whitespace(),
+            args: Tokens::new(args),
             cx: ctxt,
+            image_resolve: ImageResolveConfig::default(),
+            users: Arc::default(),
+            _lifetime: PhantomData,
         }
     }
 
-    /// Eagerly takes a word.
+    /// The message this command was invoked with. Raw message commands always have one, but since
+    /// parsers may be reused from code paths that don't, this returns an error rather than
+    /// panicking if there is none.
+    pub fn message(&self) -> Result<&'a Message, TagParseError> {
+        require_message(self.cx.data.message)
+    }
+}
+
+#[cfg(test)]
+impl<'a> ParseCtxt<'a, RawMessageArgsIter<'a>, ()> {
+    /// A raw message parsing context for `args` without a command, for testing how input is
+    /// consumed.
+    pub fn for_args(args: &'a str) -> Self {
+        Self {
+            args: Tokens::new(args),
+            cx: (),
+            image_resolve: ImageResolveConfig::default(),
+            users: Arc::default(),
+            _lifetime: PhantomData,
+        }
+    }
+}
+
+impl<'a, C> ParseCtxt<'a, RawMessageArgsIter<'a>, C> {
+    /// Eagerly takes a word. Quoted text counts as one word; see [`Tokens`] for the rules.
     /// If you want to "peek" or you aren't sure if you might want to undo this,
     /// consider using `commit_if_ok` or `fork` to try it in a subcontext.
-    pub fn next_word(&mut self, label: Label) -> Result<&'a str, ArgsExhausted> {
+    pub fn next_word(&mut self, label: Label) -> Result<Cow<'a, str>, ArgsExhausted> {
         self.args.next().ok_or(ArgsExhausted(label))
     }
 
-    /// The rest of the message, excluding flags.
+    /// Like [`ParseCtxt::next_word`], but double-quoted text is taken literally: it runs until the
+    /// next double quote and backslashes within it are not treated as escapes.
+    pub fn next_quoted_word(&mut self, label: Label) -> Result<Cow<'a, str>, ArgsExhausted> {
+        self.args.next_literal().ok_or(ArgsExhausted(label))
+    }
+
+    /// The input that hasn't been consumed yet, or `None` if there is none left. Useful for
+    /// checking whether a parser consumed anything.
+    pub fn remaining_input(&self) -> Option<&'a str> {
+        self.args.remainder()
+    }
+
+    /// Returns the next word without consuming it, or `None` if there are none left.
+    ///
+    /// Useful for deciding how to parse an argument (e.g. whether the next word is a flag or a
+    /// number) before committing to it. Once a parser has decided, it should consume the word with
+    /// `next_word`. Parsers that can fail after consuming input should still run through
+    /// `commit_if_ok` (or a `fork`), so that the input is only consumed if they succeed.
+    pub fn peek_word(&self) -> Option<Cow<'a, str>> {
+        self.args.peek()
+    }
+
+    /// The rest of the message, excluding flags. Whitespace within the text is kept exactly as it
+    /// was sent, and must not be collapsed or trimmed here.
     pub fn rest(&mut self, label: Label) -> Result<String, TagParseError> {
+        take_rest(&mut self.args, label)
+    }
+
+    pub fn rest_all(&self, _: Label) -> String {
+        self.args.remainder().map(std::borrow::ToOwned::to_owned).unwrap_or_default()
+    }
+
+    /// The rest of the message exactly as it was sent, including flags, newlines and any
+    /// surrounding whitespace. Consumes all remaining arguments.
+    pub fn rest_verbatim(&mut self, label: Label) -> Result<&'a str, ArgsExhausted> {
         let raw = self
             .args
             .remainder()
-            .ok_or(TagParseError::ArgsExhausted(ArgsExhausted(label.clone())))?;
+            .filter(|r| !r.trim().is_empty())
+            .ok_or(ArgsExhausted(label))?;
 
-        let (args, flags) = if let Some(idx) = raw.find("--") {
-            (&raw[..idx], &raw[idx..])
-        } else {
-            (raw, "")
-        };
+        self.args = Tokens::new("");
 
-        if args.is_empty() {
-            return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
-        }
+        Ok(raw)
+    }
+}
 
-        self.args = flags.split_ascii_whitespace();
+/// Takes the rest of `args` up to any flags, leaving only the flags in `args`.
+fn take_rest(args: &mut Tokens<'_>, label: Label) -> Result<String, TagParseError> {
+    let raw = args
+        .remainder()
+        .ok_or(TagParseError::ArgsExhausted(ArgsExhausted(label.clone())))?;
 
-        Ok(args.to_owned())
-    }
+    let (rest, flags) = if let Some(idx) = raw.find("--") {
+        (&raw[..idx], &raw[idx..])
+    } else {
+        (raw, "")
+    };
 
-    pub fn rest_all(&self, _: Label) -> String {
-        self.args.remainder().map(std::borrow::ToOwned::to_owned).unwrap_or_default()
+    if rest.is_empty() {
+        return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
     }
+
+    *args = Tokens::new(flags);
+
+    Ok(rest.to_owned())
 }
 
 impl<'a> ParseCtxt<'a, InteractionMessageArgs<'a>> {
-    pub fn new(ctxt: CommandCtxt<'a>, args: &'a [CommandDataOption]) -> Self {
+    /// `resolved_users` are the users included in the interaction's resolved data, which
+    /// [`ParseCtxt::fetch_user`] then returns without fetching them.
+    pub fn new(
+        ctxt: CommandCtxt<'a>,
+        args: &'a [CommandDataOption],
+        resolved_users: HashMap<Id<UserMarker>, User>,
+    ) -> Self {
         let mut map = HashMap::new();
         for arg in args {
             map.insert(arg.name.clone(), arg);
         }
 
-        Self { args: map, cx: ctxt }
+        Self {
+            args: map,
+            cx: ctxt,
+            image_resolve: ImageResolveConfig::default(),
+            users: Arc::new(Mutex::new(resolved_users)),
+            _lifetime: PhantomData,
+        }
+    }
+
+    /// Whether a user with this ID was included in the interaction's resolved data (or has been
+    /// fetched while parsing).
+    pub fn is_known_user(&self, id: Id<UserMarker>) -> bool {
+        self.users.lock().unwrap().contains_key(&id)
     }
 
     /// Eagerly finds an option by its name.
@@ -415,9 +672,67 @@ impl<'a> CommandCtxt<'a> {
 
     pub async fn reply(&self, builder: impl Into<MessageBuilder>) -> anyhow::Result<()> {
         let builder = builder.into();
+        let original_content = builder.content.clone().unwrap_or_default();
+        self.send_reply(builder, original_content).await
+    }
+
+    /// Runs the output middleware on a reply and sends it, recording `original_content` as what
+    /// the command replied with.
+    async fn send_reply(&self, mut builder: MessageBuilder, original_content: String) -> anyhow::Result<()> {
+        self.assyst().output_middleware.apply(self, &mut builder).await?;
+
         match self.data.source {
-            Source::RawMessage => gateway_reply::reply_raw_message(self, builder).await,
-            Source::Interaction => gateway_reply::reply_interaction_command(self, builder).await,
+            Source::RawMessage => gateway_reply::reply_raw_message(self, builder, original_content).await,
+            Source::Interaction => gateway_reply::reply_interaction_command(self, builder, original_content).await,
+        }
+    }
+
+    /// Sends a further message after the current reply, instead of editing it, for example to send
+    /// a result after a "processing" message. Interaction commands that haven't been responded
+    /// to yet, and raw message commands (whose replies aren't tied to an interaction), reply as
+    /// normal.
+    pub async fn followup(&self, builder: impl Into<MessageBuilder>) -> anyhow::Result<()> {
+        let responded = self.data.source == Source::Interaction
+            && self
+                .assyst()
+                .replies
+                .get_interaction_command(self.data.interaction_id.unwrap().get())
+                .is_some();
+
+        if !responded {
+            return self.reply(builder).await;
+        }
+
+        let mut builder = builder.into();
+        self.assyst().output_middleware.apply(self, &mut builder).await?;
+
+        gateway_reply::followup_interaction_command(self, builder)
+            .await
+            .map(|_| ())
+    }
+
+    /// Appends text to the current reply (or sends a new reply if there isn't one yet), for
+    /// commands that build their output progressively. Once the first message is full, the content
+    /// rolls over into further messages (followups, for interaction commands), up to the configured
+    /// maximum number of pages, after which it is sent as a file as usual.
+    pub async fn append_to_reply(&self, text: &str) -> anyhow::Result<()> {
+        let (content, pages) =
+            gateway_reply::appended(gateway_reply::current_reply_content(self), text, CONFIG.max_reply_pages);
+
+        if self.data.source == Source::RawMessage {
+            return self.reply(content).await;
+        }
+
+        // an interaction response is a single message, so any further pages are sent as followups
+        match pages {
+            Some(pages) => {
+                self.send_reply(pages[0].clone().into(), content).await?;
+                gateway_reply::update_interaction_pages(self, &pages[1..]).await
+            },
+            None => {
+                self.reply(content).await?;
+                gateway_reply::update_interaction_pages(self, &[]).await
+            },
         }
     }
 
@@ -425,15 +740,79 @@ impl<'a> CommandCtxt<'a> {
         self.data.assyst
     }
 
+    /// The prefix this command was invoked with, for showing to the user (e.g., in usage
+    /// messages). A mention prefix is shown as the default prefix instead, since a raw mention
+    /// isn't readable in a codeblock.
+    pub fn display_prefix(&self) -> &'a str {
+        if message_mention_prefix(&self.data.calling_prefix).is_some() {
+            &CONFIG.prefix.default
+        } else {
+            &self.data.calling_prefix
+        }
+    }
+
+    /// The canonical name of the command being run.
+    pub fn command_name(&self) -> &'static str {
+        self.data.command_name
+    }
+
+    /// How to invoke this command again, i.e. the display prefix followed by the command name.
+    pub fn invocation(&self) -> String {
+        format!("{}{}", self.display_prefix(), self.command_name())
+    }
+
     pub fn flux_handler(&self) -> &'a FluxHandler {
         &self.data.assyst.flux_handler
     }
 }
 
+/// Runs the parsing of a command's arguments, giving up if it takes longer than the configured
+/// budget (at least one second). Parsing can involve several network requests (fetching users,
+/// downloading media, etc.), so this stops a slow chain of them from tying up the handler
+/// indefinitely. Only parsing is covered, not running the command.
+pub async fn with_parse_timeout<T>(
+    parse: impl Future<Output = Result<T, ExecutionError>>,
+) -> Result<T, ExecutionError> {
+    let budget = Duration::from_secs(CONFIG.argument_parse_timeout_secs.max(1));
+
+    parse_with_timeout(budget, parse).await
+}
+
+/// Runs a command's access checks (see [`check_metadata`]), and then parses its arguments like
+/// [`with_parse_timeout`]. `parse` is only called if `check` passes, so a user who can't run the
+/// command never causes any parsing work, such as media downloads. Every `#[command]` runs its
+/// checks and parsing through this.
+pub async fn check_then_parse<X, T>(
+    ctxt: &mut X,
+    check: impl for<'c> FnOnce(&'c mut X) -> BoxFuture<'c, Result<(), ExecutionError>>,
+    parse: impl for<'c> FnOnce(&'c mut X) -> BoxFuture<'c, Result<T, ExecutionError>>,
+) -> Result<T, ExecutionError> {
+    check(ctxt).await?;
+    with_parse_timeout(parse(ctxt)).await
+}
+
+/// Runs `parse`, failing with [`TagParseError::ArgumentsTimeout`] if it doesn't finish within
+/// `budget`.
+async fn parse_with_timeout<T>(
+    budget: Duration,
+    parse: impl Future<Output = Result<T, ExecutionError>>,
+) -> Result<T, ExecutionError> {
+    timeout(budget, parse)
+        .await
+        .map_err(|_| ExecutionError::Parse(TagParseError::ArgumentsTimeout(budget)))?
+}
+
 pub async fn check_metadata(
     metadata: &'static CommandMetadata,
     ctxt: &mut CommandCtxt<'_>,
 ) -> Result<(), ExecutionError> {
+    // every access check must happen before the cooldown is applied (so that failing a check doesn't
+    // put the command on cooldown), and all of this happens before any arguments are parsed (which
+    // can involve downloading media)
+    if metadata.guild_only && ctxt.data.guild_id.is_none() {
+        return Err(ExecutionError::MetadataCheck(MetadataCheckError::GuildOnly));
+    }
+
     if metadata.age_restricted {
         let channel_age_restricted = ctxt
             .assyst()
@@ -514,10 +893,6 @@ pub async fn check_metadata(
             .insert(id, metadata.name, Instant::now());
     }
 
-    if metadata.guild_only && ctxt.data.guild_id.is_none() {
-        return Err(ExecutionError::MetadataCheck(MetadataCheckError::GuildOnly));
-    }
-
     if metadata.send_processing && ctxt.data.source == Source::RawMessage {
         if let Err(e) = ctxt.reply("Processing...").await {
             return Err(ExecutionError::Command(e));
@@ -538,9 +913,175 @@ pub async fn check_metadata(
             .await
             .map_err(|e| ExecutionError::Parse(errors::TagParseError::TwilightHttp(Box::new(e))))?;
 
-        ctxt.assyst()
-            .replies
-            .insert_interaction_command(ctxt.data.interaction_id.unwrap().get());
+        ctxt.assyst().replies.insert_interaction_command(
+            ctxt.data.interaction_id.unwrap().get(),
+            String::new(),
+            String::new(),
+            false,
+        );
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
+
+    const BUDGET: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn rest_keeps_spacing() {
+        let mut args = Tokens::new("\tascii  art\t\t");
+        assert_eq!(take_rest(&mut args, None).unwrap(), "\tascii  art\t\t");
+        assert_eq!(args.remainder(), None);
+
+        let mut args = Tokens::new("word \t a  b\t --flag value");
+        args.next();
+        assert_eq!(take_rest(&mut args, None).unwrap(), "\t a  b\t ");
+        assert_eq!(args.remainder(), Some("--flag value"));
+    }
+
+    #[test]
+    fn interaction_usage_lists_the_argument_options() {
+        let info = |name, option_names: &[&str]| ArgumentInfo {
+            name,
+            option_names: option_names.iter().map(|&n| n.to_owned()).collect(),
+        };
+
+        assert_eq!(info("text", &["text"]).interaction_usage(), "text:");
+        assert_eq!(
+            info("source", &["source-left", "source-right"]).interaction_usage(),
+            "source-left: source-right:"
+        );
+        // e.g. context menu commands, which read a resolved message rather than an option
+        assert_eq!(info("message", &[]).interaction_usage(), "message");
+    }
+
+    #[test]
+    fn missing_message_is_a_low_severity_argument_error() {
+        let err = require_message(None).unwrap_err();
+
+        assert!(matches!(err, TagParseError::NoMessageContext));
+        assert!(err.get_severity() == ErrorSeverity::Low);
+    }
+
+    #[tokio::test]
+    async fn slow_parse_times_out() {
+        let parse = async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        };
+
+        let result = parse_with_timeout(BUDGET, parse).await;
+        assert!(matches!(
+            result,
+            Err(ExecutionError::Parse(TagParseError::ArgumentsTimeout(budget))) if budget == BUDGET
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_checks_skip_parsing() {
+        let mut parsed = false;
+
+        let result = check_then_parse(
+            &mut parsed,
+            |_| Box::pin(async { Err(ExecutionError::MetadataCheck(MetadataCheckError::GuildOnly)) }),
+            |parsed| {
+                Box::pin(async move {
+                    *parsed = true;
+                    Ok(())
+                })
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(ExecutionError::MetadataCheck(MetadataCheckError::GuildOnly))
+        ));
+        assert!(!parsed);
+    }
+
+    #[tokio::test]
+    async fn fast_parse_keeps_its_result() {
+        assert!(matches!(parse_with_timeout(BUDGET, async { Ok(5) }).await, Ok(5)));
+
+        let failing = async { Err::<(), _>(ExecutionError::Parse(ArgsExhausted(None).into())) };
+        assert!(matches!(
+            parse_with_timeout(BUDGET, failing).await,
+            Err(ExecutionError::Parse(TagParseError::ArgsExhausted(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn successful_group_consumes_its_words() {
+        let mut ctxt = ParseCtxt::for_args("a b c");
+
+        let res = commit_group_if_ok!(&mut ctxt, |fork| {
+            let first = fork.next_word(None)?;
+            let second = fork.next_word(None)?;
+            Ok::<_, ArgsExhausted>((first, second))
+        });
+
+        let (first, second) = res.unwrap();
+        assert_eq!((&*first, &*second), ("a", "b"));
+        assert_eq!(ctxt.remaining_input(), Some("c"));
+    }
+
+    #[tokio::test]
+    async fn failed_group_consumes_nothing() {
+        let mut ctxt = ParseCtxt::for_args("a");
+
+        let res = commit_group_if_ok!(&mut ctxt, |fork| {
+            fork.next_word(None)?;
+            fork.next_word(None)?;
+            Ok::<_, ArgsExhausted>(())
+        });
+
+        assert!(res.is_err());
+        assert_eq!(ctxt.remaining_input(), Some("a"));
+        assert_eq!(ctxt.next_word(None).unwrap(), "a");
+    }
+
+    #[tokio::test]
+    async fn collects_until_nothing_parses() {
+        let mut ctxt = ParseCtxt::for_args("1 2 three 4");
+
+        let res = collect_while_advancing!(&mut ctxt, |ctxt| {
+            let mut fork = ctxt.fork();
+            let number = fork.next_word(None).ok().and_then(|w| w.parse::<u64>().ok());
+            if number.is_some() {
+                *ctxt = fork;
+            }
+            Ok::<_, ArgsExhausted>(number)
+        });
+
+        assert_eq!(res.unwrap(), [1, 2]);
+        assert_eq!(ctxt.remaining_input(), Some("three 4"));
+    }
+
+    #[tokio::test]
+    async fn values_that_consume_nothing_end_the_list() {
+        let mut ctxt = ParseCtxt::for_args("left right");
+
+        // like an argument with a default, this always succeeds, taking a word only if it can
+        let res = collect_while_advancing!(&mut ctxt, |ctxt| {
+            let mut fork = ctxt.fork();
+            match fork.next_word(None) {
+                Ok(word) if word == "left" => {
+                    *ctxt = fork;
+                    Ok::<_, ArgsExhausted>(Some("left"))
+                },
+                _ => Ok(Some("center")),
+            }
+        });
+
+        assert_eq!(res.unwrap(), ["left"]);
+        assert_eq!(ctxt.remaining_input(), Some("right"));
+
+        let mut empty = ParseCtxt::for_args("");
+        let res = collect_while_advancing!(&mut empty, |_ctxt| { Ok::<_, ArgsExhausted>(Some("center")) });
+        assert!(res.unwrap().is_empty());
+    }
+}
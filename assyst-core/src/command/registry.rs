@@ -2,11 +2,11 @@ use std::collections::HashMap;
 use std::sync::OnceLock;
 
 use tracing::debug;
-use twilight_model::application::command::{Command as InteractionCommand, CommandType};
+use twilight_model::application::command::{Command as InteractionCommand, CommandOption, CommandType};
 
 use super::{fun, image, misc, services, TCommand};
 use crate::assyst::ThreadSafeAssyst;
-use crate::command::CommandMetadata;
+use crate::command::{CommandGroupingInteractionInfo, CommandMetadata};
 
 macro_rules! declare_commands {
     ($($name:path),*) => {
@@ -81,6 +81,7 @@ declare_commands!(
     image::wormhole_command,
     image::zoom_command,
     image::zoomblur_command,
+    misc::blacklist_command,
     misc::btchannel::btchannel_command,
     misc::chars_command,
     misc::command_command,
@@ -114,6 +115,7 @@ pub fn get_or_init_commands() -> &'static HashMap<&'static str, TCommand> {
 
         for &command in RAW_COMMANDS {
             let &CommandMetadata { name, aliases, .. } = command.metadata();
+
             map.insert(name, command);
             debug!("Registering command {} (aliases={:?})", name, aliases);
             for alias in aliases {
@@ -191,3 +193,165 @@ pub async fn register_interaction_commands(assyst: ThreadSafeAssyst) -> anyhow::
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use twilight_util::builder::command::StringBuilder;
+
+    use super::*;
+    use crate::command::{ArgumentInfo, CommandInteractionInfo};
+
+    /// Discord's limit on the number of options a single (sub)command can have.
+    const MAX_COMMAND_OPTIONS: usize = 25;
+
+    /// Checks that the options a command declares match the arguments it parses, returning a
+    /// description of the first problem found.
+    ///
+    /// Interaction arguments are looked up by option name, so an argument whose options are missing
+    /// (or options that no argument reads) would otherwise cause inputs to be silently dropped, and
+    /// too many options or duplicate names cause Discord to reject the whole command list at
+    /// registration.
+    fn validate_command_options(command_name: &str, info: &CommandInteractionInfo) -> Result<(), String> {
+        let options = &info.command_options;
+        if options.len() > MAX_COMMAND_OPTIONS {
+            return Err(format!(
+                "command {command_name} has {} options, but the limit is {MAX_COMMAND_OPTIONS}",
+                options.len()
+            ));
+        }
+
+        let expected = info
+            .arguments
+            .iter()
+            .flat_map(|argument| argument.option_names.iter().map(move |name| (argument.name, name)))
+            .collect::<Vec<_>>();
+
+        for (index, option) in options.iter().enumerate() {
+            match expected.get(index) {
+                Some((_, name)) if **name == option.name => {},
+                Some((argument, name)) => {
+                    return Err(format!(
+                        "command {command_name} declares the option {} where the argument {argument} expects {name}",
+                        option.name
+                    ));
+                },
+                None => {
+                    return Err(format!(
+                        "command {command_name} declares the option {}, which no argument reads",
+                        option.name
+                    ));
+                },
+            }
+        }
+
+        if let Some((argument, name)) = expected.get(options.len()) {
+            return Err(format!(
+                "command {command_name} doesn't declare the option {name}, which the argument {argument} reads"
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        for option in options {
+            if !seen.insert(&option.name) {
+                return Err(format!(
+                    "command {command_name} has multiple arguments generating the option {}",
+                    option.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the options of a command, or of each subcommand of a group.
+    fn validate_interaction_info(command_name: &str, info: &CommandGroupingInteractionInfo) -> Result<(), String> {
+        match info {
+            CommandGroupingInteractionInfo::Command(info) => validate_command_options(command_name, info),
+            CommandGroupingInteractionInfo::Group(subcommands) => {
+                subcommands.iter().try_for_each(|(subcommand, info)| {
+                    validate_command_options(&format!("{command_name} {subcommand}"), info)
+                })
+            },
+        }
+    }
+
+    fn info(options: &[&str], arguments: &[(&'static str, &[&str])]) -> CommandInteractionInfo {
+        CommandInteractionInfo {
+            command_options: options
+                .iter()
+                .map(|&name| StringBuilder::new(name, "option").build())
+                .collect(),
+            arguments: arguments
+                .iter()
+                .map(|&(name, options)| ArgumentInfo {
+                    name,
+                    option_names: options.iter().map(|&option| option.to_owned()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn every_command_declares_the_options_it_parses() {
+        for &command in RAW_COMMANDS {
+            let name = command.metadata().name;
+            if let Err(error) = validate_interaction_info(name, &command.interaction_info()) {
+                panic!("{error}");
+            }
+        }
+    }
+
+    #[test]
+    fn matching_options_are_valid() {
+        let info = info(
+            &["text", "image-attachment", "image-link"],
+            &[("text", &["text"]), ("image", &["image-attachment", "image-link"])],
+        );
+
+        assert!(validate_command_options("test", &info).is_ok());
+    }
+
+    #[test]
+    fn argument_missing_from_the_options_is_caught() {
+        let info = info(&["text"], &[("text", &["text"]), ("quality", &["quality"])]);
+
+        let error = validate_command_options("test", &info).unwrap_err();
+        assert!(error.contains("quality"), "{error}");
+    }
+
+    #[test]
+    fn options_out_of_order_or_unread_are_caught() {
+        let swapped = info(&["b", "a"], &[("a", &["a"]), ("b", &["b"])]);
+        assert!(validate_command_options("test", &swapped).is_err());
+
+        let unread = info(&["a", "b"], &[("a", &["a"])]);
+        assert!(validate_command_options("test", &unread).is_err());
+    }
+
+    #[test]
+    fn multiple_images_fit_within_the_option_limit() {
+        use crate::command::arguments::{Image, ParseArgument};
+
+        let options = <Vec<Image>>::as_command_options("images");
+        let names = options.iter().map(|option| option.name.clone()).collect::<Vec<_>>();
+        let info = CommandInteractionInfo {
+            command_options: options,
+            arguments: vec![ArgumentInfo {
+                name: "images",
+                option_names: names,
+            }],
+        };
+
+        assert_eq!(info.command_options.len(), 2);
+        assert!(validate_command_options("test", &info).is_ok());
+    }
+
+    #[test]
+    fn duplicate_options_are_caught() {
+        let info = info(&["a", "a"], &[("a", &["a"]), ("b", &["a"])]);
+
+        assert!(validate_command_options("test", &info).is_err());
+    }
+}
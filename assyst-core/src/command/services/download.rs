@@ -5,75 +5,190 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
 use assyst_common::config::CONFIG;
-use assyst_common::util::{filetype, format_duration, sanitise_filename};
+use assyst_common::util::{filetype, format_duration, parse_file_size, sanitise_filename};
 use assyst_proc_macro::command;
 use assyst_string_fmt::Markdown;
 use rand::{thread_rng, Rng};
 use tokio::sync::Mutex;
 use tokio::task::JoinSet;
 use tokio::time::{sleep, timeout};
-use twilight_util::builder::command::{BooleanBuilder, IntegerBuilder};
+use twilight_model::application::interaction::application_command::CommandOptionValue;
+use twilight_util::builder::command::{BooleanBuilder, IntegerBuilder, StringBuilder};
 use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
-use crate::command::arguments::{ParseArgument, Word};
+use crate::command::arguments::ParseArgument;
 use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::flags::{
+    flag_enabled, flag_parse_error, flags_and_positionals_from_str, parse_flag, parse_flag_with, FlagDecode, FlagType,
+    ValidFlags,
+};
 use crate::command::messagebuilder::Attachment;
 use crate::command::{Availability, Category, CommandCtxt};
 //use crate::flag_parse_argument;
 use crate::rest::web_media_download::{download_web_media, get_youtube_playlist_entries, WebDownloadOpts};
-use crate::{int_arg_bool, int_arg_u64};
+use crate::{int_arg_bool, int_arg_u64, int_arg_u64_opt};
+
+/// The video qualities that can be requested, in pixels of height.
+const VALID_QUALITIES: &[u64] = &[144, 240, 360, 480, 720, 1080, 1440, 2160];
+
+fn validate_quality(quality: u64) -> anyhow::Result<u64> {
+    if !VALID_QUALITIES.contains(&quality) {
+        let valid = VALID_QUALITIES.iter().map(u64::to_string).collect::<Vec<_>>();
+        anyhow::bail!("Invalid quality {quality} (expected one of {})", valid.join(", "));
+    }
+
+    Ok(quality)
+}
+
+/// The audio formats that can be requested when downloading audio.
+const VALID_AUDIO_FORMATS: &[&str] = &["mp3", "ogg", "opus", "wav"];
+/// The audio bitrates that can be requested when downloading audio, in kbps.
+const VALID_AUDIO_BITRATES: &[u64] = &[8, 64, 96, 128, 256, 320];
+
+fn validate_audio_format(format: String) -> anyhow::Result<String> {
+    let format = format.to_ascii_lowercase();
+    if !VALID_AUDIO_FORMATS.contains(&format.as_str()) {
+        anyhow::bail!(
+            "Invalid audio format {format} (expected one of {})",
+            VALID_AUDIO_FORMATS.join(", ")
+        );
+    }
+
+    Ok(format)
+}
+
+fn validate_audio_bitrate(bitrate: u64) -> anyhow::Result<u64> {
+    if !VALID_AUDIO_BITRATES.contains(&bitrate) {
+        let valid = VALID_AUDIO_BITRATES.iter().map(u64::to_string).collect::<Vec<_>>();
+        anyhow::bail!("Invalid audio bitrate {bitrate} (expected one of {})", valid.join(", "));
+    }
+
+    Ok(bitrate)
+}
+
+/// Checks that the audio options are only given alongside `audio`, since they would otherwise be
+/// silently ignored.
+fn validate_audio_options(audio: bool, format: Option<&str>, bitrate: Option<u64>) -> anyhow::Result<()> {
+    if !audio && (format.is_some() || bitrate.is_some()) {
+        anyhow::bail!("The format and bitrate options can only be used when downloading audio (with --audio)");
+    }
+
+    Ok(())
+}
 
 #[derive(Default)]
 pub struct DownloadFlags {
+    /// Any values given alongside the flags. The first one is the URL to download.
+    pub positionals: Vec<String>,
     pub audio: bool,
+    /// The format to download audio as, if given. Only used along with `audio`.
+    pub audio_format: Option<String>,
+    /// The bitrate to download audio at in kbps, if given. Only used along with `audio`.
+    pub audio_bitrate: Option<u64>,
     pub quality: u64,
     pub verbose: bool,
+    /// Maximum size of the downloaded media, in bytes.
+    pub max_size: Option<u64>,
 }
 impl FlagDecode for DownloadFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self> {
+    fn valid_flags() -> ValidFlags {
         let mut valid_flags = HashMap::new();
-        valid_flags.insert("quality", FlagType::WithValue);
-        valid_flags.insert("audio", FlagType::NoValue);
-        valid_flags.insert("verbose", FlagType::NoValue);
+        valid_flags.insert(
+            "quality",
+            FlagType::WithValue
+                .with_short('q')
+                .with_default("720")
+                .with_description("Set resolution of output"),
+        );
+        valid_flags.insert(
+            "audio",
+            FlagType::NoValue
+                .with_short('a')
+                .with_description("Get content as audio"),
+        );
+        valid_flags.insert(
+            "format",
+            FlagType::WithValue.with_description("Set audio format (default mp3, requires audio)"),
+        );
+        valid_flags.insert(
+            "bitrate",
+            FlagType::WithValue.with_description("Set audio bitrate (default 128, requires audio)"),
+        );
+        valid_flags.insert(
+            "verbose",
+            FlagType::NoValue.with_description("For playlists, show detailed information"),
+        );
+        valid_flags.insert(
+            "max-size",
+            FlagType::WithValue.with_description("Set maximum size of output"),
+        );
+
+        valid_flags
+    }
+
+    fn from_str(input: &str) -> anyhow::Result<Self> {
+        let (raw_decode, positionals) = flags_and_positionals_from_str(input, Self::valid_flags())?;
+        let audio = flag_enabled(&raw_decode, "audio");
+        let audio_format = parse_flag(&raw_decode, "format")?
+            .map(validate_audio_format)
+            .transpose()?;
+        let audio_bitrate = parse_flag(&raw_decode, "bitrate")?
+            .map(validate_audio_bitrate)
+            .transpose()?;
+        validate_audio_options(audio, audio_format.as_deref(), audio_bitrate)?;
 
-        let raw_decode = flags_from_str(input, valid_flags)?;
         let result = Self {
-            audio: raw_decode.contains_key("audio"),
-            quality: raw_decode
-                .get("quality")
-                .unwrap_or(&None)
-                .clone()
-                .unwrap_or("720".to_owned())
-                .parse()
-                .context("Provided quality is invalid")?,
-            verbose: raw_decode.contains_key("verbose"),
+            positionals,
+            audio,
+            audio_format,
+            audio_bitrate,
+            // always present, since it has a default
+            quality: validate_quality(parse_flag(&raw_decode, "quality")?.unwrap_or_default())?,
+            verbose: flag_enabled(&raw_decode, "verbose"),
+            max_size: parse_flag_with(&raw_decode, "max-size", parse_file_size)?,
         };
 
         Ok(result)
     }
+
+    fn positionals(&self) -> &[String] {
+        &self.positionals
+    }
+}
+impl DownloadFlags {
+    /// The URL to download.
+    pub fn url(&self) -> Option<&str> {
+        self.positionals.first().map(String::as_str)
+    }
 }
 impl ParseArgument for DownloadFlags {
     fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
         vec![
+            StringBuilder::new("url", "url of the media to download")
+                .required(true)
+                .build(),
             IntegerBuilder::new("quality", "downloaded video quality")
                 .required(false)
-                .choices(vec![
-                    ("144", 144),
-                    ("240", 240),
-                    ("360", 360),
-                    ("480", 480),
-                    ("720", 720),
-                    ("1080", 1080),
-                ])
+                .choices(VALID_QUALITIES.iter().map(|q| (q.to_string(), *q as i64)))
                 .build(),
             BooleanBuilder::new("audio", "whether to download the media as an audio file")
                 .required(false)
                 .build(),
+            StringBuilder::new("format", "audio format, when downloading audio (default mp3)")
+                .required(false)
+                .choices(VALID_AUDIO_FORMATS.iter().map(|f| (*f, *f)))
+                .build(),
+            IntegerBuilder::new("bitrate", "audio bitrate in kbps, when downloading audio (default 128)")
+                .required(false)
+                .choices(VALID_AUDIO_BITRATES.iter().map(|b| (b.to_string(), *b as i64)))
+                .build(),
             BooleanBuilder::new("verbose", "for playlist downloading, show detailed information")
                 .required(false)
                 .build(),
+            StringBuilder::new("max-size", "maximum size of the downloaded media (e.g., 8MB, 500KB)")
+                .required(false)
+                .build(),
         ]
     }
 
@@ -82,7 +197,7 @@ impl ParseArgument for DownloadFlags {
         label: crate::command::Label,
     ) -> Result<Self, crate::command::errors::TagParseError> {
         let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
+        let parsed = Self::from_str(&args).map_err(flag_parse_error)?;
         Ok(parsed)
     }
 
@@ -90,14 +205,32 @@ impl ParseArgument for DownloadFlags {
         ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
         _: crate::command::Label,
     ) -> Result<Self, TagParseError> {
-        let quality = int_arg_u64!(ctxt, "quality", 720);
+        let positionals = match ctxt.option_by_name("url").map(|o| o.value.clone()) {
+            Ok(CommandOptionValue::String(url)) => vec![url],
+            _ => vec![],
+        };
+        let quality = validate_quality(int_arg_u64!(ctxt, "quality", 720)).map_err(TagParseError::FlagParseError)?;
         let audio = int_arg_bool!(ctxt, "audio", false);
+        let audio_format = match ctxt.option_by_name("format").map(|o| o.value.clone()) {
+            Ok(CommandOptionValue::String(format)) => Some(format),
+            _ => None,
+        };
+        let audio_bitrate = int_arg_u64_opt!(ctxt, "bitrate");
+        validate_audio_options(audio, audio_format.as_deref(), audio_bitrate).map_err(TagParseError::FlagParseError)?;
         let verbose = int_arg_bool!(ctxt, "verbose", false);
+        let max_size = match ctxt.option_by_name("max-size").map(|o| o.value.clone()) {
+            Ok(CommandOptionValue::String(size)) => Some(parse_file_size(&size)?),
+            _ => None,
+        };
 
         Ok(Self {
+            positionals,
             audio,
+            audio_format,
+            audio_bitrate,
             quality,
             verbose,
+            max_size,
         })
     }
 }
@@ -110,18 +243,22 @@ impl ParseArgument for DownloadFlags {
     cooldown = Duration::from_secs(2),
     category = Category::Services,
     usage = "[url] <flags>",
-    examples = ["https://youtu.be/dQw4w9WgXcQ", "https://youtu.be/dQw4w9WgXcQ --audio", "https://youtu.be/dQw4w9WgXcQ --quality 480"],
+    examples = ["https://youtu.be/dQw4w9WgXcQ", "https://youtu.be/dQw4w9WgXcQ --audio", "https://youtu.be/dQw4w9WgXcQ --audio --format opus --bitrate 256", "--audio https://youtu.be/dQw4w9WgXcQ", "https://youtu.be/dQw4w9WgXcQ --quality=480", "https://youtu.be/dQw4w9WgXcQ --max-size 8MB"],
     send_processing = true,
     flag_descriptions = [
-        ("audio", "Get content as MP3"),
-        ("quality [quality:144|240|360|480|720|1080|max]", "Set resolution of output"),
+        ("audio (-a)", "Get content as audio"),
+        ("format [format:mp3|ogg|opus|wav]", "Set audio format (default mp3, requires audio)"),
+        ("bitrate [kbps:8|64|96|128|256|320]", "Set audio bitrate (default 128, requires audio)"),
+        ("quality (-q) [quality:144|240|360|480|720|1080|1440|2160]", "Set resolution of output"),
+        ("max-size [size:e.g. 8MB|500KB|1.5GB|4MiB]", "Set maximum size of output"),
     ]
 )]
-pub async fn download(ctxt: CommandCtxt<'_>, url: Word, options: DownloadFlags) -> anyhow::Result<()> {
+pub async fn download(ctxt: CommandCtxt<'_>, options: DownloadFlags) -> anyhow::Result<()> {
+    let url = options.url().context("No URL was provided")?.to_owned();
     let mut opts = WebDownloadOpts::from_download_flags(options, CONFIG.urls.clone().cobalt_api);
 
-    if url.0.to_ascii_lowercase().contains("youtube.com/playlist") {
-        let videos = get_youtube_playlist_entries(&url.0).await?;
+    if url.to_ascii_lowercase().contains("youtube.com/playlist") {
+        let videos = get_youtube_playlist_entries(&url).await?;
 
         let videos_len = videos.len();
 
@@ -130,13 +267,14 @@ pub async fn download(ctxt: CommandCtxt<'_>, url: Word, options: DownloadFlags)
         opts.audio_only = Some(true);
 
         let main_msg = format!(
-            "{}Downloading {} videos as MP3. This may take a while!",
+            "{}Downloading {} videos as {}. This may take a while!",
             if videos_len > 100 {
                 format!(":warning: Playlist has {videos_len} videos, but the download limit is 100\n")
             } else {
                 String::new()
             },
-            videos.len()
+            videos.len(),
+            opts.audio_format.as_deref().unwrap_or("mp3").to_ascii_uppercase()
         );
 
         ctxt.reply(&main_msg[..]).await?;
@@ -173,11 +311,7 @@ pub async fn download(ctxt: CommandCtxt<'_>, url: Word, options: DownloadFlags)
                     }
                 };
 
-                let media = timeout(
-                    Duration::from_secs(120),
-                    download_web_media(&a.reqwest_client, &url, opts),
-                )
-                .await;
+                let media = timeout(Duration::from_secs(120), download_web_media(&a, &url, opts)).await;
                 match media {
                     Ok(Ok(m)) => {
                         let mut z_lock = z.lock().await;
@@ -250,6 +384,7 @@ pub async fn download(ctxt: CommandCtxt<'_>, url: Word, options: DownloadFlags)
             Attachment {
                 name: "files.zip".to_owned().into_boxed_str(),
                 data: out,
+                output: None,
             },
             {
                 let failed = failed.lock().unwrap();
@@ -271,7 +406,7 @@ pub async fn download(ctxt: CommandCtxt<'_>, url: Word, options: DownloadFlags)
         ))
         .await?;
     } else {
-        let result = download_web_media(&ctxt.assyst().reqwest_client, &url.0, opts).await?;
+        let result = download_web_media(ctxt.assyst(), &url, opts).await?;
 
         ctxt.reply((
             result,
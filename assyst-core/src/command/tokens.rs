@@ -0,0 +1,197 @@
+//! Splitting raw message arguments into words.
+
+use std::borrow::Cow;
+
+/// An iterator over the words of a raw message's arguments, split on ASCII whitespace.
+///
+/// A word that starts with a double (`"`) or single (`'`) quote runs until the matching closing
+/// quote instead, so that an argument containing spaces can be passed as one word. The quotes
+/// themselves are not part of the word. Quotes only have this meaning at the start of a word, so
+/// apostrophes within words (e.g. `don't`) are unaffected.
+///
+/// A backslash escapes the character after it, both inside and outside of quotes, and is removed
+/// from the word: `foo\ bar` is the single word `foo bar`, and `"say \"hi\""` is `say "hi"`. A
+/// backslash at the very end of the input is kept as it is. Words are borrowed from the input
+/// unless they contain escapes.
+///
+/// A quote with no closing quote takes the rest of the input as one word.
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+    rest: &'a str,
+}
+impl<'a> Tokens<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { rest: input }
+    }
+
+    /// The input that hasn't been split into words yet, or `None` if there is none left. This
+    /// starts immediately after the whitespace (or closing quote) that ended the previous word, so
+    /// any other whitespace is kept exactly as it was sent.
+    pub fn remainder(&self) -> Option<&'a str> {
+        if self.rest.is_empty() { None } else { Some(self.rest) }
+    }
+
+    /// Returns the next word without consuming it.
+    pub fn peek(&self) -> Option<Cow<'a, str>> {
+        self.clone().next()
+    }
+
+    /// Takes the next word like [`Iterator::next`], except that a word starting with a double quote
+    /// runs until the next double quote with no escape processing, so backslashes within it (e.g.
+    /// in Windows paths) are kept exactly as they were sent.
+    pub fn next_literal(&mut self) -> Option<Cow<'a, str>> {
+        let input = self.rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let Some(quoted) = input.strip_prefix('"') else {
+            return self.next();
+        };
+
+        let end = quoted.find('"').unwrap_or(quoted.len());
+        self.rest = quoted;
+        self.advance((end + 1).min(quoted.len()));
+
+        Some(Cow::Borrowed(&quoted[..end]))
+    }
+
+    /// Moves past everything up to `end`, plus a single whitespace character directly after it, if
+    /// there is one.
+    fn advance(&mut self, end: usize) {
+        let rest = &self.rest[end..];
+        self.rest = rest.strip_prefix(|c: char| c.is_ascii_whitespace()).unwrap_or(rest);
+    }
+}
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rest = self.rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let input = self.rest;
+
+        let quote = Some(input.chars().next()?).filter(|c| matches!(c, '"' | '\''));
+        let start = quote.map_or(0, char::len_utf8);
+
+        // only allocated once an escape is found, until then the word is a slice of the input
+        let mut unescaped: Option<String> = None;
+        let mut end = input.len();
+        let mut chars = input[start..].char_indices().map(|(i, c)| (i + start, c));
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' if let Some((_, escaped)) = chars.next() => {
+                    unescaped
+                        .get_or_insert_with(|| input[start..i].to_owned())
+                        .push(escaped);
+                },
+                c if Some(c) == quote || (quote.is_none() && c.is_ascii_whitespace()) => {
+                    end = i;
+                    break;
+                },
+                c => {
+                    if let Some(word) = &mut unescaped {
+                        word.push(c);
+                    }
+                },
+            }
+        }
+
+        let word = match unescaped {
+            Some(word) => Cow::Owned(word),
+            None => Cow::Borrowed(&input[start..end]),
+        };
+
+        // skip the closing quote; a closing whitespace is skipped by `advance`
+        let after = match quote {
+            Some(quote) if end < input.len() => end + quote.len_utf8(),
+            _ => end,
+        };
+        self.advance(after);
+
+        Some(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(input: &str) -> Vec<Cow<'_, str>> {
+        Tokens::new(input).collect()
+    }
+
+    #[test]
+    fn peeking_does_not_consume() {
+        let mut tokens = Tokens::new(r#""two words" next"#);
+
+        assert_eq!(tokens.peek().as_deref(), Some("two words"));
+        assert_eq!(tokens.peek().as_deref(), Some("two words"));
+        assert_eq!(tokens.next().as_deref(), Some("two words"));
+
+        assert_eq!(tokens.peek().as_deref(), Some("next"));
+        assert_eq!(tokens.next().as_deref(), Some("next"));
+        assert_eq!(tokens.peek(), None);
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(words("  a  b\tc\n"), ["a", "b", "c"]);
+        assert_eq!(words("   "), Vec::<Cow<str>>::new());
+    }
+
+    #[test]
+    fn quoted_words_can_contain_spaces() {
+        assert_eq!(
+            words(r#"foo "bar baz" 'qux  quux' end"#),
+            ["foo", "bar baz", "qux  quux", "end"]
+        );
+        assert_eq!(words(r#"say "it's fine""#), ["say", "it's fine"]);
+    }
+
+    #[test]
+    fn quotes_within_words_are_kept() {
+        assert_eq!(words("don't stop"), ["don't", "stop"]);
+    }
+
+    #[test]
+    fn escaped_space_does_not_split() {
+        assert_eq!(words(r"foo\ bar baz"), ["foo bar", "baz"]);
+    }
+
+    #[test]
+    fn escaped_quotes_lose_their_backslash() {
+        assert_eq!(words(r#""say \"hi\"" \"x"#), [r#"say "hi""#, r#""x"#]);
+        assert_eq!(words(r"a\\b trailing\"), [r"a\b", r"trailing\"]);
+    }
+
+    #[test]
+    fn unterminated_quote_takes_the_rest() {
+        assert_eq!(words(r#"a "b  c"#), ["a", "b  c"]);
+        assert_eq!(words(r#"a 'b \' c"#), ["a", "b ' c"]);
+    }
+
+    #[test]
+    fn words_without_escapes_are_borrowed() {
+        let mut tokens = Tokens::new(r#""a b" c\ d"#);
+
+        assert!(matches!(tokens.next(), Some(Cow::Borrowed("a b"))));
+        assert!(matches!(tokens.next(), Some(Cow::Owned(word)) if word == "c d"));
+    }
+
+    #[test]
+    fn literal_words_keep_backslashes() {
+        let mut tokens = Tokens::new(r#""C:\new folder\" next\ word "unterminated \"#);
+
+        assert_eq!(tokens.next_literal().as_deref(), Some(r"C:\new folder\"));
+        assert_eq!(tokens.next_literal().as_deref(), Some("next word"));
+        assert_eq!(tokens.next_literal().as_deref(), Some(r"unterminated \"));
+        assert_eq!(tokens.next_literal(), None);
+    }
+
+    #[test]
+    fn remainder_after_quoted_word_keeps_whitespace() {
+        let mut tokens = Tokens::new("\"a b\"  rest\n here");
+
+        assert_eq!(tokens.next().as_deref(), Some("a b"));
+        assert_eq!(tokens.remainder(), Some(" rest\n here"));
+        assert_eq!(tokens.next().as_deref(), Some("rest"));
+        assert_eq!(tokens.remainder(), Some(" here"));
+    }
+}
@@ -1,16 +1,51 @@
 use core::fmt;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Duration;
 
 use assyst_common::config::CONFIG;
 use bytes::Bytes;
-use futures_util::{Stream, StreamExt};
+use futures_util::future::{BoxFuture, Shared};
+use futures_util::{FutureExt, Stream, StreamExt};
 use human_bytes::human_bytes;
-use reqwest::{Client, StatusCode, Url};
+use reqwest::header::{ACCEPT_RANGES, RANGE};
+use reqwest::{Client, Response, StatusCode, Url};
+use tokio::sync::Semaphore;
+use tokio::time::timeout;
+
+use crate::assyst::Assyst;
 
 pub const ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES: usize = 250_000_000;
+/// How many times a download that drops partway through is retried before giving up.
+const MAX_DOWNLOAD_RESUMES: usize = 3;
 static PROXY_NUM: AtomicUsize = AtomicUsize::new(0);
 
+type InFlightDownload = Shared<BoxFuture<'static, Result<Vec<u8>, Arc<DownloadError>>>>;
+/// The URL, limit, whether the URL is untrusted and the options of a download.
+type DownloadKey = (String, usize, bool, DownloadOptions);
+type InFlightDownloads = Mutex<HashMap<DownloadKey, InFlightDownload>>;
+
+/// Downloads currently in progress, keyed by URL, limit, whether the URL is untrusted and the
+/// download options, so that concurrent requests for the same resource share one download instead
+/// of each fetching it.
+static IN_FLIGHT_DOWNLOADS: LazyLock<InFlightDownloads> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Options for [`download_content_with_options`]. The default has no timeout, no retries and no
+/// resuming, which is how [`download_content`] behaves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DownloadOptions {
+    /// How long each attempt at the download may take before it is abandoned.
+    pub timeout: Option<Duration>,
+    /// How many times the download is retried after timing out.
+    pub retries: usize,
+    /// Whether a download that drops partway through is picked up again, up to
+    /// [`MAX_DOWNLOAD_RESUMES`] times, instead of failing. Only applies to downloads that don't go
+    /// through the proxy.
+    pub resume: bool,
+}
+
 #[derive(Debug)]
 pub enum DownloadError {
     ProxyNetworkError,
@@ -18,7 +53,13 @@ pub enum DownloadError {
     Url(url::ParseError),
     NoHost,
     LimitExceeded(usize),
+    NotFound,
+    Forbidden,
+    HttpStatus(StatusCode),
     Reqwest(reqwest::Error),
+    Timeout(Duration),
+    /// An error from a download that was shared with other requests for the same resource.
+    Shared(Arc<DownloadError>),
 }
 
 impl fmt::Display for DownloadError {
@@ -30,7 +71,12 @@ impl fmt::Display for DownloadError {
             DownloadError::LimitExceeded(limit) => write!(f, "The output file exceeded the maximum file size limit of {}. Try using a smaller input.", human_bytes((*limit) as f64)),
             DownloadError::Url(e) => write!(f, "Failed to parse URL: {e}"),
             DownloadError::NoHost => write!(f, "No host found in URL"),
+            DownloadError::NotFound => write!(f, "The provided URL returned Not Found (404). Make sure the link is correct."),
+            DownloadError::Forbidden => write!(f, "The provided URL returned Forbidden (403). The website may not allow Assyst to access it."),
+            DownloadError::HttpStatus(status) => write!(f, "The provided URL returned an error status: {status}"),
             DownloadError::Reqwest(e) => write!(f, "{e}"),
+            DownloadError::Timeout(t) => write!(f, "The download took longer than {} seconds", t.as_secs()),
+            DownloadError::Shared(e) => write!(f, "{e}"),
         }
     }
 }
@@ -65,42 +111,208 @@ async fn download_with_proxy(
     Ok(resp.bytes_stream())
 }
 
-async fn download_no_proxy(
+/// Sends a request for the resource, optionally only requesting the bytes from `offset` onwards.
+async fn download_no_proxy(client: &Client, url: &str, offset: Option<usize>) -> Result<Response, DownloadError> {
+    let mut req = client
+        .get(url)
+        .header("User-Agent", "Assyst Discord Bot (https://github.com/jacherr/assyst2)");
+
+    if let Some(offset) = offset {
+        req = req.header(RANGE, format!("bytes={offset}-"));
+    }
+
+    let resp = req.send().await.map_err(DownloadError::Reqwest)?;
+
+    match resp.status() {
+        status if status.is_success() => Ok(resp),
+        StatusCode::NOT_FOUND => Err(DownloadError::NotFound),
+        StatusCode::FORBIDDEN => Err(DownloadError::Forbidden),
+        status => Err(DownloadError::HttpStatus(status)),
+    }
+}
+
+/// Downloads a resource with no proxy. If the connection drops partway through (or can't be made
+/// again while resuming), the download is retried up to `max_resumes` times, resuming from the
+/// downloaded offset if the host supports `Range` requests, or restarting from scratch if not.
+async fn download_no_proxy_resumable(
     client: &Client,
     url: &str,
-) -> Result<impl Stream<Item = Result<Bytes, reqwest::Error>>, DownloadError> {
-    Ok(client
-        .get(url)
-        .header("User-Agent", "Assyst Discord Bot (https://github.com/jacherr/assyst2)")
-        .send()
-        .await
-        .map_err(DownloadError::Reqwest)?
-        .bytes_stream())
+    limit: usize,
+    max_resumes: usize,
+) -> Result<Vec<u8>, DownloadError> {
+    let resp = download_no_proxy(client, url, None).await?;
+
+    // no need to start reading if the host already says it's too big
+    if resp.content_length().is_some_and(|len| len > limit as u64) {
+        return Err(DownloadError::LimitExceeded(limit));
+    }
+
+    let supports_ranges = resp
+        .headers()
+        .get(ACCEPT_RANGES)
+        .is_some_and(|v| v.as_bytes() == b"bytes");
+
+    let mut bytes = Vec::new();
+    let mut stream = resp.bytes_stream();
+    let mut result = read_stream_into(&mut stream, &mut bytes, limit).await;
+    let mut resumes = 0;
+
+    loop {
+        match result {
+            Ok(()) => return Ok(bytes),
+            Err(DownloadError::Reqwest(_)) if resumes < max_resumes => {
+                resumes += 1;
+
+                let offset = (supports_ranges && !bytes.is_empty()).then_some(bytes.len());
+                result = match download_no_proxy(client, url, offset).await {
+                    Ok(resp) => {
+                        // the host may still ignore the range and send everything, so start over if so
+                        if resp.status() != StatusCode::PARTIAL_CONTENT {
+                            bytes.clear();
+                        }
+
+                        stream = resp.bytes_stream();
+                        read_stream_into(&mut stream, &mut bytes, limit).await
+                    },
+                    // failing to connect again counts as another resume
+                    Err(e) => Err(e),
+                };
+            },
+            Err(e) => return Err(e),
+        }
+    }
 }
 
-async fn read_stream<S>(mut stream: S, limit: usize) -> Result<Vec<u8>, DownloadError>
+/// Reads a stream to its end, appending to `bytes`. The download is abandoned as soon as a chunk
+/// would take `bytes` over `limit`, so a host can't make us read (much) more than the limit, even
+/// if it sends more than its `Content-Length` said it would.
+async fn read_stream_into<S>(stream: &mut S, bytes: &mut Vec<u8>, limit: usize) -> Result<(), DownloadError>
 where
     S: Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
 {
-    let mut bytes = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(DownloadError::Reqwest)?;
 
-    while let Some(Ok(chunk)) = stream.next().await {
-        if bytes.len() > limit {
+        if bytes.len() + chunk.len() > limit {
             return Err(DownloadError::LimitExceeded(limit));
         }
 
         bytes.extend(chunk);
     }
 
+    Ok(())
+}
+
+async fn read_stream<S>(mut stream: S, limit: usize) -> Result<Vec<u8>, DownloadError>
+where
+    S: Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
+{
+    let mut bytes = Vec::new();
+    read_stream_into(&mut stream, &mut bytes, limit).await?;
     Ok(bytes)
 }
 
-/// Attempts to download a resource from a URL.
+/// Attempts to download a resource from a URL. If the same resource is already being downloaded,
+/// this waits for that download to finish and shares its result (or error) instead. Waits for one
+/// of `assyst`'s download permits before starting a new download.
 pub async fn download_content(
+    assyst: &Assyst,
+    url: &str,
+    limit: usize,
+    untrusted: bool,
+) -> Result<Vec<u8>, DownloadError> {
+    download_content_with_options(assyst, url, limit, untrusted, DownloadOptions::default()).await
+}
+
+/// Like [`download_content`], but with a timeout and retries (see [`DownloadOptions`]).
+pub async fn download_content_with_options(
+    assyst: &Assyst,
+    url: &str,
+    limit: usize,
+    untrusted: bool,
+    options: DownloadOptions,
+) -> Result<Vec<u8>, DownloadError> {
+    let key = (url.to_owned(), limit, untrusted, options);
+    let client = assyst.reqwest_client.clone();
+    let url = url.to_owned();
+
+    let permits = assyst.download_permits.clone();
+    let download = join_or_start_download(&IN_FLIGHT_DOWNLOADS, permits, key, move || async move {
+        download_content_with_timeout(&client, &url, limit, untrusted, options).await
+    });
+
+    // if nobody else was waiting on the download, the error can be handed back as-is
+    download
+        .await
+        .map_err(|e| Arc::try_unwrap(e).unwrap_or_else(DownloadError::Shared))
+}
+
+/// Returns the download in progress for `key`, or starts a new one with `download`.
+///
+/// New downloads run on their own task, which holds one of `permits` for as long as it runs and
+/// removes itself from `in_flight` once done. This way, the permit and the entry are always
+/// released, even if every request waiting on the download is cancelled (for example, because
+/// argument parsing timed out).
+fn join_or_start_download<D, F>(
+    in_flight: &'static InFlightDownloads,
+    permits: Arc<Semaphore>,
+    key: DownloadKey,
+    download: D,
+) -> InFlightDownload
+where
+    D: FnOnce() -> F + Send + 'static,
+    F: Future<Output = Result<Vec<u8>, DownloadError>> + Send + 'static,
+{
+    in_flight
+        .lock()
+        .unwrap()
+        .entry(key.clone())
+        .or_insert_with(|| {
+            let task = tokio::spawn(async move {
+                // the semaphore is never closed, so acquiring can't fail
+                let permit = permits.acquire_owned().await.expect("download semaphore closed");
+                let result = download().await.map_err(Arc::new);
+                drop(permit);
+
+                in_flight.lock().unwrap().remove(&key);
+                result
+            });
+
+            async move { task.await.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic())) }
+                .boxed()
+                .shared()
+        })
+        .clone()
+}
+
+async fn download_content_with_timeout(
     client: &Client,
     url: &str,
     limit: usize,
     untrusted: bool,
+    options: DownloadOptions,
+) -> Result<Vec<u8>, DownloadError> {
+    let max_resumes = if options.resume { MAX_DOWNLOAD_RESUMES } else { 0 };
+    let Some(duration) = options.timeout else {
+        return download_content_uncoalesced(client, url, limit, untrusted, max_resumes).await;
+    };
+
+    for _ in 0..=options.retries {
+        let download = download_content_uncoalesced(client, url, limit, untrusted, max_resumes);
+        if let Ok(result) = timeout(duration, download).await {
+            return result;
+        }
+    }
+
+    Err(DownloadError::Timeout(duration))
+}
+
+async fn download_content_uncoalesced(
+    client: &Client,
+    url: &str,
+    limit: usize,
+    untrusted: bool,
+    max_resumes: usize,
 ) -> Result<Vec<u8>, DownloadError> {
     const WHITELISTED_DOMAINS: &[&str] = &[
         "tenor.com",
@@ -137,6 +349,255 @@ pub async fn download_content(
     // - Proxy not configured,
     // - Proxy failed,
     // - Domain is whitelisted
-    let stream = download_no_proxy(client, url).await?;
-    read_stream(stream, limit).await
+    download_no_proxy_resumable(client, url, limit, max_resumes).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use futures_util::future::join_all;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn key(url: &str) -> DownloadKey {
+        (url.to_owned(), 0, false, DownloadOptions::default())
+    }
+
+    #[tokio::test]
+    async fn downloads_over_the_limit_wait_for_a_permit() {
+        static IN_FLIGHT: LazyLock<InFlightDownloads> = LazyLock::new(Default::default);
+        static RUNNING: AtomicUsize = AtomicUsize::new(0);
+        static MAX_RUNNING: AtomicUsize = AtomicUsize::new(0);
+        let permits = Arc::new(Semaphore::new(2));
+
+        let downloads = (0..6).map(|i| {
+            join_or_start_download(&IN_FLIGHT, permits.clone(), key(&i.to_string()), || async {
+                let running = RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+                MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                RUNNING.fetch_sub(1, Ordering::SeqCst);
+                Ok(Vec::new())
+            })
+        });
+
+        for result in join_all(downloads).await {
+            assert!(result.is_ok());
+        }
+        assert_eq!(MAX_RUNNING.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_for_the_same_resource_share_one_download() {
+        static IN_FLIGHT: LazyLock<InFlightDownloads> = LazyLock::new(Default::default);
+        static STARTED: AtomicUsize = AtomicUsize::new(0);
+        let permits = Arc::new(Semaphore::new(1));
+
+        let download = || async {
+            STARTED.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(vec![1, 2, 3])
+        };
+
+        let first = join_or_start_download(&IN_FLIGHT, permits.clone(), key("a"), download);
+        let second = join_or_start_download(&IN_FLIGHT, permits.clone(), key("a"), download);
+        assert_eq!(first.await.unwrap(), vec![1, 2, 3]);
+        assert_eq!(second.await.unwrap(), vec![1, 2, 3]);
+        assert_eq!(STARTED.load(Ordering::SeqCst), 1);
+
+        // once finished, the next request downloads the resource again
+        join_or_start_download(&IN_FLIGHT, permits, key("a"), download)
+            .await
+            .unwrap();
+        assert_eq!(STARTED.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn cancelled_downloads_release_their_permit_and_entry() {
+        static IN_FLIGHT: LazyLock<InFlightDownloads> = LazyLock::new(Default::default);
+        static FINISHED: AtomicBool = AtomicBool::new(false);
+        let permits = Arc::new(Semaphore::new(1));
+
+        let download = join_or_start_download(&IN_FLIGHT, permits.clone(), key("a"), || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            FINISHED.store(true, Ordering::SeqCst);
+            Ok(Vec::new())
+        });
+
+        // the only waiter gives up before the download finishes
+        assert!(timeout(Duration::from_millis(5), download).await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(FINISHED.load(Ordering::SeqCst));
+        assert_eq!(permits.available_permits(), 1);
+        assert!(IN_FLIGHT.lock().unwrap().is_empty());
+    }
+
+    fn chunks(sizes: &[usize]) -> impl Stream<Item = Result<Bytes, reqwest::Error>> + Unpin {
+        futures_util::stream::iter(
+            sizes
+                .iter()
+                .map(|&size| Ok(Bytes::from(vec![0; size])))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[tokio::test]
+    async fn streams_up_to_the_limit_are_read_in_full() {
+        let mut bytes = Vec::new();
+        read_stream_into(&mut chunks(&[4, 4, 2]), &mut bytes, 10).await.unwrap();
+
+        assert_eq!(bytes.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn streams_over_the_limit_stop_before_the_chunk_that_exceeds_it() {
+        // e.g. a host that sends more than the Content-Length it stated
+        let mut stream = chunks(&[4, 4, 4, 4]);
+        let mut bytes = Vec::new();
+
+        let result = read_stream_into(&mut stream, &mut bytes, 10).await;
+        assert!(matches!(result, Err(DownloadError::LimitExceeded(10))));
+        assert_eq!(bytes.len(), 8);
+
+        // nothing after the offending chunk was read
+        assert!(stream.next().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn resumed_streams_count_what_was_already_read() {
+        let mut bytes = vec![0; 8];
+
+        let result = read_stream_into(&mut chunks(&[3]), &mut bytes, 10).await;
+        assert!(matches!(result, Err(DownloadError::LimitExceeded(10))));
+        assert_eq!(bytes.len(), 8);
+    }
+
+    /// Serves one canned response per connection, in order, and returns the URL to request along
+    /// with the `Range` header of each request. Responses are written as they are and the
+    /// connection is then closed, so a response can promise more body than it sends (as if the
+    /// connection dropped), and an empty response fails the request outright.
+    async fn serve(responses: Vec<&'static [u8]>) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/file", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let recorded = ranges.clone();
+
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+
+                let request = String::from_utf8_lossy(&request).to_ascii_lowercase();
+                let range = request
+                    .lines()
+                    .find_map(|l| l.strip_prefix("range:"))
+                    .map(|r| r.trim().to_owned());
+                recorded.lock().unwrap().push(range);
+
+                _ = socket.write_all(response).await;
+            }
+        });
+
+        (url, ranges)
+    }
+
+    const TRUNCATED: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\nAccept-Ranges: bytes\r\n\r\n012345";
+
+    #[tokio::test]
+    async fn dropped_downloads_resume_from_where_they_stopped() {
+        let (url, ranges) = serve(vec![
+            TRUNCATED,
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 4\r\nContent-Range: bytes 6-9/10\r\n\r\n6789",
+        ])
+        .await;
+
+        let bytes = download_no_proxy_resumable(&Client::new(), &url, 100, MAX_DOWNLOAD_RESUMES)
+            .await
+            .unwrap();
+
+        assert_eq!(bytes, b"0123456789");
+        assert_eq!(*ranges.lock().unwrap(), [None, Some("bytes=6-".to_owned())]);
+    }
+
+    #[tokio::test]
+    async fn hosts_ignoring_the_range_restart_the_download() {
+        let (url, ranges) = serve(vec![
+            TRUNCATED,
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789",
+        ])
+        .await;
+
+        let bytes = download_no_proxy_resumable(&Client::new(), &url, 100, MAX_DOWNLOAD_RESUMES)
+            .await
+            .unwrap();
+
+        assert_eq!(bytes, b"0123456789");
+        assert_eq!(ranges.lock().unwrap()[1].as_deref(), Some("bytes=6-"));
+    }
+
+    #[tokio::test]
+    async fn failing_to_reconnect_counts_as_a_resume() {
+        let (url, _) = serve(vec![
+            TRUNCATED,
+            b"",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Length: 4\r\nContent-Range: bytes 6-9/10\r\n\r\n6789",
+        ])
+        .await;
+
+        let bytes = download_no_proxy_resumable(&Client::new(), &url, 100, MAX_DOWNLOAD_RESUMES)
+            .await
+            .unwrap();
+        assert_eq!(bytes, b"0123456789");
+
+        // with only one resume allowed, the failed reconnection uses it up
+        let (url, _) = serve(vec![TRUNCATED, b""]).await;
+        let result = download_no_proxy_resumable(&Client::new(), &url, 100, 1).await;
+        assert!(matches!(result, Err(DownloadError::Reqwest(_))));
+    }
+
+    #[tokio::test]
+    async fn downloads_are_not_resumed_unless_asked_to() {
+        let (url, ranges) = serve(vec![TRUNCATED]).await;
+
+        let result = download_no_proxy_resumable(&Client::new(), &url, 100, 0).await;
+
+        assert!(matches!(result, Err(DownloadError::Reqwest(_))));
+        assert_eq!(ranges.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn error_statuses_are_reported_with_their_meaning() {
+        let (url, _) = serve(vec![
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let client = Client::new();
+
+        let not_found = download_no_proxy_resumable(&client, &url, 100, 0).await.unwrap_err();
+        assert!(matches!(not_found, DownloadError::NotFound));
+        assert!(not_found.to_string().contains("Not Found (404)"));
+
+        let forbidden = download_no_proxy_resumable(&client, &url, 100, 0).await.unwrap_err();
+        assert!(matches!(forbidden, DownloadError::Forbidden));
+        assert!(forbidden.to_string().contains("Forbidden (403)"));
+
+        let other = download_no_proxy_resumable(&client, &url, 100, 0).await.unwrap_err();
+        assert!(matches!(
+            other,
+            DownloadError::HttpStatus(StatusCode::INTERNAL_SERVER_ERROR)
+        ));
+    }
 }
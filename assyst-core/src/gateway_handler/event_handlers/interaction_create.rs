@@ -24,9 +24,10 @@ use crate::command::componentctxt::ComponentInteractionData;
 use crate::command::registry::find_command_by_name;
 use crate::command::source::Source;
 use crate::command::{
-    CommandCtxt, CommandData, CommandGroupingInteractionInfo, ExecutionTimings, InteractionCommandParseCtxt,
+    CommandCtxt, CommandData, CommandGroupingInteractionInfo, ExecutionError, ExecutionTimings,
+    InteractionCommandParseCtxt,
 };
-use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
+use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity, PreParseError};
 
 fn parse_subcommand_data(data: &DiscordCommandData) -> Option<(String, CommandOptionValue)> {
     if let Some(option_zero) = data.options.first()
@@ -79,9 +80,19 @@ pub async fn handle(assyst: ThreadSafeAssyst, InteractionCreate(interaction): In
         }
     }
 
+    let author_id = interaction.author_id();
+
     if interaction.kind == InteractionType::ApplicationCommand
         && let Some(InteractionData::ApplicationCommand(command_data)) = interaction.data
     {
+        // same as raw message commands, blacklisted users are ignored before anything else happens
+        if let Some(author_id) = author_id
+            && assyst.user_globally_blacklisted(author_id.get())
+        {
+            debug!("{}", PreParseError::UserGloballyBlacklisted(author_id.get()));
+            return;
+        }
+
         let command = find_command_by_name(&command_data.name);
         let subcommand_data = parse_subcommand_data(&command_data);
 
@@ -146,6 +157,19 @@ pub async fn handle(assyst: ThreadSafeAssyst, InteractionCreate(interaction): In
                 .clone()
                 .map_or(HashMap::new(), |x| x.attachments);
 
+            let interaction_users = command_data
+                .resolved
+                .as_ref()
+                .map_or(HashMap::new(), |x| x.users.clone());
+            for user in interaction_users.values() {
+                assyst.rest_cache_handler.set_user(user.clone());
+            }
+
+            let interaction_channels = command_data
+                .resolved
+                .as_ref()
+                .map_or(HashMap::new(), |x| x.channels.clone());
+
             // resolve messages for context menu message commands
             let mut resolved_messages: Option<Vec<Message>> = None;
             if let Some(ms) = command_data.resolved.as_ref().map(|x| &x.messages)
@@ -173,6 +197,7 @@ pub async fn handle(assyst: ThreadSafeAssyst, InteractionCreate(interaction): In
                     metadata_check_start: Instant::now(),
                 },
                 calling_prefix: "/".to_owned(),
+                command_name: command.metadata().name,
                 message: None,
                 interaction_subcommand,
                 channel_id: interaction.channel.unwrap().id,
@@ -181,6 +206,7 @@ pub async fn handle(assyst: ThreadSafeAssyst, InteractionCreate(interaction): In
                 interaction_token: Some(interaction.token),
                 interaction_id: Some(interaction.id),
                 interaction_attachments,
+                interaction_channels,
                 command_from_install_context: match interaction.context {
                     Some(c) => c == InteractionContextType::PrivateChannel,
                     None => false,
@@ -189,13 +215,28 @@ pub async fn handle(assyst: ThreadSafeAssyst, InteractionCreate(interaction): In
                 resolved_users,
             };
 
-            let ctxt = InteractionCommandParseCtxt::new(CommandCtxt::new(&data), &sorted_incoming_options);
+            let ctxt =
+                InteractionCommandParseCtxt::new(CommandCtxt::new(&data), &sorted_incoming_options, interaction_users);
 
             if let Err(err) = command.execute_interaction_command(ctxt.clone()).await {
                 match err.get_severity() {
                     ErrorSeverity::Low => debug!("{err:?}"),
-                    ErrorSeverity::High => {
-                        let _ = ctxt.cx.reply(format!(":warning: ``{err:#}``")).await;
+                    ErrorSeverity::High => match err {
+                        // if invalid args: report usage to user
+                        ExecutionError::Argument(ref err) => {
+                            let mut invocation = format!("/{}", command.metadata().name);
+                            if let Some((subcommand, _)) = &ctxt.cx.data.interaction_subcommand {
+                                invocation = format!("{invocation} {subcommand}");
+                            }
+
+                            let _ = ctxt
+                                .cx
+                                .reply(format!(":warning: {}", err.usage_message(&invocation)))
+                                .await;
+                        },
+                        _ => {
+                            let _ = ctxt.cx.reply(format!(":warning: ``{err:#}``")).await;
+                        },
                     },
                 }
             } else {
@@ -0,0 +1,20 @@
+use tracing::debug;
+use twilight_model::gateway::payload::incoming::MemberChunk;
+
+use crate::assyst::ThreadSafeAssyst;
+
+pub fn handle(assyst: ThreadSafeAssyst, event: MemberChunk) {
+    for member in &event.members {
+        assyst.rest_cache_handler.set_user(member.user.clone());
+    }
+
+    debug!(
+        "Cached {} members of guild {} from chunk {}/{}",
+        event.members.len(),
+        event.guild_id.get(),
+        event.chunk_index + 1,
+        event.chunk_count
+    );
+
+    assyst.guild_member_requests.handle_member_chunk(event);
+}
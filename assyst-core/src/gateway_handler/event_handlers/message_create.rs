@@ -6,7 +6,7 @@ use tracing::{debug, error};
 use twilight_model::gateway::payload::incoming::MessageCreate;
 
 use super::after_command_execution_success;
-use crate::command::errors::{ExecutionError, TagParseError};
+use crate::command::errors::{ArgumentError, ExecutionError, TagParseError};
 use crate::command::source::Source;
 use crate::command::{CommandCtxt, CommandData, RawMessageParseCtxt};
 use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
@@ -32,11 +32,14 @@ pub async fn handle(assyst: ThreadSafeAssyst, MessageCreate(message): MessageCre
 
     match parse_message_into_command(assyst.clone(), &message, processing_time_start, false).await {
         Ok(Some(result)) => {
+            assyst.rest_cache_handler.set_user(message.author.clone());
+
             let data = CommandData {
                 source: Source::RawMessage,
                 assyst: &assyst,
                 execution_timings: result.execution_timings,
                 calling_prefix: result.calling_prefix,
+                command_name: result.command.metadata().name,
                 message: Some(&message),
                 interaction_subcommand: None,
                 channel_id: message.channel_id,
@@ -45,6 +48,7 @@ pub async fn handle(assyst: ThreadSafeAssyst, MessageCreate(message): MessageCre
                 interaction_token: None,
                 interaction_id: None,
                 interaction_attachments: HashMap::new(),
+                interaction_channels: HashMap::new(),
                 command_from_install_context: false,
                 resolved_messages: None,
                 resolved_users: None,
@@ -55,16 +59,19 @@ pub async fn handle(assyst: ThreadSafeAssyst, MessageCreate(message): MessageCre
                 match err.get_severity() {
                     ErrorSeverity::Low => debug!("{err:?}"),
                     ErrorSeverity::High => match err {
+                        // not really an error: the user asked for the list of flags
+                        ExecutionError::Argument(ArgumentError {
+                            error: TagParseError::FlagsHelp(ref flags),
+                            ..
+                        }) => {
+                            let _ = ctxt.cx.reply(format!("Valid flags:\n```\n{flags}\n```")).await;
+                        },
                         // if invalid args: report usage to user
-                        ExecutionError::Parse(TagParseError::ArgsExhausted(_)) => {
+                        ExecutionError::Argument(ref err) => {
+                            let invocation = format!("{}{}", ctxt.cx.display_prefix(), result.command.metadata().name);
                             let _ = ctxt
                                 .cx
-                                .reply(format!(
-                                    ":warning: `{err}\nUsage: {}{} {}`",
-                                    ctxt.cx.data.calling_prefix,
-                                    result.command.metadata().name,
-                                    result.command.metadata().usage
-                                ))
+                                .reply(format!(":warning: {}", err.usage_message(&invocation)))
                                 .await;
                         },
                         _ => {
@@ -13,11 +13,10 @@ pub async fn handle(assyst: ThreadSafeAssyst, message: MessageDelete) {
     if let Some(reply) = assyst.replies.get_raw_message(message.id.get())
         && let ReplyState::InUse(reply) = reply.state
     {
-        // ignore error
-        _ = assyst
-            .http_client
-            .delete_message(message.channel_id, Id::new(reply.message_id))
-            .await;
+        for id in reply.message_ids() {
+            // ignore error
+            _ = assyst.http_client.delete_message(message.channel_id, Id::new(id)).await;
+        }
 
         assyst.replies.remove_raw_message(message.id.get());
     }
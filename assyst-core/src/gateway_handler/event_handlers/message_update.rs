@@ -10,7 +10,7 @@ use twilight_model::id::Id;
 use twilight_model::util::Timestamp;
 
 use super::after_command_execution_success;
-use crate::command::errors::{ExecutionError, TagParseError};
+use crate::command::errors::{ArgumentError, ExecutionError, TagParseError};
 use crate::command::source::Source;
 use crate::command::{CommandCtxt, CommandData, RawMessageParseCtxt};
 use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity, ParseError, PreParseError};
@@ -37,6 +37,7 @@ pub async fn handle(assyst: ThreadSafeAssyst, event: MessageUpdate) {
                         assyst: &assyst,
                         execution_timings: result.execution_timings,
                         calling_prefix: result.calling_prefix,
+                        command_name: result.command.metadata().name,
                         message: Some(&message),
                         interaction_subcommand: None,
                         channel_id: message.channel_id,
@@ -45,6 +46,7 @@ pub async fn handle(assyst: ThreadSafeAssyst, event: MessageUpdate) {
                         interaction_token: None,
                         interaction_id: None,
                         interaction_attachments: HashMap::new(),
+                        interaction_channels: HashMap::new(),
                         command_from_install_context: false,
                         resolved_messages: None,
                         resolved_users: None,
@@ -55,16 +57,20 @@ pub async fn handle(assyst: ThreadSafeAssyst, event: MessageUpdate) {
                         match err.get_severity() {
                             ErrorSeverity::Low => debug!("{err:?}"),
                             ErrorSeverity::High => match err {
+                                // not really an error: the user asked for the list of flags
+                                ExecutionError::Argument(ArgumentError {
+                                    error: TagParseError::FlagsHelp(ref flags),
+                                    ..
+                                }) => {
+                                    let _ = ctxt.cx.reply(format!("Valid flags:\n```\n{flags}\n```")).await;
+                                },
                                 // if invalid args: report usage to user
-                                ExecutionError::Parse(TagParseError::ArgsExhausted(_)) => {
+                                ExecutionError::Argument(ref err) => {
+                                    let invocation =
+                                        format!("{}{}", ctxt.cx.display_prefix(), result.command.metadata().name);
                                     let _ = ctxt
                                         .cx
-                                        .reply(format!(
-                                            ":warning: `{err}\nUsage: {}{} {}`",
-                                            ctxt.cx.data.calling_prefix,
-                                            result.command.metadata().name,
-                                            result.command.metadata().usage
-                                        ))
+                                        .reply(format!(":warning: {}", err.usage_message(&invocation)))
                                         .await;
                                 },
                                 _ => {
@@ -83,10 +89,9 @@ pub async fn handle(assyst: ThreadSafeAssyst, event: MessageUpdate) {
                         && let ReplyState::InUse(reply) = reply.state
                     {
                         // A previous command invocation was edited to non-command, delete response
-                        _ = assyst
-                            .http_client
-                            .delete_message(message.channel_id, Id::new(reply.message_id))
-                            .await;
+                        for id in reply.message_ids() {
+                            _ = assyst.http_client.delete_message(message.channel_id, Id::new(id)).await;
+                        }
                     }
                 },
                 Err(error) => {
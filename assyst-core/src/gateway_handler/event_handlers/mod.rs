@@ -10,6 +10,7 @@ pub mod guild_create;
 pub mod guild_delete;
 pub mod guild_update;
 pub mod interaction_create;
+pub mod member_chunk;
 pub mod message_create;
 pub mod message_delete;
 pub mod message_update;
@@ -1,7 +1,7 @@
 use twilight_model::gateway::event::{DispatchEvent, GatewayEvent};
 use twilight_model::gateway::payload::incoming::{
     ChannelUpdate, EntitlementCreate, EntitlementDelete, EntitlementUpdate, GuildCreate, GuildDelete, GuildUpdate,
-    InteractionCreate, MessageCreate, MessageDelete, MessageUpdate, Ready,
+    InteractionCreate, MemberChunk, MessageCreate, MessageDelete, MessageUpdate, Ready,
 };
 
 #[derive(Debug)]
@@ -18,6 +18,7 @@ pub enum IncomingEvent {
     EntitlementCreate(EntitlementCreate),
     EntitlementUpdate(EntitlementUpdate),
     EntitlementDelete(EntitlementDelete),
+    MemberChunk(MemberChunk),
 }
 impl TryFrom<GatewayEvent> for IncomingEvent {
     type Error = ();
@@ -37,6 +38,7 @@ impl TryFrom<GatewayEvent> for IncomingEvent {
                 DispatchEvent::EntitlementCreate(e) => Ok(IncomingEvent::EntitlementCreate(e)),
                 DispatchEvent::EntitlementUpdate(e) => Ok(IncomingEvent::EntitlementUpdate(e)),
                 DispatchEvent::EntitlementDelete(e) => Ok(IncomingEvent::EntitlementDelete(e)),
+                DispatchEvent::MemberChunk(chunk) => Ok(IncomingEvent::MemberChunk(chunk)),
                 _ => Err(()),
             },
             _ => Err(()),
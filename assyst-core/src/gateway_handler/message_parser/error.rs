@@ -2,6 +2,8 @@ use std::fmt::Display;
 
 use twilight_model::channel::message::MessageType;
 
+/// Errors which can be classified as either expected noise or a genuine failure, so that callers
+/// can decide whether they are worth logging or reporting.
 pub trait GetErrorSeverity {
     fn get_severity(&self) -> ErrorSeverity;
 }
@@ -19,6 +21,8 @@ pub enum PreParseError {
     UnsupportedMessageKind(MessageType),
     /// A `MESSAGE_UPDATE` was received, but it had no edited timestamp.
     EditedMessageWithNoTimestamp,
+    /// A `MESSAGE_UPDATE` was received for a message whose reply is too old to be updated.
+    EditedMessageTooOld,
     /// Other unknown failure. Unexpected error with high severity.
     Failure(String),
 }
@@ -38,6 +42,7 @@ impl Display for PreParseError {
                 write!(f, "Unsupported message kind ({kind:?})")
             },
             Self::EditedMessageWithNoTimestamp => f.write_str("The message was updated, but not edited."),
+            Self::EditedMessageTooOld => f.write_str("The message was edited, but its reply is too old to be updated."),
             Self::Failure(message) => {
                 write!(f, "Preprocessor failure: {message}")
             },
@@ -71,6 +76,8 @@ impl Display for MetadataCheckInvalidated {
     }
 }
 
+/// An error when parsing a message into a command. Use [`GetErrorSeverity`] to distinguish
+/// messages that should be ignored from real failures.
 #[derive(Debug)]
 pub enum ParseError {
     /// Failure with preprocessing of the message.
@@ -101,6 +108,9 @@ impl From<PreParseError> for ParseError {
 
 #[derive(PartialEq, Eq)]
 pub enum ErrorSeverity {
+    /// An expected outcome, such as a message that isn't a command. Should be silently ignored,
+    /// or logged at debug level at most.
     Low,
+    /// A genuine failure that should be logged or reported to the user.
     High,
 }
@@ -15,6 +15,29 @@ pub struct ParseResult<'a> {
     pub execution_timings: ExecutionTimings,
 }
 
+/// Splits the text after the prefix into the command name and its arguments, or `None` if there is
+/// no command name.
+///
+/// The arguments are sliced from the message as they are, starting directly after the whitespace
+/// character that ends the command name, so that any further leading whitespace, newlines and
+/// trailing whitespace reach arguments such as [`Verbatim`] untouched.
+///
+/// [`Verbatim`]: crate::command::arguments::Verbatim
+fn split_command(command_text: &str) -> Option<(&str, &str)> {
+    let command_text = command_text.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    if command_text.is_empty() {
+        return None;
+    }
+
+    let end = command_text
+        .find(|c: char| c.is_ascii_whitespace())
+        .unwrap_or(command_text.len());
+    let (command, rest) = command_text.split_at(end);
+    let args = rest.strip_prefix(|c: char| c.is_ascii_whitespace()).unwrap_or(rest);
+
+    Some((command, args))
+}
+
 /// Parse any generic Message object into a Command.
 ///
 /// This function takes all steps necessary to split a message into critical command components,
@@ -37,14 +60,25 @@ pub struct ParseResult<'a> {
 ///
 /// **Step 3**: Check if this Message already has an associated reply (if, for example, the
 /// invocation was updated).
-/// These events have a timeout for handling, to prevent editing of very old
-/// messages. If it is expired, prematurely return.
+/// These events have a timeout for handling (see [`crate::replies::REPLY_EDIT_TIMEOUT`]), to
+/// prevent editing of very old messages. If it is expired, prematurely return.
 ///
 /// **Step 4**: Parse the Command from the Message itself. If it fails to parse, prematurely return.
 ///
 /// Once all steps are complete, a Command is returned, ready for execution.
 /// Note that metadata is checked *during* execution (i.e., in the base command's `Command::execute`
-/// implementation, see [`crate::command::check_metadata`])
+/// implementation, see [`crate::command::check_metadata`]), but always before any arguments are
+/// parsed.
+///
+/// Returns `Ok(None)` if the message passed preprocessing but does not name a known command.
+/// Any `Err` carries a severity (see [`GetErrorSeverity`]): [`ErrorSeverity::Low`] errors are
+/// expected outcomes, such as an unprefixed message or one sent by a bot, and should not be
+/// logged above debug level, whereas [`ErrorSeverity::High`] errors are genuine failures (e.g.,
+/// a database error while fetching the prefix) and should be logged.
+///
+/// [`GetErrorSeverity`]: super::error::GetErrorSeverity
+/// [`ErrorSeverity::Low`]: super::error::ErrorSeverity::Low
+/// [`ErrorSeverity::High`]: super::error::ErrorSeverity::High
 pub async fn parse_message_into_command(
     assyst: ThreadSafeAssyst,
     message: &Message,
@@ -63,11 +97,9 @@ pub async fn parse_message_into_command(
     // divide between command name and command arguments)
     let command_text = &message.content[preprocess.prefix.len()..];
 
-    let mut args = command_text.split_ascii_whitespace();
-    let Some(command) = args.next() else {
+    let Some((command, args)) = split_command(command_text) else {
         return Ok(None);
     };
-    let args = args.remainder().unwrap_or("");
     let Some(command) = find_command_by_name(command) else {
         return Ok(None);
     };
@@ -85,3 +117,30 @@ pub async fn parse_message_into_command(
         },
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tokens::Tokens;
+
+    #[test]
+    fn arguments_keep_their_whitespace() {
+        assert_eq!(split_command("note   indented\n"), Some(("note", "  indented\n")));
+        assert_eq!(
+            split_command("note\n\tline one\nline two\n\n"),
+            Some(("note", "\tline one\nline two\n\n"))
+        );
+        assert_eq!(split_command("  ping"), Some(("ping", "")));
+        assert_eq!(split_command(" \n "), None);
+    }
+
+    #[test]
+    fn verbatim_remainder_is_the_untouched_input() {
+        let (_, args) = split_command("tag create name\n  first line  \n\nlast line\n").unwrap();
+        let mut tokens = Tokens::new(args);
+
+        assert_eq!(tokens.next().as_deref(), Some("create"));
+        assert_eq!(tokens.next().as_deref(), Some("name"));
+        assert_eq!(tokens.remainder(), Some("  first line  \n\nlast line\n"));
+    }
+}
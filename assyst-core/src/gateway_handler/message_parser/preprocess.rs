@@ -1,13 +1,13 @@
 use std::time::{Duration, Instant};
 
 use assyst_common::config::CONFIG;
-use assyst_database::model::global_blacklist::GlobalBlacklist;
 use assyst_database::model::prefix::Prefix;
 use twilight_model::channel::message::MessageType;
 use twilight_model::channel::Message;
 
 use crate::assyst::ThreadSafeAssyst;
 use crate::gateway_handler::message_parser::error::PreParseError;
+use crate::replies::{Reply, REPLY_EDIT_TIMEOUT};
 
 /// The resultant values from the preprocessing operation. Used later in parsing and execution.
 pub struct PreprocessResult {
@@ -31,29 +31,31 @@ pub fn message_mention_prefix(content: &str) -> Option<String> {
     }
 }
 
-/// Determine which prefixes apply to this message.
+/// Determine which prefixes apply to this message, returning the one it was invoked with.
 ///
-/// If in DM: no prefix, mention, or prefix override
+/// If in DM: no prefix, additional prefixes, mention, or prefix override
 ///
-/// If in guild: guild prefix, mention, or prefix override
+/// If in guild: guild prefix, additional prefixes, mention, or prefix override
 ///
-/// If prefix override: "normal" prefix ignored
+/// If prefix override: "normal" and additional prefixes ignored
 ///
 /// Prefix precendence:
-/// 1. prefix override (disabling other prefixes)
-/// 2. mention prefix
-/// 3. no prefix/guild prefix (depending on context)
+/// 1. mention prefix
+/// 2. prefix override (disabling the normal prefix)
+/// 3. the longest matching no prefix/guild prefix (depending on context) or additional prefix
 pub async fn parse_prefix(
     assyst: ThreadSafeAssyst,
     message: &Message,
     is_in_dm: bool,
 ) -> Result<String, PreParseError> {
+    if let Some(mention_prefix) = message_mention_prefix(&message.content) {
+        return Ok(mention_prefix);
+    }
+
     let parsed_prefix = if let Some(ref r#override) = CONFIG.dev.prefix_override
         && !r#override.is_empty()
     {
         r#override.clone()
-    } else if let Some(mention_prefix) = message_mention_prefix(&message.content) {
-        mention_prefix
     } else if is_in_dm {
         String::new()
     } else {
@@ -82,22 +84,27 @@ pub async fn parse_prefix(
         }
     };
 
-    if !message.content.starts_with(&parsed_prefix) {
-        return Err(PreParseError::MessageNotPrefixed(parsed_prefix));
+    let uses_override = CONFIG.dev.prefix_override.as_ref().is_some_and(|o| !o.is_empty());
+    let additional = if uses_override {
+        &[][..]
+    } else {
+        &CONFIG.prefix.additional[..]
     };
 
-    Ok(parsed_prefix)
+    std::iter::once(&parsed_prefix)
+        .chain(additional)
+        .filter(|p| message.content.starts_with(p.as_str()))
+        .max_by_key(|p| p.len())
+        .cloned()
+        .ok_or_else(|| PreParseError::MessageNotPrefixed(parsed_prefix.clone()))
 }
 
-/// Checks if a user is globally blacklisted from the bot.
-pub async fn user_globally_blacklisted(assyst: ThreadSafeAssyst, id: u64) -> Result<bool, PreParseError> {
-    let blacklisted = GlobalBlacklist::is_blacklisted(&assyst.database_handler, id).await;
-    match blacklisted {
-        Ok(x) => Ok(x),
-        Err(error) => Err(PreParseError::Failure(format!(
-            "failed to fetch global blacklist: {error}",
-        ))),
-    }
+/// Whether the reply to an edited message was last sent or edited more than [`REPLY_EDIT_TIMEOUT`]
+/// ago, in which case the edit shouldn't re-run the command. This goes by the reply rather than the
+/// message, so that an invocation can keep being edited for as long as its reply is in use. A
+/// message with no cached reply (such as one edited into a command) can always be run.
+fn reply_expired(reply: Option<&Reply>) -> bool {
+    reply.is_some_and(|reply| reply.created.elapsed() > REPLY_EDIT_TIMEOUT)
 }
 
 /// Initial Discord message processing.
@@ -121,10 +128,19 @@ pub async fn preprocess(
         return Err(PreParseError::UserIsBotOrWebhook(Some(message.author.id.get())));
     }
 
+    // the blacklist is kept in memory, so this is cheap enough to check for every message
+    if assyst.user_globally_blacklisted(message.author.id.get()) {
+        return Err(PreParseError::UserGloballyBlacklisted(message.author.id.get()));
+    }
+
     if from_edit && message.edited_timestamp.is_none() {
         return Err(PreParseError::EditedMessageWithNoTimestamp);
     }
 
+    if from_edit && reply_expired(assyst.replies.get_raw_message(message.id.get()).as_ref()) {
+        return Err(PreParseError::EditedMessageTooOld);
+    }
+
     let relevant_message_kinds = &[MessageType::Regular, MessageType::Reply];
     if !relevant_message_kinds.contains(&message.kind) {
         return Err(PreParseError::UnsupportedMessageKind(message.kind));
@@ -137,14 +153,35 @@ pub async fn preprocess(
 
     let prefix_time = prefix_start.elapsed();
 
-    // check blacklist second to prevent large database spam
-    // from all incoming messages
-    if user_globally_blacklisted(assyst.clone(), message.author.id.get()).await? {
-        return Err(PreParseError::UserGloballyBlacklisted(message.author.id.get()));
-    }
-
     Ok(PreprocessResult {
         prefix: parsed_prefix,
         prefixing_determinism_time: prefix_time,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replies::ReplyState;
+
+    fn reply_last_used(ago: Duration) -> Reply {
+        Reply {
+            state: ReplyState::Processing,
+            created: Instant::now().checked_sub(ago).unwrap(),
+        }
+    }
+
+    #[test]
+    fn edits_are_gated_on_the_reply_age() {
+        let recent = reply_last_used(Duration::from_secs(10));
+        let expired = reply_last_used(REPLY_EDIT_TIMEOUT + Duration::from_secs(1));
+
+        assert!(!reply_expired(Some(&recent)));
+        assert!(reply_expired(Some(&expired)));
+    }
+
+    #[test]
+    fn edits_without_a_reply_are_run() {
+        assert!(!reply_expired(None));
+    }
+}
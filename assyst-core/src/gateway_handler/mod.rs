@@ -47,5 +47,8 @@ pub async fn handle_raw_event(context: ThreadSafeAssyst, event: IncomingEvent) {
         IncomingEvent::EntitlementDelete(event) => {
             event_handlers::entitlement_delete::handle(context, event).await;
         },
+        IncomingEvent::MemberChunk(event) => {
+            event_handlers::member_chunk::handle(context, event);
+        },
     }
 }
@@ -1,80 +1,317 @@
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use assyst_common::util::filetype::{get_sig, Type};
+use assyst_common::config::CONFIG;
+use assyst_common::util::filetype::get_sig;
 use tokio::sync::Mutex;
+use tokio::time::sleep;
+use twilight_http::api_error::{ApiError, GeneralApiError};
+use twilight_http::error::ErrorType;
+use twilight_http::request::{Request, TryIntoRequest};
+use twilight_http::Response;
 use twilight_model::channel::message::component::ActionRow;
-use twilight_model::channel::message::{AllowedMentions, Component};
+use twilight_model::channel::message::{AllowedMentions, Component, MessageFlags};
+use twilight_model::channel::Message;
 use twilight_model::http::attachment::Attachment as TwilightAttachment;
 use twilight_model::http::interaction::InteractionResponse;
 use twilight_model::id::Id;
 use twilight_util::builder::InteractionResponseDataBuilder;
 
-use crate::command::messagebuilder::MessageBuilder;
+use crate::command::componentctxt::ComponentCtxtRegister;
+use crate::command::messagebuilder::{Attachment, MessageBuilder};
+use crate::command::source::Source;
 use crate::command::CommandCtxt;
 use crate::replies::{Reply, ReplyInUse, ReplyState};
-use crate::rest::filer::upload_to_filer;
-use crate::rest::NORMAL_DISCORD_UPLOAD_LIMIT_BYTES;
-
-/// Trims a `String` in-place such that it fits in Discord's 2000 character message limit.
-fn trim_content_fits(content: &mut String) {
-    const CODEBLOCK: &str = "```";
-    let codeblocked = content.ends_with(CODEBLOCK);
-    if let Some((truncated_byte_index, _)) =
-        content
-            .char_indices()
-            .nth(if codeblocked { 2000 - CODEBLOCK.len() } else { 2000 })
-    {
-        // If the content length exceeds 2000 characters, truncate it at the 2000th characters' byte
-        // index
-        content.truncate(truncated_byte_index);
-        if codeblocked {
-            *content += CODEBLOCK;
+
+const CODEBLOCK: &str = "```";
+/// The longest codeblock language that is kept when a codeblock is reopened on the next page of a
+/// reply. Anything longer isn't a real language, and carrying it over would eat into the page.
+const MAX_CODEBLOCK_LANGUAGE_LENGTH: usize = 32;
+/// How many times sending a reply is retried after being ratelimited before giving up.
+const MAX_RATELIMIT_RETRIES: usize = 3;
+/// The Discord API error code for a message that doesn't exist.
+const UNKNOWN_MESSAGE_ERROR_CODE: u64 = 10008;
+
+/// Splits content into pages that each fit in Discord's 2000 character message limit, preferring
+/// to split on newlines, then on whitespace. Any codeblock that is cut off by the end of a page is
+/// closed on that page and reopened (with the same language) on the next. At most `max_pages`
+/// pages are returned; anything beyond that is cut off.
+fn paginate_content(content: &str, max_pages: usize) -> Vec<String> {
+    const LIMIT: usize = 2000;
+    // kept free on every page that has to be cut, for closing an open codeblock
+    const CLOSER: &str = "\n```";
+
+    if content.chars().count() <= LIMIT {
+        return vec![content.to_owned()];
+    }
+
+    let mut pages = Vec::new();
+    let mut rest = content;
+    let mut open_codeblock: Option<String> = None;
+
+    while !rest.is_empty() && pages.len() < max_pages.max(1) {
+        let prefix = open_codeblock.clone().unwrap_or_default();
+        let budget = LIMIT.saturating_sub(prefix.chars().count());
+
+        let end = match rest.char_indices().nth(budget.saturating_sub(CLOSER.len()).max(1)) {
+            Some((cut, _)) if rest.chars().count() > budget => break_point(&rest[..cut]).unwrap_or(cut),
+            _ => rest.len(),
+        };
+
+        let chunk = &rest[..end];
+        open_codeblock = codeblock_after(open_codeblock, chunk);
+
+        let mut page = format!("{prefix}{chunk}");
+        if open_codeblock.is_some() && end < rest.len() {
+            page.push_str(if chunk.ends_with('\n') { CODEBLOCK } else { CLOSER });
         }
+
+        pages.push(page);
+        rest = &rest[end..];
     }
+
+    pages
 }
 
-/// Gets the Filer URL for this attachment if it exceeds the guild's upload limit.
-async fn get_filer_url(
-    ctxt: &CommandCtxt<'_>,
-    content: Option<&String>,
-    data: Vec<u8>,
-) -> anyhow::Result<Option<String>> {
-    let filer_url;
-    let filer_formatted;
-
-    if data.len() > NORMAL_DISCORD_UPLOAD_LIMIT_BYTES as usize {
-        let guild_upload_limit = if let Some(guild_id) = ctxt.data.guild_id {
-            ctxt.assyst()
-                .rest_cache_handler
-                .get_guild_upload_limit_bytes(guild_id.get())
-                .await?
+/// Splits content into pages like [`paginate_content`], but returns `None` instead of cutting any
+/// of it off if it needs more than `max_pages` pages.
+pub fn paginate_content_fully(content: &str, max_pages: usize) -> Option<Vec<String>> {
+    let max_pages = max_pages.max(1);
+    let pages = paginate_content(content, max_pages + 1);
+
+    (pages.len() <= max_pages).then_some(pages)
+}
+
+/// Finds where to end a page within `chunk`: after the last newline, or failing that the last
+/// whitespace, so that lines, words and multi-character emoji aren't split if possible.
+fn break_point(chunk: &str) -> Option<usize> {
+    chunk
+        .rfind('\n')
+        .or_else(|| chunk.rfind(char::is_whitespace))
+        .filter(|&i| i > 0)
+        .map(|i| i + chunk[i..].chars().next().map_or(1, char::len_utf8))
+}
+
+/// Works out whether a codeblock is still open at the end of `chunk`, given the line that reopens
+/// the codeblock open at the start of it, if any. If so, returns the line that reopens it, keeping
+/// its language.
+fn codeblock_after(mut open: Option<String>, chunk: &str) -> Option<String> {
+    let mut rest = chunk;
+
+    while let Some(i) = rest.find(CODEBLOCK) {
+        rest = &rest[i + CODEBLOCK.len()..];
+
+        open = if open.is_some() {
+            None
         } else {
-            NORMAL_DISCORD_UPLOAD_LIMIT_BYTES
+            let line = rest.split('\n').next().unwrap_or_default();
+            let language = if line.len() <= MAX_CODEBLOCK_LANGUAGE_LENGTH
+                && line.chars().all(|c| c.is_ascii_alphanumeric() || "+-#".contains(c))
+            {
+                line
+            } else {
+                ""
+            };
+
+            Some(format!("{CODEBLOCK}{language}\n"))
         };
+    }
 
-        if data.len() > guild_upload_limit as usize {
-            filer_url = upload_to_filer(
-                &ctxt.assyst().reqwest_client,
-                data.clone(),
-                get_sig(&data).unwrap_or(Type::PNG).as_mime(),
-            )
-            .await?;
-
-            if let Some(content) = content {
-                filer_formatted = format!("{content} {filer_url}");
-                return Ok(Some(filer_formatted));
+    open
+}
+
+/// A change to one of the messages of a paginated reply, when it is edited to have new pages.
+#[derive(Debug, PartialEq)]
+enum PageUpdate {
+    /// Edit an existing message to hold the page at this index.
+    Edit { message_id: u64, page: usize },
+    /// Send a new message for the page at this index.
+    Create { page: usize },
+    /// Delete a message that is no longer needed.
+    Delete { message_id: u64 },
+}
+
+/// Works out how to turn the messages of a reply (the first message, followed by any further
+/// pages) into `pages` pages: the existing messages are edited in order, new messages are sent for
+/// any extra pages, and any messages left over are deleted.
+fn page_updates(message_ids: &[u64], pages: usize) -> Vec<PageUpdate> {
+    let edits = message_ids
+        .iter()
+        .zip(0..pages)
+        .map(|(&message_id, page)| PageUpdate::Edit { message_id, page });
+    let creates = (message_ids.len()..pages).map(|page| PageUpdate::Create { page });
+    let deletes = message_ids
+        .iter()
+        .skip(pages)
+        .map(|&message_id| PageUpdate::Delete { message_id });
+
+    edits.chain(creates).chain(deletes).collect()
+}
+
+/// How long to wait before retrying, if this error is Discord telling us we are being ratelimited.
+fn ratelimit_retry_after(error: &twilight_http::Error) -> Option<Duration> {
+    if let ErrorType::Response {
+        error: ApiError::Ratelimited(ratelimited),
+        ..
+    } = error.kind()
+    {
+        Some(Duration::from_secs_f64(ratelimited.retry_after))
+    } else {
+        None
+    }
+}
+
+/// Whether this error is Discord telling us the message we tried to access doesn't exist (e.g., it
+/// was deleted).
+fn is_unknown_message(error: &twilight_http::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorType::Response {
+            error: ApiError::General(GeneralApiError {
+                code: UNKNOWN_MESSAGE_ERROR_CODE,
+                ..
+            }),
+            ..
+        }
+    )
+}
+
+/// Sends a reply request, retrying it up to [`MAX_RATELIMIT_RETRIES`] times if we are ratelimited.
+/// Any other error is returned straight away.
+async fn send_with_ratelimit_retry(
+    ctxt: &CommandCtxt<'_>,
+    request: Request,
+) -> Result<Response<Message>, twilight_http::Error> {
+    let mut retries = 0;
+
+    loop {
+        match ctxt.data.assyst.http_client.request(request.clone()).await {
+            Err(e)
+                if retries < MAX_RATELIMIT_RETRIES
+                    && let Some(retry_after) = ratelimit_retry_after(&e) =>
+            {
+                retries += 1;
+                sleep(retry_after).await;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Makes an attachment's filename safe for Discord to preview: any character other than ASCII
+/// letters, digits, `.`, `-` and `_` is replaced, and if the name has no extension, one is inferred
+/// from the content's signature, so that images render inline. A name that is already valid is left
+/// as it is.
+fn attachment_filename(attachment: &Attachment) -> String {
+    let mut name = attachment
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
             }
-            return Ok(Some(filer_url));
+        })
+        .collect::<String>();
+
+    if name.trim_matches('.').is_empty() {
+        name = "attachment".to_owned();
+    }
+
+    let has_extension = name
+        .rsplit_once('.')
+        .is_some_and(|(stem, ext)| !stem.is_empty() && !ext.is_empty());
+
+    if !has_extension && let Some(sig) = get_sig(&attachment.data) {
+        name = format!("{}.{}", name.trim_end_matches('.'), sig.as_str());
+    }
+
+    name
+}
+
+/// Converts attachments into the form twilight sends them in, each with its own ID.
+fn twilight_attachments(attachments: &[Attachment]) -> Vec<TwilightAttachment> {
+    attachments
+        .iter()
+        .enumerate()
+        .map(|(id, a)| TwilightAttachment::from_bytes(attachment_filename(a), a.data.clone(), id as u64))
+        .collect()
+}
+
+/// What to do with the attachments of an interaction response when editing it. Raw message replies
+/// don't need this, since they are recreated rather than edited when attachments come or go (see
+/// [`should_recreate`]).
+#[derive(Debug, PartialEq)]
+enum AttachmentEdit {
+    /// Replace them with the new attachments.
+    Replace,
+    /// Remove the old attachments, which would otherwise be left under a response that is now text
+    /// only (e.g. an image command edited to fail).
+    Clear,
+    /// There were none before and there are none now.
+    Unchanged,
+}
+
+/// Works out what to do with a message's attachments when editing it, given whether it had any
+/// before and whether the new reply has any.
+fn attachment_edit(had_attachments: bool, has_attachments: bool) -> AttachmentEdit {
+    match (had_attachments, has_attachments) {
+        (_, true) => AttachmentEdit::Replace,
+        (true, false) => AttachmentEdit::Clear,
+        (false, false) => AttachmentEdit::Unchanged,
+    }
+}
+
+/// Wraps a reply's components in an action row. No components gives no rows, which also clears
+/// any components when editing.
+fn action_rows(components: Option<Vec<Component>>) -> Vec<Component> {
+    components
+        .map(|components| vec![Component::ActionRow(ActionRow { components })])
+        .unwrap_or_default()
+}
+
+/// Who a reply is allowed to ping: nobody, unless the builder overrides it.
+fn allowed_mentions_for(builder: &MessageBuilder) -> AllowedMentions {
+    builder.allowed_mentions.clone().unwrap_or_default()
+}
+
+/// Registers the context for a reply's components, so that interactions with them can be handled.
+fn register_component_ctxt(ctxt: &CommandCtxt<'_>, component_ctxt: Option<ComponentCtxtRegister>) {
+    if let Some(cx) = component_ctxt {
+        let wrapped = Arc::new(Mutex::new(cx.1.clone()));
+        for cid in cx.0 {
+            ctxt.data.assyst.component_contexts.insert(&cid, &wrapped);
         }
-        return Ok(None);
     }
-    Ok(None)
 }
 
-pub async fn edit(ctxt: &CommandCtxt<'_>, builder: MessageBuilder, reply: ReplyInUse) -> anyhow::Result<()> {
+/// Sends an additional page of a reply that was too long for one message, returning its ID.
+async fn create_page(ctxt: &CommandCtxt<'_>, content: &str) -> anyhow::Result<u64> {
     let allowed_mentions = AllowedMentions::default();
 
+    let message = ctxt
+        .data
+        .assyst
+        .http_client
+        .create_message(ctxt.data.channel_id)
+        .allowed_mentions(Some(&allowed_mentions))
+        .content(content)
+        .await?
+        .model()
+        .await?;
+
+    Ok(message.id.get())
+}
+
+pub async fn edit(
+    ctxt: &CommandCtxt<'_>,
+    builder: MessageBuilder,
+    original_content: String,
+    reply: ReplyInUse,
+) -> anyhow::Result<()> {
+    let allowed_mentions = allowed_mentions_for(&builder);
+
     let mut message = ctxt
         .data
         .assyst
@@ -82,40 +319,100 @@ pub async fn edit(ctxt: &CommandCtxt<'_>, builder: MessageBuilder, reply: ReplyI
         .update_message(ctxt.data.channel_id, Id::new(reply.message_id))
         .allowed_mentions(Some(&allowed_mentions));
 
-    let mut content_clone = builder.content.clone();
+    let pages = builder
+        .content
+        .as_deref()
+        .map(|c| paginate_content(c, CONFIG.max_reply_pages))
+        .unwrap_or_default();
 
-    if builder.attachment.is_none() && builder.content.as_ref().map_or(true, |x| x.trim().is_empty()) {
+    if builder.attachments.is_empty() && builder.content.as_ref().map_or(true, |x| x.trim().is_empty()) {
         message = message.content(Some("[Empty Response]"));
-    } else if let Some(content) = &mut content_clone {
-        trim_content_fits(content);
+    } else if let Some(content) = pages.first() {
         message = message.content(Some(content));
     }
 
-    let attachments;
-    let url;
-    if let Some(attachment) = builder.attachment {
-        if let Some(found_url) = get_filer_url(ctxt, builder.content.as_ref(), attachment.data.clone()).await? {
-            url = found_url;
-            message = message.content(Some(&url));
-        } else {
-            attachments = [TwilightAttachment::from_bytes(
-                attachment.name.into(),
-                attachment.data,
-                0,
-            )];
-            message = message.attachments(&attachments);
-            if builder.content.is_none() {
-                message = message.content(Some(""));
-            }
-        };
+    // a reply is only edited if it had attachments before and still does, or had none and still
+    // doesn't (otherwise it is recreated, see `should_recreate`), so there are never stale
+    // attachments to clear here
+    let attachments = twilight_attachments(&builder.attachments);
+    if !attachments.is_empty() {
+        message = message.attachments(&attachments);
+        if builder.content.is_none() {
+            message = message.content(Some(""));
+        }
+    }
+
+    let cs = action_rows(builder.components.clone());
+    message = message.components(Some(&cs));
+
+    match send_with_ratelimit_retry(ctxt, message.try_into_request()?).await {
+        Ok(_) => {},
+        Err(e) if is_unknown_message(&e) => {
+            // the reply was deleted, so forget about it and send a new one instead
+            ctxt.data
+                .assyst
+                .replies
+                .remove_raw_message(ctxt.data.message.unwrap().id.get());
+            return recreate(ctxt, builder, original_content, reply).await;
+        },
+        Err(e) => return Err(e.into()),
     }
 
-    message.await?;
+    register_component_ctxt(ctxt, builder.component_ctxt);
+
+    // reconcile the remaining pages with the ones from the previous invocation: edit the pages we
+    // already have, send any new ones, and delete any that are no longer needed. The first message
+    // was already edited above, along with the rest of the reply
+    let message_ids = reply.message_ids().collect::<Vec<_>>();
+    let mut extra_message_ids = Vec::new();
+    for update in page_updates(&message_ids, pages.len().max(1)).into_iter().skip(1) {
+        match update {
+            PageUpdate::Edit { message_id, page } => {
+                ctxt.data
+                    .assyst
+                    .http_client
+                    .update_message(ctxt.data.channel_id, Id::new(message_id))
+                    .content(Some(&pages[page]))
+                    .keep_attachment_ids(&[])
+                    .await?;
+                extra_message_ids.push(message_id);
+            },
+            PageUpdate::Create { page } => extra_message_ids.push(create_page(ctxt, &pages[page]).await?),
+            PageUpdate::Delete { message_id } => {
+                // ignore error
+                _ = ctxt
+                    .data
+                    .assyst
+                    .http_client
+                    .delete_message(ctxt.data.channel_id, Id::new(message_id))
+                    .await;
+            },
+        }
+    }
+
+    ctxt.data.assyst.replies.insert_raw_message(
+        ctxt.data.message.unwrap().id.get(),
+        Reply {
+            state: ReplyState::InUse(ReplyInUse {
+                message_id: reply.message_id,
+                extra_message_ids,
+                content: builder.content.unwrap_or_default(),
+                original_content,
+                has_attachments,
+            }),
+            created: Instant::now(),
+        },
+    );
+
     Ok(())
 }
 
-async fn create_message(ctxt: &CommandCtxt<'_>, builder: MessageBuilder) -> anyhow::Result<()> {
-    let allowed_mentions = AllowedMentions::default();
+async fn create_message(
+    ctxt: &CommandCtxt<'_>,
+    builder: MessageBuilder,
+    original_content: String,
+) -> anyhow::Result<()> {
+    let allowed_mentions = allowed_mentions_for(&builder);
 
     let mut message = ctxt
         .data
@@ -136,64 +433,107 @@ async fn create_message(ctxt: &CommandCtxt<'_>, builder: MessageBuilder) -> anyh
         message = message.reply(source_message.id);
     }
 
-    let mut content_clone = builder.content.clone();
+    let pages = builder
+        .content
+        .as_deref()
+        .map(|c| paginate_content(c, CONFIG.max_reply_pages))
+        .unwrap_or_default();
 
-    if builder.attachment.is_none() && builder.content.as_ref().map_or(true, |x| x.trim().is_empty()) {
+    if builder.attachments.is_empty() && builder.content.as_ref().map_or(true, |x| x.trim().is_empty()) {
         message = message.content("[Empty Response]");
-    } else if let Some(content) = &mut content_clone {
-        trim_content_fits(content);
+    } else if let Some(content) = pages.first() {
         message = message.content(content);
     }
 
-    let attachments;
-    let url;
-    if let Some(attachment) = builder.attachment {
-        if let Some(found_url) = get_filer_url(ctxt, builder.content.as_ref(), attachment.data.clone()).await? {
-            url = found_url;
-            message = message.content(&url);
-        } else {
-            attachments = [TwilightAttachment::from_bytes(
-                attachment.name.into(),
-                attachment.data,
-                0,
-            )];
-            message = message.attachments(&attachments);
-            if builder.content.is_none() {
-                message = message.content("");
-            }
-        };
+    let attachments = twilight_attachments(&builder.attachments);
+    if !attachments.is_empty() {
+        message = message.attachments(&attachments);
+        if builder.content.is_none() {
+            message = message.content("");
+        }
     }
 
-    let cs;
-    if let Some(components) = builder.components {
-        cs = vec![Component::ActionRow(ActionRow { components })];
+    let cs = action_rows(builder.components);
+    if !cs.is_empty() {
         message = message.components(&cs);
     }
 
-    let reply = message.await?.model().await?;
+    let reply = send_with_ratelimit_retry(ctxt, message.try_into_request()?)
+        .await?
+        .model()
+        .await?;
+
+    register_component_ctxt(ctxt, builder.component_ctxt);
+
+    // the reply is stored even if sending a later page fails, so that whatever was sent can still
+    // be edited or cleaned up
+    let mut result = Ok(());
+    let mut extra_message_ids = Vec::new();
+    for page in pages.iter().skip(1) {
+        match create_page(ctxt, page).await {
+            Ok(id) => extra_message_ids.push(id),
+            Err(e) => {
+                result = Err(e);
+                break;
+            },
+        }
+    }
 
     ctxt.data.assyst.replies.insert_raw_message(
         ctxt.data.message.unwrap().id.get(),
         Reply {
             state: ReplyState::InUse(ReplyInUse {
                 message_id: reply.id.get(),
-                _has_attachments: !reply.attachments.is_empty(),
+                extra_message_ids,
+                content: builder.content.unwrap_or_default(),
+                original_content,
+                has_attachments: !reply.attachments.is_empty(),
             }),
-            _created: Instant::now(),
+            created: Instant::now(),
         },
     );
 
-    if let Some(cx) = builder.component_ctxt {
-        let wrapped = Arc::new(Mutex::new(cx.1.clone()));
-        for cid in cx.0 {
-            ctxt.data.assyst.component_contexts.insert(&cid, &wrapped);
-        }
-    }
+    result
+}
 
-    Ok(())
+/// Appends `text` to the content of the current reply (if there is one). Returns the new content,
+/// which is what the reply then stores as its original content, along with its pages, or `None` for
+/// the pages if it no longer fits in `max_pages` messages (and is sent as a file instead).
+pub fn appended(current: Option<String>, text: &str, max_pages: usize) -> (String, Option<Vec<String>>) {
+    let content = match current {
+        Some(current) => current + text,
+        None => text.to_owned(),
+    };
+    let pages = paginate_content_fully(&content, max_pages);
+
+    (content, pages)
+}
+
+/// Gets the content of the current reply to this command, as the command sent it (i.e., before any
+/// output middleware ran), if there is one.
+pub fn current_reply_content(ctxt: &CommandCtxt<'_>) -> Option<String> {
+    match ctxt.data.source {
+        Source::RawMessage => ctxt
+            .data
+            .assyst
+            .replies
+            .get_raw_message(ctxt.data.message.unwrap().id.get())
+            .and_then(|r| r.in_use())
+            .map(|r| r.original_content),
+        Source::Interaction => ctxt
+            .data
+            .assyst
+            .replies
+            .get_interaction_command(ctxt.data.interaction_id.unwrap().get())
+            .map(|r| r.original_content),
+    }
 }
 
-pub async fn reply_raw_message(ctxt: &CommandCtxt<'_>, builder: MessageBuilder) -> anyhow::Result<()> {
+pub async fn reply_raw_message(
+    ctxt: &CommandCtxt<'_>,
+    builder: MessageBuilder,
+    original_content: String,
+) -> anyhow::Result<()> {
     let reply_in_use = ctxt
         .data
         .assyst
@@ -201,35 +541,91 @@ pub async fn reply_raw_message(ctxt: &CommandCtxt<'_>, builder: MessageBuilder)
         .get_raw_message(ctxt.data.message.unwrap().id.get())
         .and_then(|r| r.in_use());
 
-    if let Some(reply_in_use) = reply_in_use {
-        edit(ctxt, builder, reply_in_use).await
-    } else {
-        create_message(ctxt, builder).await
+    match reply_in_use {
+        Some(reply_in_use) if should_recreate(&reply_in_use, &builder) => {
+            recreate(ctxt, builder, original_content, reply_in_use).await
+        },
+        Some(reply_in_use) => edit(ctxt, builder, original_content, reply_in_use).await,
+        None => create_message(ctxt, builder, original_content).await,
     }
 }
 
-pub async fn reply_interaction_command(ctxt: &CommandCtxt<'_>, builder: MessageBuilder) -> anyhow::Result<()> {
-    let reply_in_use = ctxt
+/// Whether to delete the previous reply and send a new one, rather than editing it.
+///
+/// Editing a reply from text to an attachment (or the other way around) doesn't always render
+/// cleanly on Discord's side: the old content can linger or the embed preview can be stale, so a
+/// reply is only edited in place if it either had attachments before and still does, or had none
+/// before and still doesn't.
+fn should_recreate(reply: &ReplyInUse, builder: &MessageBuilder) -> bool {
+    reply.has_attachments != !builder.attachments.is_empty()
+}
+
+/// Deletes every message of the previous reply and sends a new one in its place. Creating the new
+/// message replaces the cached reply, so later edits go to it.
+///
+/// Deletion errors are ignored: if the old reply is already gone, or we can no longer access it
+/// (e.g., our permissions in the channel changed), there is nothing more to clean up, and sending
+/// a new reply is still the best outcome.
+async fn recreate(
+    ctxt: &CommandCtxt<'_>,
+    builder: MessageBuilder,
+    original_content: String,
+    reply: ReplyInUse,
+) -> anyhow::Result<()> {
+    for id in reply.message_ids() {
+        // ignore error
+        _ = ctxt
+            .data
+            .assyst
+            .http_client
+            .delete_message(ctxt.data.channel_id, Id::new(id))
+            .await;
+    }
+
+    create_message(ctxt, builder, original_content).await
+}
+
+pub async fn reply_interaction_command(
+    ctxt: &CommandCtxt<'_>,
+    builder: MessageBuilder,
+    original_content: String,
+) -> anyhow::Result<()> {
+    let previous = ctxt
         .data
         .assyst
         .replies
-        .get_interaction_command(ctxt.data.interaction_id.unwrap().get())
-        .is_some();
+        .get_interaction_command(ctxt.data.interaction_id.unwrap().get());
+    let reply_in_use = previous.is_some();
+
+    if builder.ephemeral && reply_in_use {
+        // an existing response can't be made ephemeral, so send this as an ephemeral followup
+        // instead, leaving the cached response as the one any later replies edit
+        return followup_interaction_command(ctxt, builder).await.map(|_| ());
+    }
 
     let c = ctxt.assyst().interaction_client();
     let mut response_data = InteractionResponseDataBuilder::new();
-    if let Some(ref a) = builder.attachment {
-        let attachments = [TwilightAttachment::from_bytes(a.name.clone().into(), a.data.clone(), 0)];
-        response_data = response_data.attachments(attachments);
+    if !builder.attachments.is_empty() {
+        response_data = response_data.attachments(twilight_attachments(&builder.attachments));
         response_data = response_data.content("");
     }
 
-    response_data = response_data.allowed_mentions(AllowedMentions::default());
+    let allowed_mentions = allowed_mentions_for(&builder);
+    response_data = response_data.allowed_mentions(allowed_mentions.clone());
+
+    let cs = action_rows(builder.components);
+    if !cs.is_empty() {
+        response_data = response_data.components(cs.clone());
+    }
 
     if let Some(c) = builder.content.clone() {
         response_data = response_data.content(c);
     }
 
+    if builder.ephemeral {
+        response_data = response_data.flags(MessageFlags::EPHEMERAL);
+    }
+
     let response = InteractionResponse {
         kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
         data: Some(response_data.build()),
@@ -237,21 +633,21 @@ pub async fn reply_interaction_command(ctxt: &CommandCtxt<'_>, builder: MessageB
 
     if reply_in_use {
         let token = ctxt.data.interaction_token.clone().unwrap();
-        let mut update = c.update_response(&token);
-        let attachments;
-
-        if let Some(ref a) = builder.attachment {
-            attachments = [TwilightAttachment::from_bytes(a.name.clone().into(), a.data.clone(), 0)];
-            update = update.attachments(&attachments);
+        let mut update = c.update_response(&token).allowed_mentions(Some(&allowed_mentions));
+        let attachments = twilight_attachments(&builder.attachments);
+        let had_attachments = previous.is_some_and(|r| r.has_attachments);
+
+        match attachment_edit(had_attachments, !attachments.is_empty()) {
+            AttachmentEdit::Replace => update = update.attachments(&attachments),
+            AttachmentEdit::Clear => update = update.keep_attachment_ids(&[]),
+            AttachmentEdit::Unchanged => {},
         }
 
         if let Some(ref c) = builder.content {
             update = update.content(Some(c));
         }
 
-        if let Some(ref components) = builder.components {
-            update = update.components(Some(components));
-        }
+        update = update.components(Some(&cs));
 
         update.await?;
     } else {
@@ -261,11 +657,326 @@ pub async fn reply_interaction_command(ctxt: &CommandCtxt<'_>, builder: MessageB
             &response,
         )
         .await?;
+    }
 
-        ctxt.assyst()
-            .replies
-            .insert_interaction_command(ctxt.data.interaction_id.unwrap().get());
+    register_component_ctxt(ctxt, builder.component_ctxt);
+
+    ctxt.assyst().replies.insert_interaction_command(
+        ctxt.data.interaction_id.unwrap().get(),
+        builder.content.unwrap_or_default(),
+        original_content,
+        !builder.attachments.is_empty(),
+    );
+
+    Ok(())
+}
+
+/// Makes the followups holding further pages of an interaction command's response match `pages`:
+/// the existing ones are edited, new ones are sent for any extra pages, and any left over are
+/// deleted. The response itself must already have been sent.
+pub async fn update_interaction_pages(ctxt: &CommandCtxt<'_>, pages: &[String]) -> anyhow::Result<()> {
+    let interaction_id = ctxt.data.interaction_id.unwrap().get();
+    let token = ctxt.data.interaction_token.clone().unwrap();
+    let c = ctxt.assyst().interaction_client();
+    let allowed_mentions = AllowedMentions::default();
+
+    let previous = ctxt
+        .assyst()
+        .replies
+        .get_interaction_command(interaction_id)
+        .map(|r| r.extra_message_ids)
+        .unwrap_or_default();
+
+    let mut extra_message_ids = Vec::new();
+    for update in page_updates(&previous, pages.len()) {
+        match update {
+            PageUpdate::Edit { message_id, page } => {
+                // pages are text only, so nothing should be left attached to a reused followup
+                c.update_followup(&token, Id::new(message_id))
+                    .content(Some(&pages[page]))
+                    .keep_attachment_ids(&[])
+                    .await?;
+                extra_message_ids.push(message_id);
+            },
+            PageUpdate::Create { page } => {
+                let message = c
+                    .create_followup(&token)
+                    .allowed_mentions(Some(&allowed_mentions))
+                    .content(&pages[page])
+                    .await?
+                    .model()
+                    .await?;
+                extra_message_ids.push(message.id.get());
+            },
+            PageUpdate::Delete { message_id } => {
+                // ignore error
+                _ = c.delete_followup(&token, Id::new(message_id)).await;
+            },
+        }
     }
 
+    ctxt.assyst()
+        .replies
+        .set_interaction_extra_messages(interaction_id, extra_message_ids);
+
     Ok(())
 }
+
+/// Sends a further message for an interaction command that has already been responded to,
+/// returning the new message's ID. Unlike [`reply_interaction_command`], this never edits the
+/// existing response. The message is recorded alongside the response in the reply cache.
+pub async fn followup_interaction_command(ctxt: &CommandCtxt<'_>, builder: MessageBuilder) -> anyhow::Result<u64> {
+    let interaction_id = ctxt.data.interaction_id.unwrap().get();
+    let token = ctxt.data.interaction_token.clone().unwrap();
+    let c = ctxt.assyst().interaction_client();
+    let allowed_mentions = allowed_mentions_for(&builder);
+    let mut followup = c.create_followup(&token).allowed_mentions(Some(&allowed_mentions));
+
+    if builder.ephemeral {
+        followup = followup.flags(MessageFlags::EPHEMERAL);
+    }
+
+    let attachments = twilight_attachments(&builder.attachments);
+    if !attachments.is_empty() {
+        followup = followup.attachments(&attachments);
+    }
+
+    if let Some(ref content) = builder.content {
+        followup = followup.content(content);
+    }
+
+    let cs = action_rows(builder.components);
+    if !cs.is_empty() {
+        followup = followup.components(&cs);
+    }
+
+    let message = followup.await?.model().await?;
+
+    register_component_ctxt(ctxt, builder.component_ctxt);
+    ctxt.assyst()
+        .replies
+        .add_interaction_followup(interaction_id, message.id.get());
+
+    Ok(message.id.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_pages_fit(pages: &[String]) {
+        for page in pages {
+            assert!(
+                page.chars().count() <= 2000,
+                "page is {} characters",
+                page.chars().count()
+            );
+        }
+    }
+
+    /// The allowed mentions sent in the body of a twilight request.
+    fn sent_allowed_mentions(request: Request) -> AllowedMentions {
+        let body: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+        serde_json::from_value(body["allowed_mentions"].clone()).unwrap()
+    }
+
+    #[test]
+    fn allowed_mentions_override_is_sent() {
+        let client = twilight_http::Client::new(String::new());
+        let ping_user: AllowedMentions = serde_json::from_value(serde_json::json!({
+            "parse": [],
+            "users": ["5"],
+            "replied_user": true,
+        }))
+        .unwrap();
+
+        let builder = MessageBuilder {
+            content: Some("<@5> reminder".to_owned()),
+            allowed_mentions: Some(ping_user.clone()),
+            ..Default::default()
+        };
+        let mentions = allowed_mentions_for(&builder);
+
+        let create = client
+            .create_message(Id::new(1))
+            .allowed_mentions(Some(&mentions))
+            .try_into_request()
+            .unwrap();
+        assert_eq!(sent_allowed_mentions(create), ping_user);
+
+        let update = client
+            .update_message(Id::new(1), Id::new(2))
+            .allowed_mentions(Some(&mentions))
+            .try_into_request()
+            .unwrap();
+        assert_eq!(sent_allowed_mentions(update), ping_user);
+    }
+
+    #[test]
+    fn nobody_is_pinged_by_default() {
+        let client = twilight_http::Client::new(String::new());
+        let mentions = allowed_mentions_for(&MessageBuilder::from("<@5> hello"));
+
+        let create = client
+            .create_message(Id::new(1))
+            .allowed_mentions(Some(&mentions))
+            .try_into_request()
+            .unwrap();
+        let sent = sent_allowed_mentions(create);
+        assert_eq!(sent, AllowedMentions::default());
+        assert!(sent.parse.is_empty() && sent.users.is_empty() && !sent.replied_user);
+    }
+
+    #[test]
+    fn short_content_is_one_page() {
+        assert_eq!(paginate_content("hello", 5), vec!["hello".to_owned()]);
+    }
+
+    #[test]
+    fn codeblock_split_across_pages_is_closed_and_reopened() {
+        let content = format!("```rust\n{}```", "let x = 1;\n".repeat(300));
+        let pages = paginate_content(&content, 5);
+
+        assert_eq!(pages.len(), 2);
+        assert_pages_fit(&pages);
+        assert!(pages[0].ends_with("\n```"));
+        assert!(pages[1].starts_with("```rust\n"));
+        for page in &pages {
+            assert_eq!(
+                page.matches(CODEBLOCK).count() % 2,
+                0,
+                "unbalanced codeblock in {page:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn long_codeblock_language_line_is_not_carried_over() {
+        let content = format!("```{}", "a".repeat(3000));
+        let pages = paginate_content(&content, 5);
+
+        assert!(pages.len() > 1);
+        assert_pages_fit(&pages);
+        assert!(pages[1].starts_with("```\n"));
+    }
+
+    #[test]
+    fn multi_byte_characters_at_the_cut_are_not_split() {
+        for content in [
+            "é".repeat(2500),
+            "👍".repeat(2500),
+            format!("{}{}", "a".repeat(1995), "🎉".repeat(10)),
+        ] {
+            let pages = paginate_content(&content, 5);
+
+            assert!(pages.len() > 1);
+            assert_pages_fit(&pages);
+            assert_eq!(pages.concat(), content);
+        }
+    }
+
+    #[test]
+    fn appended_content_rolls_over_into_further_pages() {
+        // 900 characters
+        let text = "line of output\n".repeat(60);
+
+        let mut current = None;
+        let mut page_counts = Vec::new();
+        for _ in 0..3 {
+            let (content, pages) = appended(current, &text, 5);
+            let pages = pages.unwrap();
+            assert_eq!(pages.concat(), content);
+
+            page_counts.push(pages.len());
+            // stored as the reply's original content, which the next append builds on
+            current = Some(content);
+        }
+
+        assert_eq!(page_counts, [1, 1, 2]);
+        assert_eq!(current.unwrap(), text.repeat(3));
+    }
+
+    #[test]
+    fn appending_beyond_the_page_limit_gives_no_pages() {
+        let text = "a".repeat(1500);
+
+        let (content, pages) = appended(None, &text, 1);
+        assert_eq!(pages.map(|p| p.len()), Some(1));
+
+        let (content, pages) = appended(Some(content), &text, 1);
+        assert_eq!(pages, None);
+        // nothing is cut off, since it is sent as a file instead
+        assert_eq!(content, text.repeat(2));
+    }
+
+    #[test]
+    fn fewer_pages_edit_the_first_message_and_delete_the_rest() {
+        let updates = page_updates(&[1, 2, 3], 1);
+
+        assert_eq!(
+            updates,
+            vec![
+                PageUpdate::Edit { message_id: 1, page: 0 },
+                PageUpdate::Delete { message_id: 2 },
+                PageUpdate::Delete { message_id: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn more_pages_edit_existing_messages_and_send_new_ones() {
+        let updates = page_updates(&[1, 2], 4);
+
+        assert_eq!(
+            updates,
+            vec![
+                PageUpdate::Edit { message_id: 1, page: 0 },
+                PageUpdate::Edit { message_id: 2, page: 1 },
+                PageUpdate::Create { page: 2 },
+                PageUpdate::Create { page: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn content_beyond_the_page_limit_is_cut_off() {
+        let pages = paginate_content(&"word ".repeat(2000), 2);
+
+        assert_eq!(pages.len(), 2);
+        assert_pages_fit(&pages);
+    }
+
+    #[test]
+    fn raw_replies_are_recreated_when_attachments_come_or_go() {
+        let reply = |has_attachments| ReplyInUse {
+            message_id: 1,
+            extra_message_ids: Vec::new(),
+            content: String::new(),
+            original_content: String::new(),
+            has_attachments,
+        };
+        let text = MessageBuilder::from("text");
+        let image = MessageBuilder::from(Attachment {
+            name: "image.png".into(),
+            data: vec![0; 4],
+            output: None,
+        });
+
+        assert!(should_recreate(&reply(true), &text));
+        assert!(should_recreate(&reply(false), &image));
+        assert!(!should_recreate(&reply(true), &image));
+        assert!(!should_recreate(&reply(false), &text));
+    }
+
+    #[test]
+    fn editing_an_attachment_reply_to_text_clears_the_attachment() {
+        assert_eq!(attachment_edit(true, false), AttachmentEdit::Clear);
+    }
+
+    #[test]
+    fn attachments_are_only_touched_when_needed() {
+        assert_eq!(attachment_edit(true, true), AttachmentEdit::Replace);
+        assert_eq!(attachment_edit(false, true), AttachmentEdit::Replace);
+        assert_eq!(attachment_edit(false, false), AttachmentEdit::Unchanged);
+    }
+}
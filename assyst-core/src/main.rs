@@ -24,6 +24,7 @@ use gateway_handler::handle_raw_event;
 use gateway_handler::incoming_event::IncomingEvent;
 use rest::patreon::init_patreon_refresh;
 use task::tasks::refresh_entitlements::refresh_entitlements;
+use task::tasks::refresh_global_blacklist::refresh_global_blacklist;
 use task::tasks::reminders::handle_reminders;
 use tokio::spawn;
 use tracing::{info /* trace */};
@@ -32,7 +33,10 @@ use twilight_model::id::marker::WebhookMarker;
 use twilight_model::id::Id;
 
 use crate::assyst::{Assyst, ThreadSafeAssyst};
+use crate::command::middleware::{FilerUploadFallback, ImageOutputTransform, TextAttachmentOverflow};
 use crate::task::tasks::get_premium_users::get_premium_users;
+use crate::task::tasks::log_rate_trackers::log_rate_trackers;
+use crate::task::tasks::prune_replies::prune_replies;
 use crate::task::tasks::top_gg_stats::post_top_gg_stats;
 use crate::task::Task;
 
@@ -46,6 +50,7 @@ mod persistent_cache_handler;
 mod replies;
 mod rest;
 mod task;
+mod user_resolver;
 
 // Jemallocator is probably unnecessary for the average instance,
 // but when handling hundreds of events per second the performance improvement
@@ -107,6 +112,15 @@ async fn main() {
     )
     .await;
 
+    assyst.register_output_middleware(Arc::new(TextAttachmentOverflow));
+    info!("Registered text attachment overflow output middleware");
+
+    assyst.register_output_middleware(Arc::new(ImageOutputTransform));
+    info!("Registered image output transform output middleware");
+
+    assyst.register_output_middleware(Arc::new(FilerUploadFallback));
+    info!("Registered Filer upload fallback output middleware");
+
     assyst.register_task(Task::new(
         assyst.clone(),
         // 10 mins
@@ -150,6 +164,32 @@ async fn main() {
         info!("Entitlement refreshing disabled in config.dev.disable_entitlement_fetching: not registering task");
     }
 
+    assyst.register_task(Task::new_delayed(
+        assyst.clone(),
+        // 5 mins
+        Duration::from_secs(60 * 5),
+        Duration::from_secs(60 * 5),
+        function_task_callback!(log_rate_trackers),
+    ));
+    info!("Registered rate tracker logging task");
+
+    assyst.register_task(Task::new(
+        assyst.clone(),
+        // 1 min
+        Duration::from_secs(60),
+        function_task_callback!(prune_replies),
+    ));
+    info!("Registered reply pruning task");
+
+    assyst.register_task(Task::new_delayed(
+        assyst.clone(),
+        // 5 mins
+        Duration::from_secs(60 * 5),
+        Duration::from_secs(60 * 5),
+        function_task_callback!(refresh_global_blacklist),
+    ));
+    info!("Registered global blacklist refreshing task");
+
     info!("Starting assyst-webserver");
     assyst_webserver::run(
         assyst.database_handler.clone(),
@@ -210,7 +250,8 @@ async fn main() {
                         | EventTypeFlags::CHANNEL_UPDATE
                         | EventTypeFlags::ENTITLEMENT_CREATE
                         | EventTypeFlags::ENTITLEMENT_DELETE
-                        | EventTypeFlags::ENTITLEMENT_UPDATE,
+                        | EventTypeFlags::ENTITLEMENT_UPDATE
+                        | EventTypeFlags::MEMBER_CHUNK,
                 )
                 .ok()
                 .flatten();
@@ -2,12 +2,30 @@ use std::time::{Duration, Instant};
 
 use moka::sync::Cache;
 
-#[derive(Copy, Clone, Debug)]
+/// How long after a command is invoked that editing it will re-run it. Replies are kept for this
+/// long after they were last used.
+pub const REPLY_EDIT_TIMEOUT: Duration = Duration::from_secs(60 * 5);
+
+#[derive(Clone, Debug)]
 pub struct ReplyInUse {
     /// The message ID of this reply
     pub message_id: u64,
+    /// The message IDs of any further pages, if the reply was too long for one message.
+    pub extra_message_ids: Vec<u64>,
+    /// The full content of the reply, across all pages.
+    pub content: String,
+    /// The content the command replied with, before any output middleware ran. Unlike `content`,
+    /// this isn't replaced when the reply is changed on its way out (e.g., when overflowing content
+    /// is sent as a file), so it is what appending to the reply builds on.
+    pub original_content: String,
     /// Whether the reply has any attachments.
-    pub _has_attachments: bool,
+    pub has_attachments: bool,
+}
+impl ReplyInUse {
+    /// All messages making up this reply: the first one, followed by any further pages.
+    pub fn message_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        std::iter::once(self.message_id).chain(self.extra_message_ids.iter().copied())
+    }
 }
 
 #[allow(dead_code)]
@@ -20,28 +38,46 @@ pub enum ReplyState {
 #[derive(Debug, Clone)]
 pub struct Reply {
     pub state: ReplyState,
-    pub _created: Instant,
+    /// When this reply was last sent or edited.
+    pub created: Instant,
 }
 
 impl Reply {
     pub fn in_use(&self) -> Option<ReplyInUse> {
-        if let ReplyState::InUse(reply) = self.state {
-            Some(reply)
+        if let ReplyState::InUse(ref reply) = self.state {
+            Some(reply.clone())
         } else {
             None
         }
     }
 }
 
+/// The response to an interaction command, along with any followup messages sent after it.
+#[derive(Clone, Debug)]
+pub struct InteractionReply {
+    /// The content of the response.
+    pub content: String,
+    /// The content the command responded with, before any output middleware ran, including any
+    /// further pages sent as followups (see [`ReplyInUse::original_content`]).
+    pub original_content: String,
+    /// The message IDs of any followup messages, in the order they were sent.
+    pub followup_message_ids: Vec<u64>,
+    /// The message IDs of the followups holding further pages of the response, for content that
+    /// was appended past what fits in one message.
+    pub extra_message_ids: Vec<u64>,
+    /// Whether the response has any attachments.
+    pub has_attachments: bool,
+}
+
 /// Cached command replies. First cache is for "raw" messages, second is for interaction messages.
-pub struct Replies(Cache<u64, Reply>, Cache<u64, ()>);
+pub struct Replies(Cache<u64, Reply>, Cache<u64, InteractionReply>);
 
 impl Replies {
     pub fn new() -> Self {
         Self(
             Cache::builder()
                 .max_capacity(1000)
-                .time_to_idle(Duration::from_secs(60 * 5))
+                .time_to_idle(REPLY_EDIT_TIMEOUT)
                 .build(),
             Cache::builder()
                 .max_capacity(1000)
@@ -62,11 +98,69 @@ impl Replies {
         self.0.get(&id)
     }
 
-    pub fn insert_interaction_command(&self, id: u64) {
-        self.1.insert(id, ());
+    /// Removes raw message replies that haven't been sent or edited in `max_age`, returning how
+    /// many were removed. The cache evicts idle entries by itself, but only lazily, so this keeps
+    /// it from holding onto replies that can no longer be edited.
+    pub fn prune_raw_messages(&self, max_age: Duration) -> usize {
+        let expired = self
+            .0
+            .iter()
+            .filter(|(_, reply)| reply.created.elapsed() > max_age)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        for id in &expired {
+            self.0.invalidate(id);
+        }
+        self.0.run_pending_tasks();
+
+        expired.len()
+    }
+
+    /// Stores the content of an interaction command's response, keeping any followups already sent.
+    pub fn insert_interaction_command(
+        &self,
+        id: u64,
+        content: String,
+        original_content: String,
+        has_attachments: bool,
+    ) {
+        let (followup_message_ids, extra_message_ids) = self
+            .1
+            .get(&id)
+            .map(|r| (r.followup_message_ids, r.extra_message_ids))
+            .unwrap_or_default();
+
+        self.1.insert(
+            id,
+            InteractionReply {
+                content,
+                original_content,
+                followup_message_ids,
+                extra_message_ids,
+                has_attachments,
+            },
+        );
+    }
+
+    /// Records the followups holding further pages of an interaction command's response.
+    pub fn set_interaction_extra_messages(&self, id: u64, extra_message_ids: Vec<u64>) {
+        if let Some(mut reply) = self.1.get(&id) {
+            reply.extra_message_ids = extra_message_ids;
+            self.1.insert(id, reply);
+        }
+    }
+
+    /// Records a followup message sent for an interaction command that has already been responded
+    /// to.
+    pub fn add_interaction_followup(&self, id: u64, message_id: u64) {
+        if let Some(mut reply) = self.1.get(&id) {
+            reply.followup_message_ids.push(message_id);
+            self.1.insert(id, reply);
+        }
     }
 
-    pub fn get_interaction_command(&self, id: u64) -> Option<()> {
+    pub fn get_interaction_command(&self, id: u64) -> Option<InteractionReply> {
         self.1.get(&id)
     }
 }
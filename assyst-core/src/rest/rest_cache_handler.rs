@@ -5,9 +5,11 @@ use std::time::Duration;
 
 use moka::sync::Cache;
 use twilight_http::Client as HttpClient;
+use twilight_model::channel::permission_overwrite::{PermissionOverwrite, PermissionOverwriteType};
 use twilight_model::guild::{Permissions, PremiumTier};
-use twilight_model::id::marker::{ChannelMarker, GuildMarker, UserMarker};
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, RoleMarker, UserMarker};
 use twilight_model::id::Id;
+use twilight_model::user::User;
 
 use super::{
     NORMAL_DISCORD_UPLOAD_LIMIT_BYTES, PREMIUM_TIER2_DISCORD_UPLOAD_LIMIT_BYTES,
@@ -33,6 +35,8 @@ pub struct RestCacheHandler {
     channel_nsfw_status: Cache<u64, bool>,
     /// Guild ID -> User ID
     guild_owners: Cache<u64, u64>,
+    /// User ID -> User, from events and previous fetches
+    users: Cache<u64, User>,
 }
 impl RestCacheHandler {
     pub fn new(client: Arc<HttpClient>) -> RestCacheHandler {
@@ -41,6 +45,7 @@ impl RestCacheHandler {
             guild_upload_limits: default_cache(),
             channel_nsfw_status: default_cache(),
             guild_owners: default_cache(),
+            users: default_cache(),
         }
     }
 
@@ -49,13 +54,23 @@ impl RestCacheHandler {
         self.guild_upload_limits.run_pending_tasks();
         self.channel_nsfw_status.run_pending_tasks();
         self.guild_owners.run_pending_tasks();
+        self.users.run_pending_tasks();
 
         size += self.guild_upload_limits.entry_count() * size_of::<(u64, u64)>() as u64;
         size += self.channel_nsfw_status.entry_count() * size_of::<(u64, bool)>() as u64;
         size += self.guild_owners.entry_count() * size_of::<(u64, u64)>() as u64;
+        size += self.users.entry_count() * size_of::<(u64, User)>() as u64;
         size
     }
 
+    pub fn set_user(&self, user: User) {
+        self.users.insert(user.id.get(), user);
+    }
+
+    pub fn get_user(&self, user_id: Id<UserMarker>) -> Option<User> {
+        self.users.get(&user_id.get())
+    }
+
     pub fn set_guild_upload_limit_bytes(&self, guild_id: u64, tier: PremiumTier) {
         let amount = match tier {
             PremiumTier::None | PremiumTier::Tier1 => NORMAL_DISCORD_UPLOAD_LIMIT_BYTES,
@@ -177,4 +192,159 @@ impl RestCacheHandler {
 
         Ok(owner == user_id || member_is_manager)
     }
+
+    /// Checks if a user can view a guild channel and read its message history, taking the user's
+    /// roles and the channel's permission overwrites into account. Threads are checked against
+    /// their parent channel.
+    pub async fn user_can_read_channel(&self, guild_id: u64, channel_id: u64, user_id: u64) -> anyhow::Result<bool> {
+        let mut channel = self
+            .http_client
+            .channel(Id::<ChannelMarker>::new(channel_id))
+            .await?
+            .model()
+            .await?;
+
+        if channel.guild_id.map(Id::get) != Some(guild_id) {
+            return Ok(false);
+        }
+
+        if channel.kind.is_thread()
+            && let Some(parent_id) = channel.parent_id
+        {
+            channel = self.http_client.channel(parent_id).await?.model().await?;
+        }
+
+        if self.get_guild_owner(guild_id).await? == user_id {
+            return Ok(true);
+        }
+
+        let member = self
+            .http_client
+            .guild_member(Id::<GuildMarker>::new(guild_id), Id::<UserMarker>::new(user_id))
+            .await?
+            .model()
+            .await?;
+
+        let roles = self
+            .http_client
+            .roles(Id::<GuildMarker>::new(guild_id))
+            .await?
+            .models()
+            .await?;
+
+        // the @everyone role shares its ID with the guild
+        let base = roles
+            .iter()
+            .filter(|r| r.id.get() == guild_id || member.roles.contains(&r.id))
+            .fold(Permissions::empty(), |a, r| a | r.permissions);
+
+        let permissions = channel_permissions(
+            base,
+            guild_id,
+            &member.roles,
+            user_id,
+            channel.permission_overwrites.as_deref().unwrap_or_default(),
+        );
+
+        Ok(permissions.contains(Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY))
+    }
+}
+
+/// Applies a channel's permission overwrites to a member's guild-wide permissions, in the same
+/// order as Discord: the `@everyone` overwrite, then the overwrites of the member's roles combined,
+/// then the member's own overwrite. Administrators bypass overwrites entirely.
+fn channel_permissions(
+    base: Permissions,
+    guild_id: u64,
+    member_roles: &[Id<RoleMarker>],
+    user_id: u64,
+    overwrites: &[PermissionOverwrite],
+) -> Permissions {
+    if base.contains(Permissions::ADMINISTRATOR) {
+        return Permissions::all();
+    }
+
+    let apply = |permissions: Permissions, allow: Permissions, deny: Permissions| (permissions & !deny) | allow;
+    let mut permissions = base;
+
+    if let Some(everyone) = overwrites
+        .iter()
+        .find(|o| o.kind == PermissionOverwriteType::Role && o.id.get() == guild_id)
+    {
+        permissions = apply(permissions, everyone.allow, everyone.deny);
+    }
+
+    let (allow, deny) = overwrites
+        .iter()
+        .filter(|o| o.kind == PermissionOverwriteType::Role && member_roles.contains(&o.id.cast()))
+        .fold((Permissions::empty(), Permissions::empty()), |(allow, deny), o| {
+            (allow | o.allow, deny | o.deny)
+        });
+    permissions = apply(permissions, allow, deny);
+
+    if let Some(member) = overwrites
+        .iter()
+        .find(|o| o.kind == PermissionOverwriteType::Member && o.id.get() == user_id)
+    {
+        permissions = apply(permissions, member.allow, member.deny);
+    }
+
+    permissions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: u64 = 1;
+    const ROLE: u64 = 2;
+    const USER: u64 = 3;
+    const READ: Permissions = Permissions::VIEW_CHANNEL.union(Permissions::READ_MESSAGE_HISTORY);
+
+    fn overwrite(id: u64, kind: PermissionOverwriteType, allow: Permissions, deny: Permissions) -> PermissionOverwrite {
+        PermissionOverwrite {
+            allow,
+            deny,
+            id: Id::new(id),
+            kind,
+        }
+    }
+
+    fn can_read(base: Permissions, overwrites: &[PermissionOverwrite]) -> bool {
+        channel_permissions(base, GUILD, &[Id::new(ROLE)], USER, overwrites).contains(READ)
+    }
+
+    #[test]
+    fn everyone_overwrite_hides_channel() {
+        let everyone = overwrite(GUILD, PermissionOverwriteType::Role, Permissions::empty(), READ);
+
+        assert!(can_read(READ, &[]));
+        assert!(!can_read(READ, &[everyone]));
+    }
+
+    #[test]
+    fn role_overwrite_takes_precedence_over_everyone() {
+        let everyone = overwrite(GUILD, PermissionOverwriteType::Role, Permissions::empty(), READ);
+        let role = overwrite(ROLE, PermissionOverwriteType::Role, READ, Permissions::empty());
+        let other_role = overwrite(ROLE + 10, PermissionOverwriteType::Role, READ, Permissions::empty());
+
+        assert!(can_read(READ, &[everyone, role]));
+        assert!(!can_read(READ, &[everyone, other_role]));
+    }
+
+    #[test]
+    fn member_overwrite_takes_precedence_over_roles() {
+        let role = overwrite(ROLE, PermissionOverwriteType::Role, READ, Permissions::empty());
+        let member = overwrite(USER, PermissionOverwriteType::Member, Permissions::empty(), READ);
+
+        assert!(!can_read(Permissions::empty(), &[role, member]));
+        assert!(can_read(Permissions::empty(), &[role]));
+    }
+
+    #[test]
+    fn administrators_bypass_overwrites() {
+        let member = overwrite(USER, PermissionOverwriteType::Member, Permissions::empty(), READ);
+
+        assert!(can_read(Permissions::ADMINISTRATOR, &[member]));
+    }
 }
@@ -6,27 +6,33 @@ use assyst_common::config::config::CobaltApiInstance;
 use assyst_common::util::string_from_likely_utf8;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
-use reqwest::{Client, StatusCode};
+use reqwest::StatusCode;
 use serde::Deserialize;
 use serde_json::{from_str, json};
 use tokio::process::Command;
 use tokio::time::timeout;
 use tracing::debug;
 
+use crate::assyst::Assyst;
 use crate::command::services::download::DownloadFlags;
 use crate::downloader::{download_content, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES};
 
 #[derive(Default, Clone)]
 pub struct WebDownloadOpts {
     pub audio_only: Option<bool>,
+    pub audio_format: Option<String>,
+    pub audio_bitrate: Option<u64>,
     pub quality: Option<String>,
     pub urls: Vec<CobaltApiInstance>,
     pub verbose: bool,
+    pub max_size: Option<u64>,
 }
 impl WebDownloadOpts {
     pub fn from_download_flags(flags: DownloadFlags, urls: Vec<CobaltApiInstance>) -> Self {
         Self {
             audio_only: Some(flags.audio),
+            audio_format: flags.audio_format,
+            audio_bitrate: flags.audio_bitrate,
             quality: if flags.quality != 0 {
                 Some(flags.quality.to_string())
             } else {
@@ -34,6 +40,7 @@ impl WebDownloadOpts {
             },
             urls,
             verbose: flags.verbose,
+            max_size: flags.max_size,
         }
     }
 }
@@ -113,7 +120,8 @@ pub struct WebDownloadErrorContext {
 
 /// Attempts to download web media. Will try all APIs until one succeeds, unless
 /// `opts.api_url_override` is set.
-pub async fn download_web_media(client: &Client, url: &str, opts: WebDownloadOpts) -> anyhow::Result<Vec<u8>> {
+pub async fn download_web_media(assyst: &Assyst, url: &str, opts: WebDownloadOpts) -> anyhow::Result<Vec<u8>> {
+    let client = &assyst.reqwest_client;
     let urls = {
         let mut urls = opts.urls;
         if urls.is_empty() {
@@ -125,6 +133,10 @@ pub async fn download_web_media(client: &Client, url: &str, opts: WebDownloadOpt
         urls
     };
 
+    let limit = opts.max_size.map_or(ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES, |size| {
+        (size as usize).min(ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES)
+    });
+
     let mut result: Option<Vec<u8>> = None;
     let mut err: String = String::new();
 
@@ -143,7 +155,8 @@ pub async fn download_web_media(client: &Client, url: &str, opts: WebDownloadOpt
             .json(&json!({
                 "url": url,
                 "downloadMode": if opts.audio_only.unwrap_or(false) { "audio" } else { "auto" },
-                "audioFormat": "mp3",
+                "audioFormat": opts.audio_format.as_deref().unwrap_or("mp3"),
+                "audioBitrate": opts.audio_bitrate.unwrap_or(128).to_string(),
                 "videoQuality": opts.quality.clone().unwrap_or("720".to_owned()),
             }))
             .timeout(Duration::from_secs(60))
@@ -185,12 +198,7 @@ pub async fn download_web_media(client: &Client, url: &str, opts: WebDownloadOpt
         if let Some(r) = req_result_url {
             debug!("downloading from url {r} for web media {url}");
 
-            let media = match timeout(
-                Duration::from_secs(120),
-                download_content(client, &r, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES, false),
-            )
-            .await
-            {
+            let media = match timeout(Duration::from_secs(120), download_content(assyst, &r, limit, false)).await {
                 Ok(Ok(m)) => m,
                 Ok(Err(e)) => {
                     err = format!("Failed to download media: {e}");
@@ -0,0 +1,163 @@
+use assyst_common::config::CONFIG;
+use assyst_common::err;
+use assyst_common::util::rate_tracker::{KeyedRateTracker, SharedRateTracker};
+use assyst_common::util::unix_timestamp;
+use assyst_database::model::rate_snapshot::RateSnapshot;
+use tracing::info;
+
+use crate::assyst::ThreadSafeAssyst;
+
+/// How many of the most used commands to include in the summary.
+const TOP_COMMANDS_COUNT: usize = 5;
+
+/// The command and event rates read from the rate trackers at one point in time.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RateSummary {
+    pub commands_per_minute: usize,
+    pub events_per_second: usize,
+    pub top_commands: Vec<(&'static str, usize)>,
+}
+impl RateSummary {
+    /// Reads the current rates from the trackers. With `reset` set, the trackers are cleared after
+    /// being read, so that the next summary only covers the time since this one. Otherwise, their
+    /// tracking windows are left untouched, and only trackers for commands with no recent uses are
+    /// pruned.
+    pub fn take(
+        total_commands: &SharedRateTracker,
+        events: &SharedRateTracker,
+        individual_commands: &mut KeyedRateTracker<&'static str>,
+        reset: bool,
+    ) -> RateSummary {
+        let summary = RateSummary {
+            commands_per_minute: total_commands.get_rate(),
+            events_per_second: events.get_rate(),
+            top_commands: individual_commands.top_rates(TOP_COMMANDS_COUNT),
+        };
+
+        if reset {
+            total_commands.reset();
+            events.reset();
+            individual_commands.reset();
+        } else {
+            individual_commands.prune();
+        }
+
+        summary
+    }
+
+    /// Formats the top commands as `name=uses` pairs separated by commas.
+    pub fn top_commands_field(&self) -> String {
+        self.top_commands
+            .iter()
+            .map(|(name, uses)| format!("{name}={uses}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Logs a summary of the current command and event rates, storing it in the database and
+/// resetting the trackers if enabled in the config.
+pub async fn log_rate_trackers(assyst: ThreadSafeAssyst) {
+    let metrics = &assyst.metrics_handler;
+    let options = &CONFIG.rate_tracker_logging;
+
+    let summary = RateSummary::take(
+        &metrics.total_commands_rate_tracker,
+        &metrics.events_rate_tracker,
+        &mut *metrics.individual_commands_rate_trackers.lock().await,
+        options.reset_after_logging,
+    );
+    let top_commands = summary.top_commands_field();
+
+    info!(
+        commands_per_minute = summary.commands_per_minute,
+        events_per_second = summary.events_per_second,
+        top_commands_last_hour = %top_commands,
+        "Rate tracker summary"
+    );
+
+    if options.persist {
+        let snapshot = RateSnapshot {
+            timestamp: unix_timestamp() as i64,
+            commands_per_minute: summary.commands_per_minute as i64,
+            events_per_second: summary.events_per_second as i64,
+            top_commands,
+        };
+
+        if let Err(e) = snapshot.insert(&assyst.database_handler).await {
+            err!("Failed to store rate tracker summary: {e:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const LENGTH: Duration = Duration::from_secs(60 * 60);
+
+    fn trackers() -> (SharedRateTracker, SharedRateTracker, KeyedRateTracker<&'static str>) {
+        let commands = SharedRateTracker::new(LENGTH);
+        let events = SharedRateTracker::new(LENGTH);
+        let mut individual_commands = KeyedRateTracker::new(LENGTH);
+
+        for name in ["caption", "caption", "caption", "ping", "speechbubble", "speechbubble"] {
+            commands.add_sample();
+            individual_commands.add_sample(name);
+        }
+        for _ in 0..10 {
+            events.add_sample();
+        }
+
+        (commands, events, individual_commands)
+    }
+
+    #[test]
+    fn summary_reads_every_tracker() {
+        let (commands, events, mut individual_commands) = trackers();
+
+        let summary = RateSummary::take(&commands, &events, &mut individual_commands, false);
+        assert_eq!(
+            summary,
+            RateSummary {
+                commands_per_minute: 6,
+                events_per_second: 10,
+                top_commands: vec![("caption", 3), ("speechbubble", 2), ("ping", 1)],
+            }
+        );
+        assert_eq!(summary.top_commands_field(), "caption=3, speechbubble=2, ping=1");
+
+        // without a reset, the next summary sees the same samples
+        assert_eq!(
+            RateSummary::take(&commands, &events, &mut individual_commands, false),
+            summary
+        );
+    }
+
+    #[test]
+    fn summary_resets_the_trackers_after_reading_them() {
+        let (commands, events, mut individual_commands) = trackers();
+
+        let summary = RateSummary::take(&commands, &events, &mut individual_commands, true);
+        assert_eq!(summary.commands_per_minute, 6);
+        assert_eq!(summary.events_per_second, 10);
+        assert_eq!(summary.top_commands.len(), 3);
+
+        assert_eq!(commands.count(), 0);
+        assert_eq!(events.count(), 0);
+        assert!(individual_commands.is_empty());
+
+        let summary = RateSummary::take(&commands, &events, &mut individual_commands, true);
+        assert_eq!(
+            summary,
+            RateSummary {
+                commands_per_minute: 0,
+                events_per_second: 0,
+                top_commands: vec![],
+            }
+        );
+        assert_eq!(summary.top_commands_field(), "");
+    }
+}
@@ -1,4 +1,7 @@
 pub mod get_premium_users;
+pub mod log_rate_trackers;
+pub mod prune_replies;
 pub mod refresh_entitlements;
+pub mod refresh_global_blacklist;
 pub mod reminders;
 pub mod top_gg_stats;
@@ -0,0 +1,13 @@
+use tracing::debug;
+
+use crate::assyst::ThreadSafeAssyst;
+use crate::replies::REPLY_EDIT_TIMEOUT;
+
+/// Removes cached command replies that are too old for editing the command to re-run it.
+pub async fn prune_replies(assyst: ThreadSafeAssyst) {
+    let pruned = assyst.replies.prune_raw_messages(REPLY_EDIT_TIMEOUT);
+
+    if pruned > 0 {
+        debug!("Pruned {pruned} expired command replies");
+    }
+}
@@ -1,26 +1,88 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use assyst_common::err;
 use assyst_common::macros::handle_log;
+use assyst_common::util::format_duration;
+use assyst_common::util::rate_tracker::RateTracker;
 use assyst_database::model::active_guild_premium_entitlement::ActiveGuildPremiumEntitlement;
-use tracing::info;
+use tracing::{debug, info};
+use twilight_model::application::monetization::Entitlement;
 
 use crate::assyst::ThreadSafeAssyst;
 
+/// How long changes to entitlements are tracked for in [`EntitlementRefreshStats`].
+const CHANGE_TRACKING_LENGTH: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Statistics about entitlement refreshes, for noticing when premium state is changing unusually
+/// often, or when Discord is slow to respond.
+pub struct EntitlementRefreshStats {
+    /// Entitlements added within the last day.
+    pub added: RateTracker,
+    /// Entitlements updated within the last day.
+    pub updated: RateTracker,
+    /// Entitlements removed within the last day.
+    pub removed: RateTracker,
+    /// When the last refresh finished.
+    pub last_refresh: Option<Instant>,
+    /// How long fetching entitlements from Discord took in the last refresh.
+    pub last_fetch_duration: Option<Duration>,
+}
+impl Default for EntitlementRefreshStats {
+    fn default() -> Self {
+        Self {
+            added: RateTracker::new(CHANGE_TRACKING_LENGTH),
+            updated: RateTracker::new(CHANGE_TRACKING_LENGTH),
+            removed: RateTracker::new(CHANGE_TRACKING_LENGTH),
+            last_refresh: None,
+            last_fetch_duration: None,
+        }
+    }
+}
+
+/// Fetches all of the application's entitlements from Discord.
+async fn fetch_entitlements(assyst: &ThreadSafeAssyst) -> anyhow::Result<Vec<Entitlement>> {
+    Ok(assyst
+        .http_client
+        .entitlements(assyst.application_id)
+        .await?
+        .model()
+        .await?)
+}
+
+/// The new state of a stored entitlement, if Discord's copy of it has changed (e.g. it was renewed
+/// or moved to another guild). Expired entitlements are left to be removed instead.
+fn changed_entitlement(
+    stored: &ActiveGuildPremiumEntitlement,
+    fetched: Entitlement,
+) -> anyhow::Result<Option<ActiveGuildPremiumEntitlement>> {
+    let active = ActiveGuildPremiumEntitlement::try_from(fetched)?;
+
+    if active == *stored || active.expired() {
+        Ok(None)
+    } else {
+        Ok(Some(active))
+    }
+}
+
 pub async fn refresh_entitlements(assyst: ThreadSafeAssyst) {
-    let additional = match assyst.http_client.entitlements(assyst.application_id).await {
-        Ok(x) => match x.model().await {
-            Ok(e) => e,
-            Err(e) => {
-                err!("Failed to get potential new entitlements: {e:?}");
-                vec![]
-            },
-        },
-        Err(e) => {
-            err!("Failed to get potential new entitlements: {e:?}");
-            vec![]
-        },
-    };
+    refresh_entitlements_with(assyst, false).await;
+}
+
+/// Syncs stored entitlements with Discord's. With `dry_run` set, the changes that would be made are
+/// only logged: nothing is written to the database or to the in-memory entitlements, and the
+/// refresh isn't counted in [`EntitlementRefreshStats`].
+pub async fn refresh_entitlements_with(assyst: ThreadSafeAssyst, dry_run: bool) {
+    let fetch_start = Instant::now();
+    let additional = fetch_entitlements(&assyst).await.unwrap_or_else(|e| {
+        err!("Failed to get potential new entitlements: {e:?}");
+        vec![]
+    });
+    let fetch_duration = fetch_start.elapsed();
+
+    let mut added = 0;
+    let mut updated = 0;
+    let mut removed = 0;
 
     for a in additional.clone() {
         if !assyst.entitlements.lock().unwrap().contains_key(&(a.id.get() as i64)) {
@@ -36,10 +98,20 @@ pub async fn refresh_entitlements(assyst: ThreadSafeAssyst) {
                 break;
             }
 
+            if dry_run {
+                info!(
+                    "Dry run: would add entitlement {} (guild {})",
+                    active.entitlement_id, active.guild_id
+                );
+                added += 1;
+                continue;
+            }
+
             if let Err(e) = active.set(&assyst.database_handler).await {
                 err!("Error adding new entitlement for ID {}: {e:?}", active.entitlement_id);
             };
             handle_log(format!("New entitlement! Guild: {}", active.guild_id));
+            added += 1;
 
             assyst
                 .entitlements
@@ -54,6 +126,48 @@ pub async fn refresh_entitlements(assyst: ThreadSafeAssyst) {
         .ok()
         .unwrap_or(HashMap::new());
 
+    // update entitlements that have changed since they were stored, e.g. renewed or moved guild
+    for a in &additional {
+        let Some(stored) = db_entitlements.get(&(a.id.get() as i64)) else {
+            continue;
+        };
+
+        let active = match changed_entitlement(stored, a.clone()) {
+            Ok(Some(a)) => a,
+            Ok(None) => continue,
+            Err(e) => {
+                err!("Error processing updated entitlement: {e:?}");
+                continue;
+            },
+        };
+
+        if dry_run {
+            info!(
+                "Dry run: would update entitlement {} (guild {})",
+                active.entitlement_id, active.guild_id
+            );
+            updated += 1;
+            continue;
+        }
+
+        if let Err(e) = active.update(&assyst.database_handler).await {
+            err!("Error updating entitlement {}: {e:?}", active.entitlement_id);
+            continue;
+        }
+
+        info!(
+            "Updated entitlement {} (guild {})",
+            active.entitlement_id, active.guild_id
+        );
+        updated += 1;
+
+        assyst
+            .entitlements
+            .lock()
+            .unwrap()
+            .insert(active.entitlement_id, active);
+    }
+
     // remove entitlements from the db that are not in the rest response
     for entitlement in db_entitlements.values() {
         if !additional
@@ -61,7 +175,17 @@ pub async fn refresh_entitlements(assyst: ThreadSafeAssyst) {
             .any(|x| x.id.get() as i64 == entitlement.entitlement_id)
             || entitlement.expired()
         {
+            if dry_run {
+                info!(
+                    "Dry run: would remove entitlement {} (guild {})",
+                    entitlement.entitlement_id, entitlement.guild_id
+                );
+                removed += 1;
+                continue;
+            }
+
             assyst.entitlements.lock().unwrap().remove(&entitlement.entitlement_id);
+            removed += 1;
             info!(
                 "Removed expired entitlement {} (guild {})",
                 entitlement.entitlement_id, entitlement.guild_id
@@ -76,4 +200,100 @@ pub async fn refresh_entitlements(assyst: ThreadSafeAssyst) {
             }
         }
     }
+
+    if !dry_run {
+        let mut stats = assyst.entitlement_refresh_stats.lock().unwrap();
+        (0..added).for_each(|_| stats.added.add_sample());
+        (0..updated).for_each(|_| stats.updated.add_sample());
+        (0..removed).for_each(|_| stats.removed.add_sample());
+        stats.last_refresh = Some(Instant::now());
+        stats.last_fetch_duration = Some(fetch_duration);
+    }
+
+    let fetch_duration = format_duration(&fetch_duration);
+    if dry_run {
+        info!(
+            "Entitlement refresh dry run: {added} to add, {updated} to update, {removed} to remove (fetched in \
+             {fetch_duration})"
+        );
+    } else if added > 0 || updated > 0 || removed > 0 {
+        info!(
+            "Refreshed entitlements: {added} added, {updated} updated, {removed} removed (fetched in {fetch_duration})"
+        );
+    } else {
+        debug!("Refreshed entitlements: no changes (fetched in {fetch_duration})");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTS_AT: &str = "2024-01-01T00:00:00.000000+00:00";
+    const STARTS_AT_MS: i64 = 1_704_067_200_000;
+    const ENDS_AT: &str = "2099-01-01T00:00:00.000000+00:00";
+    const ENDS_AT_MS: i64 = 4_070_908_800_000;
+
+    fn entitlement(guild_id: Option<u64>, ends_at: &str) -> Entitlement {
+        serde_json::from_value(serde_json::json!({
+            "id": "10",
+            "sku_id": "20",
+            "application_id": "30",
+            "user_id": "40",
+            "guild_id": guild_id.map(|id| id.to_string()),
+            "type": 8,
+            "deleted": false,
+            "consumed": false,
+            "starts_at": STARTS_AT,
+            "ends_at": ends_at,
+        }))
+        .unwrap()
+    }
+
+    fn stored() -> ActiveGuildPremiumEntitlement {
+        ActiveGuildPremiumEntitlement {
+            entitlement_id: 10,
+            guild_id: 50,
+            user_id: 40,
+            started_unix_ms: STARTS_AT_MS,
+            expiry_unix_ms: ENDS_AT_MS,
+        }
+    }
+
+    #[test]
+    fn unchanged_entitlements_are_left_alone() {
+        assert_eq!(
+            changed_entitlement(&stored(), entitlement(Some(50), ENDS_AT)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn renewed_or_moved_entitlements_are_updated() {
+        let renewed = changed_entitlement(&stored(), entitlement(Some(50), "2099-02-01T00:00:00.000000+00:00"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(renewed.expiry_unix_ms, ENDS_AT_MS + 31 * 24 * 60 * 60 * 1000);
+        assert_eq!(renewed.guild_id, 50);
+
+        let moved = changed_entitlement(&stored(), entitlement(Some(60), ENDS_AT))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            moved,
+            ActiveGuildPremiumEntitlement {
+                guild_id: 60,
+                ..stored()
+            }
+        );
+    }
+
+    #[test]
+    fn expired_or_invalid_entitlements_are_not_updated() {
+        // expired ones are removed instead
+        let expired = entitlement(Some(50), "2024-02-01T00:00:00.000000+00:00");
+        assert_eq!(changed_entitlement(&stored(), expired).unwrap(), None);
+
+        assert!(changed_entitlement(&stored(), entitlement(None, ENDS_AT)).is_err());
+    }
 }
@@ -0,0 +1,10 @@
+use assyst_common::err;
+
+use crate::assyst::ThreadSafeAssyst;
+
+/// Reloads the in-memory global blacklist, picking up any changes made to the database directly.
+pub async fn refresh_global_blacklist(assyst: ThreadSafeAssyst) {
+    if let Err(e) = assyst.refresh_global_blacklist().await {
+        err!("Failed to refresh global blacklist: {e:?}");
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use assyst_common::pipe::Pipe;
+use futures_util::{stream, StreamExt};
+use tokio::spawn;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+use tracing::{info, warn};
+use twilight_model::gateway::payload::incoming::MemberChunk;
+use twilight_model::gateway::payload::outgoing::RequestGuildMembers;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+use twilight_model::id::Id;
+use twilight_model::user::User;
+
+/// How many users are fetched over REST at once when resolving several.
+pub const USER_FETCH_CONCURRENCY: usize = 4;
+/// How long to wait for requested guild members before falling back to REST.
+const MEMBER_CHUNK_TIMEOUT: Duration = Duration::from_secs(2);
+/// The most user IDs Discord accepts in one guild member request.
+const MAX_MEMBER_REQUEST_USER_IDS: usize = 100;
+
+type PendingMemberRequest = (Vec<User>, oneshot::Sender<Vec<User>>);
+
+/// Requests guild members through assyst-gateway, and collects the member chunks sent in
+/// response.
+pub struct GuildMemberRequests {
+    command_tx: UnboundedSender<String>,
+    /// Nonce -> members received so far, and where to send them once the last chunk arrives
+    pending: Mutex<HashMap<String, PendingMemberRequest>>,
+    next_nonce: AtomicU64,
+}
+impl GuildMemberRequests {
+    pub fn new(path: &str) -> GuildMemberRequests {
+        let (tx, rx) = unbounded_channel::<String>();
+        GuildMemberRequests::init_pipe(rx, path);
+        GuildMemberRequests {
+            command_tx: tx,
+            pending: Mutex::new(HashMap::new()),
+            next_nonce: AtomicU64::new(0),
+        }
+    }
+
+    fn init_pipe(mut rx: UnboundedReceiver<String>, path: &str) {
+        let path = path.to_owned();
+        spawn(async move {
+            info!("Connecting to assyst-gateway command pipe on {path}");
+            loop {
+                let mut pipe = Pipe::poll_connect(&path, None).await.unwrap();
+                info!("Connected to assyst-gateway command pipe on {path}");
+                loop {
+                    // ok to unwrap because tx is permanently stored in the handler
+                    let command = rx.recv().await.unwrap();
+
+                    // the request is lost, so whoever made it falls back to REST after timing out
+                    if pipe.write_string(command).await.is_err() {
+                        break;
+                    }
+                }
+                warn!("Communication to assyst-gateway command pipe lost, attempting reconnection");
+            }
+        });
+    }
+
+    /// Requests the members of a guild with the given IDs, returning the users of those that are
+    /// in it. Gives up after [`MEMBER_CHUNK_TIMEOUT`], returning whatever arrived until then.
+    pub async fn request(&self, guild_id: Id<GuildMarker>, user_ids: &[Id<UserMarker>]) -> Vec<User> {
+        let mut users = Vec::new();
+        for user_ids in user_ids.chunks(MAX_MEMBER_REQUEST_USER_IDS) {
+            users.extend(self.request_chunk(guild_id, user_ids).await);
+        }
+
+        users
+    }
+
+    async fn request_chunk(&self, guild_id: Id<GuildMarker>, user_ids: &[Id<UserMarker>]) -> Vec<User> {
+        let nonce = self.next_nonce.fetch_add(1, Ordering::Relaxed).to_string();
+        let Ok(request) = RequestGuildMembers::builder(guild_id)
+            .nonce(nonce.clone())
+            .user_ids(user_ids.to_vec())
+        else {
+            return Vec::new();
+        };
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(nonce.clone(), (Vec::new(), tx));
+        // can unwrap since the pipe thread never drops the receiver
+        self.command_tx.send(serde_json::to_string(&request).unwrap()).unwrap();
+
+        let users = timeout(MEMBER_CHUNK_TIMEOUT, rx).await.ok().and_then(Result::ok);
+        match users {
+            Some(users) => users,
+            None => self
+                .pending
+                .lock()
+                .unwrap()
+                .remove(&nonce)
+                .map(|(users, _)| users)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Handles a `GUILD_MEMBERS_CHUNK`, completing the request it answers once its last chunk
+    /// arrives. Chunks for requests not made by [`GuildMemberRequests::request`] are ignored.
+    pub fn handle_member_chunk(&self, chunk: MemberChunk) {
+        let Some(nonce) = chunk.nonce else {
+            return;
+        };
+
+        let mut pending = self.pending.lock().unwrap();
+        let Some((users, _)) = pending.get_mut(&nonce) else {
+            return;
+        };
+
+        users.extend(chunk.members.into_iter().map(|member| member.user));
+
+        if chunk.chunk_index + 1 >= chunk.chunk_count
+            && let Some((users, tx)) = pending.remove(&nonce)
+        {
+            let _ = tx.send(users);
+        }
+    }
+}
+
+/// Resolves users by ID, looking each up at most once however often it appears in `ids`: first
+/// with `cached`, then all that are still missing at once with `from_members`, and finally by
+/// fetching each remaining user with `fetch`, at most [`USER_FETCH_CONCURRENCY`] at a time.
+///
+/// Users that can't be found are left out, rather than failing the whole batch.
+pub async fn resolve_users<M, F>(
+    ids: &[Id<UserMarker>],
+    cached: impl Fn(Id<UserMarker>) -> Option<User>,
+    from_members: impl FnOnce(Vec<Id<UserMarker>>) -> M,
+    fetch: impl Fn(Id<UserMarker>) -> F,
+) -> HashMap<Id<UserMarker>, User>
+where
+    M: Future<Output = Vec<User>>,
+    F: Future<Output = Option<User>>,
+{
+    let mut users = HashMap::new();
+    let mut missing = Vec::new();
+    for &id in ids {
+        if users.contains_key(&id) || missing.contains(&id) {
+            continue;
+        }
+
+        match cached(id) {
+            Some(user) => {
+                users.insert(id, user);
+            },
+            None => missing.push(id),
+        }
+    }
+
+    if missing.is_empty() {
+        return users;
+    }
+
+    for user in from_members(missing.clone()).await {
+        if missing.contains(&user.id) {
+            users.insert(user.id, user);
+        }
+    }
+    missing.retain(|id| !users.contains_key(id));
+
+    let fetched = stream::iter(missing)
+        .map(fetch)
+        .buffer_unordered(USER_FETCH_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+    users.extend(fetched.into_iter().flatten().map(|user| (user.id, user)));
+
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn user(id: u64) -> User {
+        serde_json::from_value(json!({
+            "id": id.to_string(),
+            "username": format!("user{id}"),
+            "discriminator": "0",
+            "avatar": null,
+        }))
+        .unwrap()
+    }
+
+    fn id(id: u64) -> Id<UserMarker> {
+        Id::new(id)
+    }
+
+    #[tokio::test]
+    async fn cached_and_duplicate_ids_are_not_fetched() {
+        let cache = HashMap::from([(id(1), user(1))]);
+        let member_requests = Mutex::new(Vec::new());
+        let fetches = Mutex::new(Vec::new());
+
+        let users = resolve_users(
+            &[id(1), id(2), id(1), id(3), id(2), id(3)],
+            |id| cache.get(&id).cloned(),
+            |ids| {
+                member_requests.lock().unwrap().push(ids);
+                async { vec![user(2)] }
+            },
+            |id| {
+                fetches.lock().unwrap().push(id);
+                async move { Some(user(id.get())) }
+            },
+        )
+        .await;
+
+        assert_eq!(users.len(), 3);
+        assert_eq!(*member_requests.lock().unwrap(), [vec![id(2), id(3)]]);
+        assert_eq!(*fetches.lock().unwrap(), [id(3)]);
+    }
+
+    #[tokio::test]
+    async fn fully_cached_ids_make_no_requests() {
+        let cache = HashMap::from([(id(1), user(1)), (id(2), user(2))]);
+        let requests = AtomicUsize::new(0);
+
+        let users = resolve_users(
+            &[id(2), id(1), id(2)],
+            |id| cache.get(&id).cloned(),
+            |_| {
+                requests.fetch_add(1, Ordering::Relaxed);
+                async { Vec::new() }
+            },
+            |_| {
+                requests.fetch_add(1, Ordering::Relaxed);
+                async { None }
+            },
+        )
+        .await;
+
+        assert_eq!(users.len(), 2);
+        assert_eq!(requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn unknown_users_are_left_out() {
+        let users = resolve_users(
+            &[id(1), id(2)],
+            |_| None,
+            |_| async { Vec::new() },
+            |id| async move { (id.get() == 1).then(|| user(1)) },
+        )
+        .await;
+
+        assert_eq!(users.keys().copied().collect::<Vec<_>>(), [id(1)]);
+    }
+}
@@ -55,6 +55,11 @@ impl DatabaseCache {
         self.prefixes.insert(guild_id, prefix);
     }
 
+    /// Evicts the cached prefix for a guild, so that the next lookup goes to the database.
+    pub fn remove_prefix(&self, guild_id: u64) {
+        self.prefixes.invalidate(&guild_id);
+    }
+
     pub fn get_prefixes_cache_size(&self) -> usize {
         self.prefixes.run_pending_tasks();
         self.prefixes.entry_count() as usize
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use crate::DatabaseHandler;
 
 /// The global blacklist is a list of users who are completely blacklisted from using any of the
@@ -30,6 +32,14 @@ impl GlobalBlacklist {
         }
     }
 
+    /// Every blacklisted user ID.
+    pub async fn get_all(handler: &DatabaseHandler) -> anyhow::Result<HashSet<u64>> {
+        let query = r"SELECT user_id FROM blacklist";
+
+        let rows = sqlx::query_as::<_, (i64,)>(query).fetch_all(&handler.pool).await?;
+        Ok(rows.into_iter().map(|(user_id,)| user_id as u64).collect())
+    }
+
     pub async fn set_user_blacklisted(&self, handler: &DatabaseHandler, user_id: u64) -> anyhow::Result<()> {
         let query = r"INSERT INTO blacklist VALUES ($1)";
 
@@ -7,6 +7,7 @@ pub mod free_tier_2_requests;
 pub mod global_blacklist;
 pub mod guild_disabled_command;
 pub mod prefix;
+pub mod rate_snapshot;
 pub mod reminder;
 pub mod tag;
 pub mod user_votes;
@@ -46,6 +46,13 @@ impl Prefix {
         }
     }
 
+    /// Discards any cached prefix for this guild and fetches it from the database again. Useful if
+    /// the prefix was changed without going through [`Prefix::set`].
+    pub async fn refresh(handler: &DatabaseHandler, guild_id: u64) -> anyhow::Result<Option<Self>> {
+        handler.cache.remove_prefix(guild_id);
+        Self::get(handler, guild_id).await
+    }
+
     #[must_use] pub fn size_of(&self) -> u64 {
         self.prefix.as_bytes().len() as u64
     }
@@ -0,0 +1,26 @@
+use crate::DatabaseHandler;
+
+/// A summary of command and event rates at one point in time, stored for historical graphs.
+#[derive(sqlx::FromRow, Debug)]
+pub struct RateSnapshot {
+    /// When the snapshot was taken, in milliseconds since the Unix epoch.
+    pub timestamp: i64,
+    pub commands_per_minute: i64,
+    pub events_per_second: i64,
+    /// The most used commands, formatted as `name=uses` pairs separated by commas.
+    pub top_commands: String,
+}
+impl RateSnapshot {
+    pub async fn insert(&self, handler: &DatabaseHandler) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO rate_snapshots VALUES ($1, $2, $3, $4)";
+
+        sqlx::query(query)
+            .bind(self.timestamp)
+            .bind(self.commands_per_minute)
+            .bind(self.events_per_second)
+            .bind(&self.top_commands)
+            .execute(&handler.pool)
+            .await
+            .map(|_| ())
+    }
+}
@@ -1,15 +1,18 @@
 use assyst_common::config::CONFIG;
 use assyst_common::ok_or_break;
 use assyst_common::pipe::pipe_server::PipeServer;
-use assyst_common::pipe::GATEWAY_PIPE_PATH;
+use assyst_common::pipe::{GATEWAY_COMMAND_PIPE_PATH, GATEWAY_PIPE_PATH};
 use assyst_common::util::tracing_init;
 use futures_util::StreamExt;
 use tokio::sync::mpsc::{channel, Sender};
 use tokio::{signal, spawn};
 use tracing::{debug, info, trace, warn};
-use twilight_gateway::{create_recommended, ConfigBuilder as GatewayConfigBuilder, Intents, Message, Shard};
+use twilight_gateway::{
+    create_recommended, ConfigBuilder as GatewayConfigBuilder, Intents, Message, MessageSender, Shard,
+};
 use twilight_http::Client as HttpClient;
 use twilight_model::gateway::payload::outgoing::update_presence::UpdatePresencePayload;
+use twilight_model::gateway::payload::outgoing::RequestGuildMembers;
 use twilight_model::gateway::presence::{Activity, ActivityType, Status};
 
 // Jemallocator is probably unnecessary for the average instance,
@@ -85,6 +88,25 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    // shards are created in order, so a shard's number is its index here
+    let senders = shards.iter().map(Shard::sender).collect::<Vec<_>>();
+
+    let mut command_pipe_server = PipeServer::listen(GATEWAY_COMMAND_PIPE_PATH).unwrap();
+    info!("Core command listener started on {}", GATEWAY_COMMAND_PIPE_PATH);
+
+    // command pipe thread
+    tokio::spawn(async move {
+        loop {
+            if let Ok(mut stream) = command_pipe_server.accept_connection().await {
+                info!("Command connection received from assyst-core");
+                while let Ok(command) = stream.read_string().await {
+                    forward_guild_member_request(&senders, &command);
+                }
+                warn!("Command connection to assyst-core lost, awaiting reconnection");
+            }
+        }
+    });
+
     let mut tasks = vec![];
     let shards_count = shards.len();
 
@@ -102,6 +124,23 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Sends a serialized [`RequestGuildMembers`] from assyst-core to the shard of the guild it is
+/// for.
+fn forward_guild_member_request(senders: &[MessageSender], command: &str) {
+    let request = match serde_json::from_str::<RequestGuildMembers>(command) {
+        Ok(request) => request,
+        Err(e) => {
+            warn!(?e, "invalid guild member request from assyst-core");
+            return;
+        },
+    };
+
+    let shard = (request.d.guild_id.get() >> 22) % senders.len() as u64;
+    if let Err(e) = senders[shard as usize].command(&request) {
+        warn!(?e, "failed to send guild member request to shard {shard}");
+    }
+}
+
 async fn runner(mut shard: Shard, tx: Sender<String>) {
     loop {
         match shard.next().await {
@@ -45,6 +45,9 @@ impl syn::parse::Parse for CommandAttributes {
 ///
 /// fn remind(ctxt: &mut CommandCtxt<'_>, time: Time, rest: Rest) {}
 /// ```
+///
+/// Parameters can be annotated with `#[description = "..."]` to override the generic description
+/// of their slash command option.
 #[proc_macro_attribute]
 pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
     let CommandAttributes(attrs) = syn::parse_macro_input!(attrs as CommandAttributes);
@@ -72,9 +75,12 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
     let mut parse_idents = Vec::new();
     let mut parse_exprs = Vec::new();
     let mut parse_usage = Vec::new();
+    let mut parse_names = Vec::new();
+    let mut parse_indices = Vec::new();
     let mut parse_attrs = Vec::new();
     let mut interaction_parse_exprs = Vec::new();
     let mut command_option_exprs = Vec::new();
+    let mut argument_info_exprs = Vec::new();
 
     // sanity check that the first parameter is the `ctxt`, and exclude it from the list of
     // arguments it wouldn't compile anyway since `CommandCtxt` can't be parsed as an argument
@@ -89,18 +95,49 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
                 if let Pat::Ident(ident) = &**pat {
                     let ident_string = ident.ident.to_string();
 
-                    command_option_exprs.push(quote! {{
+                    let description = attrs.iter().find_map(|attr| {
+                        if let Meta::NameValue(n) = &attr.meta
+                            && n.path.is_ident("description")
+                        {
+                            Some(n.value.clone())
+                        } else {
+                            None
+                        }
+                    });
+
+                    command_option_exprs.push(match description {
+                        Some(description) => quote! {{
+                            crate::command::arguments::with_description(
+                                <#ty>::as_command_options(#ident_string),
+                                #ident_string,
+                                #description
+                            )
+                        }},
+                        None => quote! {{
                             <#ty>::as_command_options(#ident_string)
-                    }});
+                        }},
+                    });
+
+                    argument_info_exprs.push(quote! {
+                        crate::command::ArgumentInfo {
+                            name: #ident_string,
+                            option_names: <#ty>::as_command_options(#ident_string)
+                                .into_iter()
+                                .map(|option| option.name)
+                                .collect(),
+                        }
+                    });
 
                     parse_attrs.push((ident_string, attrs.clone(), ty.clone()));
                 }
 
                 attrs.clear();
                 parse_idents.push(Ident::new(&format!("p{index}"), Span::call_site()));
-                parse_exprs.push(quote!(<#ty>::parse_raw_message(&mut ctxt, Some((stringify!(#pat).to_string(), stringify!(#ty).to_string()))).await));
+                parse_exprs.push(quote!(<#ty>::parse_raw_message(ctxt, Some((stringify!(#pat).to_string(), stringify!(#ty).to_string()))).await));
                 parse_usage.push(quote!(<#ty as crate::command::arguments::ParseArgument>::usage(stringify!(#pat))));
-                interaction_parse_exprs.push(quote!(<#ty>::parse_command_option(&mut ctxt, Some((stringify!(#pat).to_string(), stringify!(#ty).to_string()))).await));
+                parse_names.push(quote!(stringify!(#pat)));
+                parse_indices.push(index);
+                interaction_parse_exprs.push(quote!(<#ty>::parse_command_option(ctxt, Some((stringify!(#pat).to_string(), stringify!(#ty).to_string()))).await));
             },
         }
     }
@@ -121,7 +158,6 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
 
     // collect stuff from argument attributes
     // add more here as required
-    // todo: support parameter descriptions later
     let mut autocomplete_fns: Punctuated<proc_macro2::TokenStream, Comma> = Punctuated::new();
 
     for param in parse_attrs {
@@ -155,6 +191,11 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
                     } else {
                         panic!("autocomplete: invalid value ({:?})", n.value);
                     }
+                } else if s.ident == "description" {
+                    // handled when generating the command options
+                    if !matches!(n.value, Expr::Lit(ExprLit { lit: Lit::Str(_), .. })) {
+                        panic!("description: invalid value ({:?})", n.value);
+                    }
                 } else {
                     panic!("fn arg attr: invalid name ({:?})", s.ident.to_string());
                 }
@@ -197,6 +238,9 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
 
     let flag_descriptions = fields.remove("flag_descriptions").unwrap_or_else(empty_array_expr);
     let guild_only = fields.remove("guild_only").unwrap_or_else(false_expr);
+    let image_resolve = fields
+        .remove("image_resolve")
+        .unwrap_or_else(|| parse_quote!(crate::command::arguments::ImageResolveConfig::ALL));
 
     let following = quote::quote! {
         #[allow(non_camel_case_types)]
@@ -226,7 +270,8 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
                     flag_descriptions: descriptions,
                     context_menu_message_command: #context_menu_message_command,
                     context_menu_user_command: #context_menu_user_command,
-                    guild_only: #guild_only
+                    guild_only: #guild_only,
+                    image_resolve: #image_resolve
                 })
             }
 
@@ -290,7 +335,9 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
                   command_options.extend(#command_option_exprs);
                 )*
 
-                let command_info = crate::command::CommandInteractionInfo { command_options };
+                let arguments = vec![#(#argument_info_exprs),*];
+
+                let command_info = crate::command::CommandInteractionInfo { command_options, arguments };
                 crate::command::CommandGroupingInteractionInfo::Command(command_info)
             }
 
@@ -301,11 +348,33 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
             ) -> Result<(), crate::command::ExecutionError> {
                 use crate::command::arguments::ParseArgument;
 
-                crate::command::check_metadata(self.metadata(), &mut ctxt.cx).await?;
+                let metadata = self.metadata();
+                ctxt.image_resolve = metadata.image_resolve;
+
+                // attached to parse errors so the user can see which argument was wrong
+                #[allow(unused_variables)]
+                let argument_error = |index: usize, name: &'static str, error| {
+                    crate::command::ExecutionError::Argument(crate::command::errors::ArgumentError {
+                        index,
+                        name,
+                        usage: vec![#(#parse_usage),*],
+                        subcommand: None,
+                        error,
+                    })
+                };
 
-                #(
-                    let #parse_idents = #parse_exprs.map_err(crate::command::ExecutionError::Parse)?;
-                )*
+                // the arguments are only parsed (which can involve downloading media) if every
+                // access check passes
+                let (#(#parse_idents,)*) = crate::command::check_then_parse(
+                    &mut ctxt,
+                    |ctxt| Box::pin(crate::command::check_metadata(metadata, &mut ctxt.cx)),
+                    |#[allow(unused_variables)] ctxt| Box::pin(async move {
+                        #(
+                            let #parse_idents = #parse_exprs.map_err(|e| argument_error(#parse_indices, #parse_names, e))?;
+                        )*
+                        Ok::<_, crate::command::ExecutionError>((#(#parse_idents,)*))
+                    }),
+                ).await?;
 
                 #fn_name(ctxt.cx, #(#parse_idents),*).await.map_err(crate::command::ExecutionError::Command)
             }
@@ -317,11 +386,32 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
             ) -> Result<(), crate::command::ExecutionError> {
                 use crate::command::arguments::ParseArgument;
 
-                crate::command::check_metadata(self.metadata(), &mut ctxt.cx).await?;
+                let metadata = self.metadata();
+                ctxt.image_resolve = metadata.image_resolve;
+
+                // the same as for raw messages, but with the usage given as slash command options
+                #[allow(unused_variables)]
+                let argument_error = |index: usize, name: &'static str, error| {
+                    let arguments: Vec<crate::command::ArgumentInfo> = vec![#(#argument_info_exprs),*];
+                    crate::command::ExecutionError::Argument(crate::command::errors::ArgumentError {
+                        index,
+                        name,
+                        usage: arguments.iter().map(crate::command::ArgumentInfo::interaction_usage).collect(),
+                        subcommand: None,
+                        error,
+                    })
+                };
 
-                #(
-                    let #parse_idents = #interaction_parse_exprs.map_err(crate::command::ExecutionError::Parse)?;
-                )*
+                let (#(#parse_idents,)*) = crate::command::check_then_parse(
+                    &mut ctxt,
+                    |ctxt| Box::pin(crate::command::check_metadata(metadata, &mut ctxt.cx)),
+                    |#[allow(unused_variables)] ctxt| Box::pin(async move {
+                        #(
+                            let #parse_idents = #interaction_parse_exprs.map_err(|e| argument_error(#parse_indices, #parse_names, e))?;
+                        )*
+                        Ok::<_, crate::command::ExecutionError>((#(#parse_idents,)*))
+                    }),
+                ).await?;
 
                 #fn_name(ctxt.cx, #(#parse_idents),*).await.map_err(crate::command::ExecutionError::Command)
             }